@@ -16,6 +16,8 @@ const UP: u32 = 2;
 const DOWN: u32 = 3;
 const EAST: u32 = 4;
 const WEST: u32 = 5;
+// Both quads of plants, which are lit as if they faced upwards
+const CROSS: u32 = 6;
 
 const TEXTURE_IDX_STONE: u32 = 0;
 const TEXTURE_IDX_DIRT: u32 = 1;
@@ -97,6 +99,9 @@ fn get_uv(world_position: vec3<f32>, normal_id: u32) -> vec2<f32> {
         case WEST: {
             return fract(vec2(world_position.x, -world_position.y));
         }
+        case CROSS: {
+            return fract(vec2(world_position.z, -world_position.y));
+        }
         default: {
             return vec2(0., 0.);
         }
@@ -123,6 +128,9 @@ fn get_world_normal(normal_id: u32) -> vec3<f32> {
         case WEST: {
             return vec3(0., 0., -1.);
         }
+        case CROSS: {
+            return vec3(0., 1., 0.);
+        }
         default: {
             return vec3(1., 0., 0.);
         }
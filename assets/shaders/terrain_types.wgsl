@@ -18,6 +18,8 @@ const UP: u32 = 2;
 const DOWN: u32 = 3;
 const EAST: u32 = 4;
 const WEST: u32 = 5;
+// Both quads of plants, which are lit as if they faced upwards
+const CROSS: u32 = 6;
 
 const TEXTURE_IDX_STONE: u32 = 0;
 const TEXTURE_IDX_DIRT: u32 = 1;
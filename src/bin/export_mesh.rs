@@ -0,0 +1,132 @@
+//! Exports the terrain meshes of a box of chunks to a glTF or OBJ file without opening a window.
+//!
+//! Usage: export_mesh <world directory> <min x y z> <max x y z> <output> [--generate]
+//!
+//! The format is chosen by the output's extension, .gltf or .obj. Coordinates are chunk positions
+//! and both corners are included. Chunks must have been saved, or be generated first with
+//! `--generate`. Faces on the boundary of the box are kept, so the result is a closed surface.
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+use voxel_engine::{
+    chunk::data::Blocks,
+    render::{export::ExportedMesh, mesh::chunk_mesh},
+    world::{neighborhood::Neighborhood, pregenerate, save::ChunkFormat},
+};
+
+const USAGE: &str =
+    "Usage: export_mesh <world directory> <min x y z> <max x y z> <output.gltf|output.obj> [--generate]";
+
+fn main() -> ExitCode {
+    let mut args = env::args()
+        .skip(1)
+        .collect::<Vec<_>>();
+    let generate = args.len() == 9 && args.pop().is_some_and(|flag| flag == "--generate");
+    if args.len() != 8 {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let world = PathBuf::from(&args[0]);
+    let output = PathBuf::from(&args[7]);
+    let (Some(a), Some(b)) = (parse_position(&args[1..4]), parse_position(&args[4..7])) else {
+        eprintln!("Chunk coordinates must be integers\n{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let (min, max) = (a.min(b), a.max(b));
+    if generate && pregenerate::run(world.clone(), min, max).is_error() {
+        return ExitCode::FAILURE;
+    }
+    let mesh = match build_mesh(&world, min, max) {
+        Ok(mesh) => mesh,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = match output
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("gltf") => mesh.write_gltf(&output),
+        Some("obj") => mesh.write_obj(&output),
+        _ => {
+            eprintln!("The output must end in .gltf or .obj\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => {
+            println!(
+                "Exported {} triangles to {}",
+                mesh.triangle_count(),
+                output.display()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_position(coords: &[String]) -> Option<IVec3> {
+    let [x, y, z] = coords else {
+        return None;
+    };
+    Some(IVec3::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?))
+}
+
+fn build_mesh(world: &Path, min: IVec3, max: IVec3) -> std::io::Result<ExportedMesh> {
+    let format = ChunkFormat::of_world(world)?;
+    let mut chunks = HashMap::new();
+    let mut missing = 0;
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                let pos = IVec3::new(x, y, z);
+                match format.read(world, pos)? {
+                    Some(saved) => {
+                        chunks.insert(pos, Arc::new(saved.blocks));
+                    }
+                    None => missing += 1,
+                }
+            }
+        }
+    }
+    if missing > 0 {
+        eprintln!("{missing} chunks haven't been saved and were left out, see --generate");
+    }
+    // Sorted so that exporting the same chunks always gives the same file
+    let mut positions = chunks
+        .keys()
+        .copied()
+        .collect::<Vec<_>>();
+    positions.sort_by_key(|pos| pos.to_array());
+    let mut mesh = ExportedMesh::default();
+    for pos in positions {
+        if !chunks[&pos].is_meshable() {
+            continue;
+        }
+        let mut neighborhood = Neighborhood::<Blocks>::default();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor = chunks
+                        .get(&(pos + IVec3::new(dx, dy, dz)))
+                        .cloned();
+                    *neighborhood.get_chunk_mut(dx, dy, dz) = neighbor;
+                }
+            }
+        }
+        if let Some(chunk_mesh) = chunk_mesh(neighborhood) {
+            mesh.add_chunk(pos, &chunk_mesh);
+        }
+    }
+    Ok(mesh)
+}
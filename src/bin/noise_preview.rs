@@ -0,0 +1,148 @@
+//! Renders maps of the terrain noise to PNG images without generating any chunks or opening a
+//! window.
+//!
+//! Usage: noise_preview <seed> <min x z> <max x z> <output directory> [options]
+//!
+//! Coordinates are block positions and both corners are included. One image is written for the
+//! continent noise, the land height, the biomes and each cave slice.
+
+use bevy::prelude::*;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use voxel_engine::world::{
+    config::{self, WorldgenConfig},
+    preset::WorldPreset,
+    preview::{NoisePreview, PreviewArea, PreviewLayer},
+    seed,
+};
+
+const USAGE: &str = "\
+Usage: noise_preview <seed> <min x z> <max x z> <output directory> [options]
+
+Options:
+    --preset <preset>            Preview a preset such as amplified, default is the usual terrain
+    --config <worldgen.ron>      Use a worldgen config, such as the one in a world's directory
+    --cave-y <y>                 Add a slice of the caves at this height, may be repeated
+    --blocks-per-pixel <n>       Only draw every nth column, default 1";
+
+/// Sliced when no `--cave-y` is given, deep enough for the caves to be fully open
+const DEFAULT_CAVE_Y: i32 = -64;
+
+struct Options {
+    preset: WorldPreset,
+    config: WorldgenConfig,
+    cave_ys: Vec<i32>,
+    blocks_per_pixel: i32,
+}
+
+fn main() -> ExitCode {
+    let args = env::args()
+        .skip(1)
+        .collect::<Vec<_>>();
+    if args.len() < 6 {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let seed = seed::seed_from_text(&args[0]);
+    let output = PathBuf::from(&args[5]);
+    let result = parse_column(&args[1..3]).and_then(|a| {
+        let b = parse_column(&args[3..5])?;
+        let options = parse_options(&args[6..])?;
+        render(seed, a.min(b), a.max(b), &output, options)
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}\n{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_column(coords: &[String]) -> io::Result<IVec2> {
+    let [x, z] = coords else {
+        return Err(invalid_input("Expected two coordinates".into()));
+    };
+    match (x.parse(), z.parse()) {
+        (Ok(x), Ok(z)) => Ok(IVec2::new(x, z)),
+        _ => Err(invalid_input(format!(
+            "Expected two integer coordinates, got {x} {z}"
+        ))),
+    }
+}
+
+fn parse_options(args: &[String]) -> io::Result<Options> {
+    let mut options = Options {
+        preset: WorldPreset::default(),
+        config: WorldgenConfig::default(),
+        cave_ys: Vec::new(),
+        blocks_per_pixel: 1,
+    };
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let Some(value) = args.next() else {
+            return Err(invalid_input(format!("{flag} needs a value")));
+        };
+        match flag.as_str() {
+            "--preset" => options.preset = WorldPreset::from_text(value).map_err(invalid_input)?,
+            "--config" => options.config = config::read_file(Path::new(value))?,
+            "--cave-y" => options
+                .cave_ys
+                .push(parse_number(flag, value)?),
+            "--blocks-per-pixel" => {
+                options.blocks_per_pixel = parse_number(flag, value)?;
+                if options.blocks_per_pixel < 1 {
+                    return Err(invalid_input(format!("{flag} must be at least 1")));
+                }
+            }
+            _ => return Err(invalid_input(format!("Unknown option {flag}"))),
+        }
+    }
+    if options.cave_ys.is_empty() {
+        options.cave_ys.push(DEFAULT_CAVE_Y);
+    }
+    Ok(options)
+}
+
+fn parse_number(flag: &str, value: &str) -> io::Result<i32> {
+    value
+        .parse()
+        .map_err(|_| invalid_input(format!("{flag} must be an integer, got {value}")))
+}
+
+fn render(seed: u32, min: IVec2, max: IVec2, output: &Path, options: Options) -> io::Result<()> {
+    let area = PreviewArea {
+        min,
+        max,
+        blocks_per_pixel: options.blocks_per_pixel,
+    };
+    let preview = NoisePreview::new(seed, options.preset, options.config);
+    let layers = [
+        PreviewLayer::Continent,
+        PreviewLayer::Height,
+        PreviewLayer::Biome,
+    ]
+    .into_iter()
+    .chain(
+        options
+            .cave_ys
+            .into_iter()
+            .map(|y| PreviewLayer::Caves { y }),
+    );
+    fs::create_dir_all(output)?;
+    for layer in layers {
+        let path = output.join(format!("{}.png", layer.name()));
+        preview
+            .render(layer, &area)
+            .write_png(&path)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
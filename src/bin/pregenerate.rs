@@ -0,0 +1,102 @@
+//! Generates a box of chunks and saves them to a world without opening a window.
+//!
+//! Usage: pregenerate <world directory> <min x y z> <max x y z> [seed [preset]]
+//!
+//! The world is created if it doesn't exist yet, using the seed and preset if they're given. The
+//! preset is a name such as `amplified` or `superflat:bedrock,3*stone,grass`, and an empty seed
+//! picks one at random. Coordinates are chunk positions and both corners are included.
+
+use bevy::prelude::*;
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+};
+use voxel_engine::world::{
+    pregenerate,
+    preset::WorldPreset,
+    save::{
+        metadata::{self, WorldMetadata},
+        ChunkFormat,
+    },
+    seed,
+};
+
+const USAGE: &str =
+    "Usage: pregenerate <world directory> <min x y z> <max x y z> [seed [preset]]";
+
+fn main() -> AppExit {
+    let args = env::args()
+        .skip(1)
+        .collect::<Vec<_>>();
+    if !(7..=9).contains(&args.len()) {
+        eprintln!("{USAGE}");
+        return AppExit::error();
+    }
+    let directory = PathBuf::from(&args[0]);
+    let (Some(min), Some(max)) = (parse_position(&args[1..4]), parse_position(&args[4..7])) else {
+        eprintln!("Chunk coordinates must be integers\n{USAGE}");
+        return AppExit::error();
+    };
+    if let Err(e) = prepare_world(&directory, args.get(7), args.get(8)) {
+        eprintln!("Error: {e}");
+        return AppExit::error();
+    }
+    pregenerate::run(directory, min, max)
+}
+
+fn parse_position(coords: &[String]) -> Option<IVec3> {
+    let [x, y, z] = coords else {
+        return None;
+    };
+    Some(IVec3::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?))
+}
+
+/// Creates the world with the given seed and preset if it doesn't exist, or checks that they
+/// match
+fn prepare_world(
+    directory: &Path,
+    seed_text: Option<&String>,
+    preset_text: Option<&String>,
+) -> io::Result<()> {
+    let seed = seed_text.map(|text| seed::seed_from_text(text));
+    let preset = preset_text
+        .map(|text| WorldPreset::from_text(text))
+        .transpose()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    match metadata::read(directory)? {
+        Some(metadata) => {
+            if seed.is_some_and(|seed| seed != metadata.seed) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("The world already has the seed {}", metadata.seed),
+                ));
+            }
+            if preset.is_some_and(|preset| preset != metadata.preset) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("The world already has the {} preset", metadata.preset.name()),
+                ));
+            }
+            println!(
+                "Using the seed {} and the {} preset",
+                metadata.seed,
+                metadata.preset.name()
+            );
+        }
+        None => {
+            let seed = seed.unwrap_or_else(|| seed::seed_from_text(""));
+            let world = WorldMetadata::new(
+                seed,
+                preset.unwrap_or_default(),
+                ChunkFormat::detect(directory),
+            );
+            metadata::write(directory, &world)?;
+            println!(
+                "Created a world with the seed {} and the {} preset",
+                world.seed,
+                world.preset.name()
+            );
+        }
+    }
+    Ok(())
+}
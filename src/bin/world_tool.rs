@@ -0,0 +1,270 @@
+//! Inspects and converts saved worlds without starting the game.
+//!
+//! Run `cargo run --bin world_tool` for usage.
+
+use bevy::prelude::*;
+use std::{
+    collections::BTreeMap,
+    env, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use voxel_engine::{
+    block::Block,
+    chunk::position::ChunkPosition,
+    world::{
+        config,
+        save::{
+            chunk_file,
+            metadata::{self, WorldMetadata},
+            region_file, ChunkFormat, SavedChunk,
+        },
+    },
+};
+
+const USAGE: &str = "\
+Usage: world_tool <command> <world directory> [arguments]
+
+Commands:
+    list <world>                         List every saved chunk
+    histogram <world>                    Count the blocks in the whole world
+    histogram <world> --chunk <x y z>    Count the blocks in one chunk
+    histogram <world> --region <x y z>   Count the blocks in one region
+    dump <world> <x y z>                 Print a chunk as JSON
+    convert <world> <json|region> <out>  Copy the world to <out> in another chunk format
+    verify <world>                       Check that every chunk and the metadata can be read";
+
+fn main() -> ExitCode {
+    let args = env::args()
+        .skip(1)
+        .collect::<Vec<_>>();
+    let args = args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let result = match args.as_slice() {
+        ["list", world] => list(Path::new(world)),
+        ["histogram", world] => histogram(Path::new(world), Area::World),
+        ["histogram", world, "--chunk", x, y, z] => parse_position([x, y, z])
+            .and_then(|pos| histogram(Path::new(world), Area::Chunk(pos))),
+        ["histogram", world, "--region", x, y, z] => parse_position([x, y, z])
+            .and_then(|pos| histogram(Path::new(world), Area::Region(pos))),
+        ["dump", world, x, y, z] => {
+            parse_position([x, y, z]).and_then(|pos| dump(Path::new(world), pos))
+        }
+        ["convert", world, format, output] => parse_format(format)
+            .and_then(|format| convert(Path::new(world), format, &PathBuf::from(output))),
+        ["verify", world] => verify(Path::new(world)),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_position(coords: [&&str; 3]) -> io::Result<IVec3> {
+    let [x, y, z] = coords.map(|n| n.parse::<i32>());
+    match (x, y, z) {
+        (Ok(x), Ok(y), Ok(z)) => Ok(IVec3::new(x, y, z)),
+        _ => Err(invalid_input(format!(
+            "Expected three integer coordinates, got {} {} {}",
+            coords[0], coords[1], coords[2]
+        ))),
+    }
+}
+
+fn parse_format(format: &str) -> io::Result<ChunkFormat> {
+    match format {
+        "json" => Ok(ChunkFormat::Json),
+        "region" => Ok(ChunkFormat::Region),
+        _ => Err(invalid_input(format!(
+            "Unknown chunk format {format}, expected json or region"
+        ))),
+    }
+}
+
+fn invalid_input(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+fn open_world(world: &Path) -> io::Result<ChunkFormat> {
+    if !world.is_dir() {
+        return Err(invalid_input(format!(
+            "{} is not a world directory",
+            world.display()
+        )));
+    }
+    // Damaged metadata is reported by verify, and shouldn't stop the chunks from being read
+    let format = ChunkFormat::of_world(world).unwrap_or_else(|_| ChunkFormat::detect(world));
+    if format.has_other_chunks(world) {
+        eprintln!(
+            "Warning: chunks are saved in more than one format, only the {format:?} chunks are used"
+        );
+    }
+    Ok(format)
+}
+
+/// Chunk positions in a stable order, so that output can be compared between runs
+fn sorted_chunks(format: ChunkFormat, world: &Path) -> io::Result<Vec<ChunkPosition>> {
+    let mut chunks = format.list_chunks(world)?;
+    chunks.sort_by_key(|pos| pos.to_array());
+    Ok(chunks
+        .into_iter()
+        .map(ChunkPosition)
+        .collect())
+}
+
+fn read_chunk(format: ChunkFormat, world: &Path, pos: ChunkPosition) -> io::Result<SavedChunk> {
+    format
+        .read(world, pos.0)?
+        .ok_or_else(|| invalid_input(format!("Chunk {} has not been saved", pos.0)))
+}
+
+fn list(world: &Path) -> io::Result<()> {
+    let format = open_world(world)?;
+    let chunks = sorted_chunks(format, world)?;
+    println!("Format: {format:?}");
+    if let Some(metadata) = metadata::read(world)? {
+        println!("Seed: {}", metadata.seed);
+    }
+    println!("Chunks: {}", chunks.len());
+    for pos in chunks.iter() {
+        let IVec3 { x, y, z } = pos.0;
+        println!("{x} {y} {z}");
+    }
+    Ok(())
+}
+
+enum Area {
+    World,
+    Chunk(IVec3),
+    Region(IVec3),
+}
+
+fn histogram(world: &Path, area: Area) -> io::Result<()> {
+    let format = open_world(world)?;
+    let chunks = match area {
+        Area::World => sorted_chunks(format, world)?,
+        Area::Chunk(pos) => vec![ChunkPosition(pos)],
+        Area::Region(region_pos) => sorted_chunks(format, world)?
+            .into_iter()
+            .filter(|pos| region_file::region_position(pos.0) == region_pos)
+            .collect(),
+    };
+    let mut counts: BTreeMap<Block, usize> = BTreeMap::new();
+    for pos in chunks.iter() {
+        let chunk = read_chunk(format, world, *pos)?;
+        for block in chunk.blocks.0.iter() {
+            *counts.entry(*block).or_default() += 1;
+        }
+    }
+    let total = counts
+        .values()
+        .sum::<usize>();
+    let id_width = counts
+        .keys()
+        .map(|block| block.id().len())
+        .max()
+        .unwrap_or(0);
+    println!("Chunks: {}", chunks.len());
+    for (block, count) in counts.iter() {
+        let percentage = 100.0 * *count as f32 / total as f32;
+        println!("{:<id_width$} {count:>12} {percentage:>7.3}%", block.id());
+    }
+    Ok(())
+}
+
+fn dump(world: &Path, pos: IVec3) -> io::Result<()> {
+    let format = open_world(world)?;
+    let chunk = read_chunk(format, world, ChunkPosition(pos))?;
+    println!(
+        "{}",
+        chunk_file::to_pretty_json(&chunk.blocks, &chunk.stage)?
+    );
+    Ok(())
+}
+
+fn convert(world: &Path, target: ChunkFormat, output: &Path) -> io::Result<()> {
+    let format = open_world(world)?;
+    if output.exists() {
+        return Err(invalid_input(format!(
+            "{} already exists",
+            output.display()
+        )));
+    }
+    let chunks = sorted_chunks(format, world)?;
+    for (i, pos) in chunks.iter().enumerate() {
+        let chunk = read_chunk(format, world, *pos)?;
+        target.write(output, pos.0, &chunk.blocks, &chunk.stage)?;
+        if (i + 1) % 100 == 0 {
+            eprintln!("Converted {}/{} chunks", i + 1, chunks.len());
+        }
+    }
+    // Reading the metadata upgrades it to the current version as well
+    match metadata::read(world)? {
+        Some(metadata) => metadata::write(
+            output,
+            &WorldMetadata {
+                chunk_format: target,
+                ..metadata
+            },
+        )?,
+        None => std::fs::create_dir_all(output)?,
+    }
+    if let Some(config) = config::read(world)? {
+        config::write(output, &config)?;
+    }
+    println!(
+        "Converted {} chunks from {format:?} to {target:?}",
+        chunks.len()
+    );
+    Ok(())
+}
+
+fn verify(world: &Path) -> io::Result<()> {
+    let format = open_world(world)?;
+    let mut errors = 0;
+    let mut report = |what: String, e: io::Error| {
+        println!("{what}: {e}");
+        errors += 1;
+    };
+    if let Err(e) = metadata::read(world) {
+        report("World metadata".into(), e);
+    }
+    let mut chunks = vec![];
+    match format {
+        ChunkFormat::Json => chunks = chunk_file::list_chunks(world)?,
+        ChunkFormat::Region => {
+            for region_pos in region_file::list_regions(world)? {
+                match region_file::check_region(world, region_pos)
+                    .and_then(|_| region_file::list_chunks_in_region(world, region_pos))
+                {
+                    Ok(region_chunks) => chunks.extend(region_chunks),
+                    Err(e) => report(format!("Region {region_pos}"), e),
+                }
+            }
+        }
+    }
+    for pos in chunks.iter() {
+        match format.read(world, *pos) {
+            Ok(Some(_)) => {}
+            Ok(None) => report(
+                format!("Chunk {pos}"),
+                invalid_input("Listed but could not be found"),
+            ),
+            Err(e) => report(format!("Chunk {pos}"), e),
+        }
+    }
+    println!("Checked {} chunks, found {errors} errors", chunks.len());
+    if errors > 0 {
+        return Err(io::Error::other("World is damaged"));
+    }
+    Ok(())
+}
@@ -1,9 +1,13 @@
 use bevy::prelude::*;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 pub const FLUID_DROP: f32 = -0.125;
 pub const SURFACE_HEIGHT: f32 = 1.0 + FLUID_DROP;
 
+/// The order of the variants is not stable, so save files must use `Block::id` or
+/// `Block::numeric_id` instead
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord, EnumIter)]
 pub enum Block {
     #[default]
@@ -16,6 +20,18 @@ pub enum Block {
     Leaves,
     Water,
     Bedrock,
+    CoalOre,
+    IronOre,
+    GoldOre,
+    DiamondOre,
+    Gravel,
+    TallGrass,
+    Dandelion,
+    Poppy,
+    DeadBush,
+    RedMushroom,
+    BrownMushroom,
+    Cactus,
 }
 
 // Required for Block to work as a key in hashmap operations `entry_ref` + `or_insert_with`
@@ -26,6 +42,69 @@ impl From<&Block> for Block {
 }
 
 impl Block {
+    /// Identifies the block in save files. This must never change once a block has been added,
+    /// and the ID of a removed block must not be reused.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Air => "air",
+            Self::Stone => "stone",
+            Self::Dirt => "dirt",
+            Self::Grass => "grass",
+            Self::Sand => "sand",
+            Self::Wood => "wood",
+            Self::Leaves => "leaves",
+            Self::Water => "water",
+            Self::Bedrock => "bedrock",
+            Self::CoalOre => "coal_ore",
+            Self::IronOre => "iron_ore",
+            Self::GoldOre => "gold_ore",
+            Self::DiamondOre => "diamond_ore",
+            Self::Gravel => "gravel",
+            Self::TallGrass => "tall_grass",
+            Self::Dandelion => "dandelion",
+            Self::Poppy => "poppy",
+            Self::DeadBush => "dead_bush",
+            Self::RedMushroom => "red_mushroom",
+            Self::BrownMushroom => "brown_mushroom",
+            Self::Cactus => "cactus",
+        }
+    }
+
+    /// Compact equivalent of `Block::id`, with the same stability rules
+    pub fn numeric_id(&self) -> u16 {
+        match self {
+            Self::Air => 0,
+            Self::Stone => 1,
+            Self::Dirt => 2,
+            Self::Grass => 3,
+            Self::Sand => 4,
+            Self::Wood => 5,
+            Self::Leaves => 6,
+            Self::Water => 7,
+            Self::Bedrock => 8,
+            Self::CoalOre => 9,
+            Self::IronOre => 10,
+            Self::GoldOre => 11,
+            Self::DiamondOre => 12,
+            Self::Gravel => 13,
+            Self::TallGrass => 14,
+            Self::Dandelion => 15,
+            Self::Poppy => 16,
+            Self::DeadBush => 17,
+            Self::RedMushroom => 18,
+            Self::BrownMushroom => 19,
+            Self::Cactus => 20,
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::iter().find(|block| block.id() == id)
+    }
+
+    pub fn from_numeric_id(numeric_id: u16) -> Option<Self> {
+        Self::iter().find(|block| block.numeric_id() == numeric_id)
+    }
+
     // pub fn get_colour(&self) -> Color {
     //     match self {
     //         Self::Grass => Color::linear_rgb(0.2, 0.6, 0.0),
@@ -45,10 +124,23 @@ impl Block {
     pub fn is_solid(&self) -> bool {
         match self {
             Self::Air => false,
-            _ => true,
+            _ => !self.is_plant(),
         }
     }
 
+    /// Plants are drawn as two crossed quads rather than as a cube, and can be walked through
+    pub fn is_plant(&self) -> bool {
+        matches!(
+            self,
+            Self::TallGrass
+                | Self::Dandelion
+                | Self::Poppy
+                | Self::DeadBush
+                | Self::RedMushroom
+                | Self::BrownMushroom
+        )
+    }
+
     pub fn is_translucent(&self) -> bool {
         match self {
             Self::Water => true,
@@ -57,6 +149,19 @@ impl Block {
     }
 }
 
+impl Serialize for Block {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for Block {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Self::from_id(&id).ok_or_else(|| de::Error::custom(format!("Unknown block ID \"{id}\"")))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Default)]
 pub enum BlockSide {
     #[default]
@@ -1,5 +1,5 @@
 use super::{spatial::SpatiallyMapped, CHUNK_LENGTH, CHUNK_SIZE_I32};
-use crate::{block::Block, define_spatial};
+use crate::{block::Block, define_spatial, world::biome::Biome};
 use bevy::prelude::*;
 use noise::NoiseFn;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
@@ -28,6 +28,9 @@ define_spatial!(HeightNoise, 2, f32);
 define_spatial!(CaveNetworkNoise, 3, f32);
 define_spatial!(TemperatureNoise, 2, f32);
 define_spatial!(HumidityNoise, 2, f32);
+define_spatial!(BiomeMap, 2, Biome);
+// Multiplies the height of the land, see `biome::blended_height_scale`
+define_spatial!(HeightScale, 2, f32);
 
 pub trait FromNoise<const DIM: usize> {
     fn from_noise<Noise>(noise: Noise, chunk_pos: IVec3) -> Self
@@ -7,6 +7,7 @@ use bevy::{
     prelude::*,
 };
 use iyes_perf_ui::prelude::*;
+use perf_ui_biome::PerfUiBiome;
 use perf_ui_camera_block::PerfUiCameraBlock;
 use perf_ui_camera_facing::PerfUiCameraFacing;
 use perf_ui_camera_pos::PerfUiCameraPosition;
@@ -14,6 +15,7 @@ use perf_ui_targeted_block::PerfUiTargetedBlock;
 
 mod chunk_border;
 mod hitbox_frame;
+mod perf_ui_biome;
 mod perf_ui_camera_block;
 mod perf_ui_camera_facing;
 mod perf_ui_camera_pos;
@@ -35,6 +37,7 @@ impl Plugin for DebugPlugin {
         .add_perf_ui_simple_entry::<PerfUiCameraFacing>()
         .add_perf_ui_simple_entry::<PerfUiCameraBlock>()
         .add_perf_ui_simple_entry::<PerfUiTargetedBlock>()
+        .add_perf_ui_simple_entry::<PerfUiBiome>()
         .init_resource::<DebugUiIsVisible>()
         .add_systems(Startup, (setup, toggle_debug_ui).chain())
         .add_systems(
@@ -51,6 +54,7 @@ fn setup(mut commands: Commands) {
         PerfUiCameraFacing::default(),
         PerfUiCameraBlock::default(),
         PerfUiTargetedBlock::default(),
+        PerfUiBiome::default(),
         DebugUi,
     ));
 }
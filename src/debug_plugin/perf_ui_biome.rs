@@ -0,0 +1,57 @@
+use bevy::{
+    ecs::system::lifetimeless::{SQuery, SRes},
+    prelude::*,
+};
+use iyes_perf_ui::entry::PerfUiEntry;
+
+use crate::{
+    chunk::{data::BiomeMap, position::ChunkPosition, spatial::SpatiallyMapped, CHUNK_SIZE_I32},
+    player::Player,
+    world::{biome::Biome, index::ChunkIndex},
+};
+
+#[derive(Component)]
+pub struct PerfUiBiome {
+    sort_key: i32,
+}
+
+impl Default for PerfUiBiome {
+    fn default() -> Self {
+        Self {
+            sort_key: iyes_perf_ui::utils::next_sort_key(),
+        }
+    }
+}
+
+impl PerfUiEntry for PerfUiBiome {
+    type Value = Biome;
+    type SystemParam = (
+        SQuery<&'static Transform, (With<Camera3d>, With<Player>)>,
+        SRes<ChunkIndex>,
+        SQuery<&'static BiomeMap>,
+    );
+
+    fn label(&self) -> &str {
+        "Biome"
+    }
+
+    fn sort_key(&self) -> i32 {
+        self.sort_key
+    }
+
+    fn update_value(
+        &self,
+        (q_camera_pos, chunk_index, q_biome_map): &mut <Self::SystemParam as bevy::ecs::system::SystemParam>::Item<'_, '_>,
+    ) -> Option<Self::Value> {
+        let camera_pos = q_camera_pos.single().ok()?.translation;
+        let chunk_pos = ChunkPosition::from_world_position(&camera_pos);
+        let entity = chunk_index.entity_by_pos.get(&chunk_pos.0)?;
+        let biome_map = q_biome_map.get(*entity).ok()?;
+        let local_pos = camera_pos.floor().as_ivec3() - chunk_pos.0 * CHUNK_SIZE_I32;
+        Some(*biome_map.at_pos([local_pos.x as usize, local_pos.z as usize]))
+    }
+
+    fn format_value(&self, value: &Self::Value) -> String {
+        format!("{:?}", value)
+    }
+}
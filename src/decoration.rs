@@ -0,0 +1,215 @@
+use bevy::{
+    ecs::{query::QueryItem, system::SystemParamItem},
+    prelude::*,
+};
+use std::sync::Arc;
+
+use crate::{
+    block::Block,
+    chunk::{
+        data::{BiomeMap, Blocks},
+        spatial::SpatiallyMapped,
+        CHUNK_SIZE, CHUNK_SIZE_I32,
+    },
+    utils::{mix_hash, nth_random},
+    world::{
+        biome::Biome,
+        neighborhood::Neighborhood,
+        pass::{PassOutput, WorldgenPass},
+        preset::WorldPreset,
+        seed::WorldSeed,
+        stage::Stage,
+    },
+};
+
+/// Where and how often a plant is placed
+#[derive(Clone)]
+pub struct DecorationRule {
+    pub block: Block,
+    /// The chance of each block of ground getting the plant, by biome. Biomes which aren't listed
+    /// don't get the plant.
+    pub densities: Vec<(Biome, f32)>,
+    /// Blocks which the plant can be placed on
+    pub ground: Vec<Block>,
+}
+
+impl DecorationRule {
+    fn density(&self, biome: Biome, ground: &Block) -> f32 {
+        if !self.ground.contains(ground) {
+            return 0.0;
+        }
+        self.densities
+            .iter()
+            .find(|(b, _)| *b == biome)
+            .map_or(0.0, |(_, density)| *density)
+    }
+}
+
+/// Every plant which worldgen places. Each block of ground gets at most one plant, so the
+/// densities of the rules which share a biome and ground block should add up to less than 1.
+#[derive(Resource, Clone)]
+pub struct DecorationRegistry(pub Arc<Vec<DecorationRule>>);
+
+impl Default for DecorationRegistry {
+    fn default() -> Self {
+        use Biome::*;
+        let rules = vec![
+            DecorationRule {
+                block: Block::TallGrass,
+                densities: vec![
+                    (Plains, 0.3),
+                    (Savanna, 0.25),
+                    (Forest, 0.15),
+                    (Taiga, 0.08),
+                    (Tundra, 0.02),
+                ],
+                ground: vec![Block::Grass],
+            },
+            DecorationRule {
+                block: Block::Dandelion,
+                densities: vec![(Plains, 0.02), (Forest, 0.01)],
+                ground: vec![Block::Grass],
+            },
+            DecorationRule {
+                block: Block::Poppy,
+                densities: vec![(Plains, 0.02), (Forest, 0.01)],
+                ground: vec![Block::Grass],
+            },
+            DecorationRule {
+                block: Block::DeadBush,
+                densities: vec![(Desert, 0.02), (Savanna, 0.01), (Tundra, 0.01)],
+                ground: vec![Block::Sand, Block::Grass, Block::Dirt],
+            },
+            DecorationRule {
+                block: Block::RedMushroom,
+                densities: vec![(Taiga, 0.01), (Forest, 0.005)],
+                ground: vec![Block::Grass, Block::Dirt],
+            },
+            DecorationRule {
+                block: Block::BrownMushroom,
+                densities: vec![(Taiga, 0.015), (Forest, 0.01)],
+                ground: vec![Block::Grass, Block::Dirt],
+            },
+        ];
+        Self(Arc::new(rules))
+    }
+}
+
+impl DecorationRegistry {
+    /// Plants on every block of ground in a chunk which has air above it, in the chunk's
+    /// coordinates. Plants on the top layer of the chunk below are placed in this chunk's bottom
+    /// layer, so the blocks of the chunk below are needed for those.
+    pub fn get_decoration_blocks(
+        &self,
+        seed: u32,
+        chunk_pos: IVec3,
+        blocks: &Blocks,
+        blocks_below: Option<&Blocks>,
+        biome_map: &BiomeMap,
+    ) -> Vec<(Block, [usize; 3])> {
+        let mut decorations = vec![];
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let biome = *biome_map.at_pos([x, z]);
+                for y in 0..CHUNK_SIZE {
+                    if blocks.at_pos([x, y, z]) != &Block::Air {
+                        continue;
+                    }
+                    let ground = match y {
+                        0 => blocks_below.map(|below| below.at_pos([x, CHUNK_SIZE - 1, z])),
+                        _ => Some(blocks.at_pos([x, y - 1, z])),
+                    };
+                    let Some(ground) = ground else {
+                        continue;
+                    };
+                    let world_pos =
+                        chunk_pos * CHUNK_SIZE_I32 + IVec3::new(x as i32, y as i32, z as i32);
+                    if let Some(block) = self.pick(biome, ground, block_hash(seed, world_pos)) {
+                        decorations.push((block, [x, y, z]));
+                    }
+                }
+            }
+        }
+        decorations
+    }
+
+    /// Picks at most one plant for a block of ground, with each rule's density as its chance
+    fn pick(&self, biome: Biome, ground: &Block, hash: u32) -> Option<Block> {
+        let chance = (nth_random(hash, 0) % 10000) as f32 / 10000.0;
+        let mut total = 0.0;
+        self.0.iter().find_map(|rule| {
+            total += rule.density(biome, ground);
+            (chance < total).then_some(rule.block)
+        })
+    }
+}
+
+/// Scatters plants over the ground once structures have been placed, so that plants aren't
+/// placed inside them. Presets without features have no plants.
+pub struct DecorationPass;
+
+impl WorldgenPass for DecorationPass {
+    type Param = (
+        Res<'static, DecorationRegistry>,
+        Res<'static, WorldSeed>,
+        Res<'static, WorldPreset>,
+    );
+    type ChunkData = (
+        &'static Blocks,
+        &'static Neighborhood<Blocks>,
+        &'static BiomeMap,
+    );
+    type Input = (
+        DecorationRegistry,
+        u32,
+        bool,
+        Blocks,
+        Option<Arc<Blocks>>,
+        BiomeMap,
+    );
+
+    const INPUT_STAGE: Option<Stage> = Some(Stage::Structures);
+    const OUTPUT_STAGE: Stage = Stage::Decorations;
+    // Structures in the top layer of the chunk below can cover the ground which plants in this
+    // chunk's bottom layer would stand on, so they have to be placed first. Waiting for just that
+    // chunk rather than every neighbour keeps the area of finished chunks from shrinking further.
+    const NEIGHBORHOOD_RADIUS: i32 = 0;
+    const REQUIRED_NEIGHBORS: &'static [IVec3] = &[IVec3::NEG_Y];
+
+    fn prepare(
+        (registry, seed, preset): &SystemParamItem<Self::Param>,
+        (blocks, blocks_neighborhood, biome_map): QueryItem<Self::ChunkData>,
+    ) -> Self::Input {
+        (
+            (*registry).clone(),
+            seed.0,
+            preset.has_features(),
+            blocks.clone(),
+            blocks_neighborhood.get_chunk(0, -1, 0).clone(),
+            biome_map.clone(),
+        )
+    }
+
+    fn run(
+        (registry, seed, has_features, blocks, blocks_below, biome_map): Self::Input,
+        chunk_pos: IVec3,
+    ) -> PassOutput {
+        if !has_features {
+            return PassOutput::BlockUpdates(vec![]);
+        }
+        PassOutput::BlockUpdates(registry.get_decoration_blocks(
+            seed,
+            chunk_pos,
+            &blocks,
+            blocks_below.as_deref(),
+            &biome_map,
+        ))
+    }
+}
+
+fn block_hash(seed: u32, pos: IVec3) -> u32 {
+    let hash = mix_hash(seed ^ 0x4445_4300);
+    let hash = mix_hash(hash ^ pos.x as u32);
+    let hash = mix_hash(hash.wrapping_add(pos.y as u32));
+    mix_hash(hash ^ mix_hash(pos.z as u32))
+}
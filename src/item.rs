@@ -4,6 +4,7 @@ use std::{
 };
 
 use bevy::{prelude::*, render::view::RenderLayers};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     age::{Age, Lifespan},
@@ -25,7 +26,7 @@ impl Plugin for ItemPlugin {
     }
 }
 
-#[derive(Component, Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Item {
     Block(Block),
 }
@@ -33,7 +34,7 @@ pub enum Item {
 pub const STACK_LIMIT: u8 = 100;
 pub const SECONDS_BEFORE_ELIGIBLE_FOR_PICKUP: f32 = 2.0;
 
-#[derive(Component, Clone, Copy, Debug, Eq, PartialEq, Default)]
+#[derive(Component, Clone, Copy, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct Quantity(pub u8);
 
 impl Into<String> for Quantity {
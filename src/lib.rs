@@ -0,0 +1,31 @@
+#![feature(let_chains)]
+#![feature(int_roundings)]
+#![feature(iter_map_windows)]
+#![feature(step_trait)]
+
+use bevy::prelude::*;
+use render_layer::WORLD_LAYER;
+
+pub mod age;
+pub mod block;
+pub mod camera_distance;
+pub mod chunk;
+pub mod debug_plugin;
+pub mod decoration;
+pub mod item;
+pub mod ore;
+pub mod physics;
+pub mod player;
+pub mod portal;
+pub mod render;
+pub mod render_layer;
+pub mod schematic;
+pub mod state;
+pub mod structure;
+pub mod ui;
+pub mod utils;
+pub mod vox;
+pub mod world;
+
+pub const SKY_COLOUR: Color = Color::linear_rgb(0.25, 0.60, 0.92);
+pub const TICKS_PER_SECOND: u8 = 20;
@@ -1,8 +1,3 @@
-#![feature(let_chains)]
-#![feature(int_roundings)]
-#![feature(iter_map_windows)]
-#![feature(step_trait)]
-
 use bevy::{
     input::common_conditions::input_just_pressed,
     pbr::{
@@ -12,31 +7,19 @@ use bevy::{
     prelude::*,
     window::CursorGrabMode,
 };
-use player::{Player, PlayerCamera};
-use render_layer::WORLD_LAYER;
-use state::AppState;
-
-use crate::state::InGameState;
-
-mod age;
-mod block;
-mod camera_distance;
-mod chunk;
-mod debug_plugin;
-mod item;
-mod physics;
-mod player;
-mod portal;
-mod render;
-mod render_layer;
-mod state;
-mod structure;
-mod ui;
-mod utils;
-mod world;
-
-const SKY_COLOUR: Color = Color::linear_rgb(0.25, 0.60, 0.92);
-const TICKS_PER_SECOND: u8 = 20;
+use voxel_engine::{
+    age, camera_distance, chunk, debug_plugin, item, physics,
+    player::{self, Player, PlayerCamera},
+    portal, render, schematic,
+    state::{AppState, InGameState},
+    ui, vox,
+    world::{
+        self,
+        save::{metadata::WorldMetadata, LoadWorldMetadata},
+        spawn::FindingSpawn,
+    },
+    SKY_COLOUR, TICKS_PER_SECOND,
+};
 
 fn main() {
     App::new()
@@ -63,11 +46,16 @@ fn main() {
             item::ItemPlugin,
             render::RenderPlugin,
             portal::PortalPlugin,
+            vox::VoxPlugin,
+            schematic::SchematicPlugin,
         ))
         .insert_state(AppState::Init)
         .add_sub_state::<InGameState>()
         .insert_resource(Time::<Fixed>::from_hz(TICKS_PER_SECOND as f64))
-        .add_systems(OnEnter(AppState::InGame), setup_game)
+        .add_systems(
+            OnEnter(AppState::InGame),
+            setup_game.after(LoadWorldMetadata),
+        )
         .add_systems(
             Update,
             (
@@ -90,16 +78,21 @@ fn setup_game(
     mut commands: Commands,
     mut windows: Query<&mut Window>,
     mut gizmos_config_store: ResMut<GizmoConfigStore>,
+    metadata: Res<WorldMetadata>,
 ) {
     let mut window = windows.single_mut().expect("Window component");
     window.cursor_options.visible = false;
     window.cursor_options.grab_mode = CursorGrabMode::Locked;
 
-    commands.spawn((
+    let mut player = commands.spawn((
         Player,
         PlayerCamera,
         Transform::from_xyz(0.0, 2.0, 0.0).looking_to(Vec3::X, Vec3::Y),
     ));
+    match &metadata.player {
+        Some(saved_player) => player.insert(saved_player.bundle()),
+        None => player.insert(FindingSpawn::default()),
+    };
     commands.spawn((
         DirectionalLight {
             color: Color::WHITE,
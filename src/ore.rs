@@ -0,0 +1,195 @@
+use bevy::{
+    ecs::{query::QueryItem, system::SystemParamItem},
+    prelude::*,
+};
+use std::sync::Arc;
+
+use crate::{
+    block::Block,
+    chunk::{data::Terrain, spatial::SpatiallyMapped, CHUNK_SIZE_I32},
+    utils::{mix_hash, nth_random, VolumetricRange},
+    world::{
+        pass::{PassOutput, WorldgenPass},
+        preset::WorldPreset,
+        seed::WorldSeed,
+        stage::Stage,
+    },
+};
+
+const DIRECTIONS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// Where and how often an ore is placed
+#[derive(Clone)]
+pub struct OreRule {
+    pub block: Block,
+    /// Veins start between these heights, inclusive
+    pub min_height: i32,
+    pub max_height: i32,
+    /// The number of steps of the random walk which makes a vein. Veins have at most this many
+    /// blocks and reach at most this far from where they start, so it must be less than
+    /// `CHUNK_SIZE`.
+    pub vein_size: u8,
+    /// The average number of veins which start in a chunk between the heights
+    pub veins_per_chunk: f32,
+}
+
+impl OreRule {
+    /// Blocks of one vein, in world coordinates
+    fn vein(&self, hash: u32) -> impl Iterator<Item = IVec3> + '_ {
+        let start = IVec3::new(
+            (nth_random(hash, 0) % CHUNK_SIZE_I32 as u32) as i32,
+            (nth_random(hash, 1) % CHUNK_SIZE_I32 as u32) as i32,
+            (nth_random(hash, 2) % CHUNK_SIZE_I32 as u32) as i32,
+        );
+        (0..self.vein_size as u32).scan(start, move |pos, step| {
+            let block = *pos;
+            *pos += DIRECTIONS[(nth_random(hash, 3 + step) % 6) as usize];
+            Some(block)
+        })
+    }
+}
+
+/// Every ore which worldgen places. Ores which come later replace ores which come first where
+/// their veins cross.
+#[derive(Resource, Clone)]
+pub struct OreRegistry(pub Arc<Vec<OreRule>>);
+
+impl Default for OreRegistry {
+    fn default() -> Self {
+        let rules = vec![
+            OreRule {
+                block: Block::CoalOre,
+                min_height: -80,
+                max_height: 64,
+                vein_size: 14,
+                veins_per_chunk: 16.0,
+            },
+            OreRule {
+                block: Block::IronOre,
+                min_height: -128,
+                max_height: 16,
+                vein_size: 9,
+                veins_per_chunk: 10.0,
+            },
+            OreRule {
+                block: Block::GoldOre,
+                min_height: -160,
+                max_height: -64,
+                vein_size: 8,
+                veins_per_chunk: 3.0,
+            },
+            OreRule {
+                block: Block::DiamondOre,
+                min_height: -160,
+                max_height: -120,
+                vein_size: 6,
+                veins_per_chunk: 1.5,
+            },
+        ];
+        Self(Arc::new(rules))
+    }
+}
+
+impl OreRegistry {
+    /// Ore blocks which replace stone in a chunk, in the chunk's coordinates. Veins can start in
+    /// any chunk next to this one, and each vein only depends on the seed and the position of the
+    /// chunk which it starts in, so veins continue across chunk borders.
+    pub fn get_ore_blocks(
+        &self,
+        seed: u32,
+        chunk_pos: IVec3,
+        terrain: &Terrain,
+    ) -> Vec<(Block, [usize; 3])> {
+        let mut blocks = vec![];
+        for (rule_index, rule) in self.0.iter().enumerate() {
+            for (x, y, z) in VolumetricRange::new(-1..2, -1..2, -1..2) {
+                let start_chunk = chunk_pos + IVec3::new(x, y, z);
+                let origin = start_chunk * CHUNK_SIZE_I32;
+                if origin.y > rule.max_height || origin.y + CHUNK_SIZE_I32 <= rule.min_height {
+                    continue;
+                }
+                let hash = chunk_hash(seed, rule_index, start_chunk);
+                for vein_index in 0..vein_count(rule, hash) {
+                    let vein_hash = nth_random(hash, vein_index + 1);
+                    let mut vein = rule
+                        .vein(vein_hash)
+                        .map(|pos| pos + origin)
+                        .peekable();
+                    let Some(start) = vein.peek() else {
+                        continue;
+                    };
+                    if start.y < rule.min_height || start.y > rule.max_height {
+                        continue;
+                    }
+                    for pos in vein {
+                        let pos = pos - chunk_pos * CHUNK_SIZE_I32;
+                        let inside = pos.cmpge(IVec3::ZERO).all()
+                            && pos.cmplt(IVec3::splat(CHUNK_SIZE_I32)).all();
+                        if !inside {
+                            continue;
+                        }
+                        let pos = pos.as_uvec3().to_array().map(|x| x as usize);
+                        if terrain.at_pos(pos) == &Block::Stone {
+                            blocks.push((rule.block, pos));
+                        }
+                    }
+                }
+            }
+        }
+        blocks
+    }
+}
+
+/// Replaces stone with ore veins. The veins only depend on the seed, so the pass doesn't need to
+/// wait for any other chunks. Presets without features have no ores.
+pub struct OrePass;
+
+impl WorldgenPass for OrePass {
+    type Param = (
+        Res<'static, OreRegistry>,
+        Res<'static, WorldSeed>,
+        Res<'static, WorldPreset>,
+    );
+    type ChunkData = &'static Terrain;
+    type Input = (OreRegistry, u32, bool, Terrain);
+
+    const INPUT_STAGE: Option<Stage> = Some(Stage::Sculpt);
+    const OUTPUT_STAGE: Stage = Stage::Ores;
+    const NEIGHBORHOOD_RADIUS: i32 = 0;
+
+    fn prepare(
+        (registry, seed, preset): &SystemParamItem<Self::Param>,
+        terrain: QueryItem<Self::ChunkData>,
+    ) -> Self::Input {
+        ((*registry).clone(), seed.0, preset.has_features(), terrain.clone())
+    }
+
+    fn run((registry, seed, has_features, terrain): Self::Input, chunk_pos: IVec3) -> PassOutput {
+        if !has_features {
+            return PassOutput::BlockUpdates(vec![]);
+        }
+        PassOutput::BlockUpdates(registry.get_ore_blocks(seed, chunk_pos, &terrain))
+    }
+}
+
+/// The number of veins which start in a chunk, rounding the average number randomly
+fn vein_count(rule: &OreRule, hash: u32) -> u32 {
+    let whole = rule.veins_per_chunk.floor();
+    let fraction = rule.veins_per_chunk - whole;
+    let chance = (nth_random(hash, 0) % 1000) as f32 / 1000.0;
+    whole as u32 + u32::from(chance < fraction)
+}
+
+fn chunk_hash(seed: u32, rule_index: usize, chunk_pos: IVec3) -> u32 {
+    let hash = mix_hash(seed ^ mix_hash(rule_index as u32 ^ 0x4F52_4500));
+    let hash = mix_hash(hash ^ chunk_pos.x as u32);
+    let hash = mix_hash(hash.wrapping_add(chunk_pos.y as u32));
+    mix_hash(hash ^ mix_hash(chunk_pos.z as u32))
+}
@@ -6,7 +6,7 @@ use crate::{
         velocity::Velocity,
     },
     render_layer::{PORTAL_LAYER, WORLD_LAYER},
-    world::neighborhood::ComponentIndex,
+    world::{neighborhood::ComponentIndex, render_distance::RenderDistance},
 };
 use bevy::{prelude::*, render::view::RenderLayers};
 use block_target::BlockTargetPlugin;
@@ -41,11 +41,13 @@ impl Plugin for PlayerPlugin {
 }
 
 const PLAYER_MAX_HEALTH: u32 = 20;
+/// Height of the camera above the player's feet
+pub const PLAYER_EYE_HEIGHT: f32 = 1.65;
 
 #[derive(Component)]
 #[require(
     Transform,
-    Aabb::square_prism(0.35, 1.8, 1.65),
+    Aabb::square_prism(0.35, 1.8, PLAYER_EYE_HEIGHT),
     Collidable,
     Gravity,
     Velocity,
@@ -83,7 +85,7 @@ pub struct Sneaking(pub bool);
     Msaa::Sample8,
     // TODO: Enable DepthPrepass once implemented for terrain material
     // DepthPrepass,
-    DistanceFog { ..air_distance_fog() },
+    DistanceFog { ..air_distance_fog(&RenderDistance::default()) },
     RenderLayers::from_layers(&[WORLD_LAYER, PORTAL_LAYER]),
     CameraBlock,
 )]
@@ -146,11 +148,12 @@ fn update_camera_block(
     }
 }
 
-fn air_distance_fog() -> DistanceFog {
+/// Fades out the terrain before the edge of the loaded chunks
+fn air_distance_fog(render_distance: &RenderDistance) -> DistanceFog {
     DistanceFog {
         color: Color::WHITE,
         falloff: FogFalloff::from_visibility_colors(
-            CHUNK_SIZE as f32 * 5.0, // distance in world units up to which objects retain visibility (>= 5% contrast)
+            CHUNK_SIZE as f32 * render_distance.horizontal as f32, // distance in world units up to which objects retain visibility (>= 5% contrast)
             Color::WHITE, // atmospheric extinction color (after light is lost due to absorption by atmospheric particles)
             Color::linear_rgba(0.8, 0.8, 0.92, 0.3), //SKY_COLOUR.with_alpha(0.5), // atmospheric inscattering color (light gained due to scattering from the sun)
         ),
@@ -171,12 +174,16 @@ fn water_distance_fog() -> DistanceFog {
 }
 
 fn update_distance_fog(
-    mut q_player: Query<(&CameraBlock, &mut DistanceFog), Changed<CameraBlock>>,
+    mut q_player: Query<(Ref<CameraBlock>, &mut DistanceFog)>,
+    render_distance: Res<RenderDistance>,
 ) {
-    for (CameraBlock(camera_block), mut fog) in q_player.iter_mut() {
-        *fog = match camera_block {
+    for (camera_block, mut fog) in q_player.iter_mut() {
+        if !camera_block.is_changed() && !render_distance.is_changed() {
+            continue;
+        }
+        *fog = match camera_block.0 {
             Block::Water => water_distance_fog(),
-            _ => air_distance_fog(),
+            _ => air_distance_fog(&render_distance),
         }
     }
 }
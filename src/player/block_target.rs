@@ -79,7 +79,7 @@ fn update_targeted_block(
             .at_pos(pos.floor().as_ivec3())
             .cloned()
             .unwrap_or_default();
-        if block.is_solid() {
+        if block.is_solid() || block.is_plant() {
             let block_pos = pos.floor().as_ivec3();
             let space_pos = (camera_pos + camera_direction * t1.next_down())
                 .floor()
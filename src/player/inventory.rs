@@ -1,4 +1,5 @@
 use bevy::{ecs::query::QueryData, prelude::*};
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
 use crate::{
@@ -138,7 +139,7 @@ impl HotbarSelection {
     }
 }
 
-#[derive(Component, Default, Clone, Copy)]
+#[derive(Component, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Inventory {
     pub hotbar: [Option<InventoryItem>; INVENTORY_WIDTH],
 }
@@ -191,7 +192,7 @@ enum InventoryLocation {
     Hotbar(usize),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct InventoryItem {
     pub item: Item,
     pub quantity: Quantity,
@@ -1,8 +1,9 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use super::Player;
 
-#[derive(Component, Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Component, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum PlayerMode {
     Survival,
     NoClip,
@@ -9,11 +9,15 @@ use bevy::{
         view::RenderLayers,
     },
 };
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::{
     physics::PhysicsSystemSet,
     player::Player,
     render_layer::{PORTAL_LAYER, WORLD_LAYER},
+    state::AppState,
+    world::save::{metadata::WorldMetadata, LoadWorldMetadata},
     SKY_COLOUR,
 };
 
@@ -22,7 +26,10 @@ pub struct PortalPlugin;
 impl Plugin for PortalPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(MaterialPlugin::<PortalEntranceMaterial>::default())
-            .add_systems(Startup, spawn_portals)
+            .add_systems(
+                OnEnter(AppState::InGame),
+                spawn_portals.after(LoadWorldMetadata),
+            )
             .add_systems(PreUpdate, add_prev_position_component)
             .add_systems(
                 Update,
@@ -52,36 +59,81 @@ pub struct PortalCamera {
     exit: Entity,
 }
 
-fn spawn_portals(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+/// A portal as it is stored in the world metadata file
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PortalPlacement {
+    pub transform: Transform,
+    pub size: Vec2,
+    /// Index of the exit portal in the list of placements
+    pub exit: Option<usize>,
+}
+
+/// The portals placed in a newly created world
+pub fn default_placements() -> Vec<PortalPlacement> {
     let size = Vec2::new(4.0, 4.0);
-    let portal_mesh_dimensions = size.extend(0.0);
-    let rectangle = meshes.add(Cuboid::from_size(portal_mesh_dimensions));
-    let portal_a_id = commands
-        .spawn((
-            Mesh3d(rectangle.clone()),
-            Transform::from_xyz(-3.0, 1.0 + portal_mesh_dimensions.y * 0.5, 5.5),
-            RenderLayers::layer(WORLD_LAYER),
-        ))
-        .id();
-    let portal_b_id = commands
-        .spawn((
-            Mesh3d(rectangle.clone()),
-            Transform::from_xyz(-46.0, 22.0 + portal_mesh_dimensions.y * 0.5, 20.5),
-            RenderLayers::layer(WORLD_LAYER),
-        ))
-        .id();
-    commands
-        .entity(portal_a_id)
-        .insert(PortalEntrance {
-            exit: Some(portal_b_id),
+    vec![
+        PortalPlacement {
+            transform: Transform::from_xyz(-3.0, 1.0 + size.y * 0.5, 5.5),
             size,
-        });
-    commands
-        .entity(portal_b_id)
-        .insert(PortalEntrance {
-            exit: Some(portal_a_id),
+            exit: Some(1),
+        },
+        PortalPlacement {
+            transform: Transform::from_xyz(-46.0, 22.0 + size.y * 0.5, 20.5),
             size,
+            exit: Some(0),
+        },
+    ]
+}
+
+/// Inverse of `spawn_portals`
+pub fn placements<'a>(
+    portals: impl Iterator<Item = (Entity, &'a Transform, &'a PortalEntrance)>,
+) -> Vec<PortalPlacement> {
+    let portals = portals.collect::<Vec<_>>();
+    let index_by_entity = portals
+        .iter()
+        .enumerate()
+        .map(|(index, (entity, ..))| (*entity, index))
+        .collect::<HashMap<_, _>>();
+    portals
+        .iter()
+        .map(|(_, transform, portal)| PortalPlacement {
+            transform: **transform,
+            size: portal.size,
+            exit: portal
+                .exit
+                .and_then(|exit| index_by_entity.get(&exit).copied()),
+        })
+        .collect()
+}
+
+fn spawn_portals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    metadata: Res<WorldMetadata>,
+) {
+    let ids = metadata
+        .portals
+        .iter()
+        .map(|placement| {
+            let rectangle = meshes.add(Cuboid::from_size(placement.size.extend(0.0)));
+            commands
+                .spawn((
+                    Mesh3d(rectangle),
+                    placement.transform,
+                    RenderLayers::layer(WORLD_LAYER),
+                ))
+                .id()
+        })
+        .collect::<Vec<_>>();
+    for (id, placement) in ids.iter().zip(metadata.portals.iter()) {
+        commands.entity(*id).insert(PortalEntrance {
+            exit: placement
+                .exit
+                .and_then(|exit| ids.get(exit).copied()),
+            size: placement.size,
         });
+    }
 }
 
 fn setup_portal_camera(
@@ -1,8 +1,9 @@
 use bevy::app::Plugin;
 
+pub mod export;
 pub mod material;
 pub mod mesh;
-mod texture;
+pub mod texture;
 
 pub struct RenderPlugin;
 
@@ -0,0 +1,394 @@
+//! Writes chunk meshes to glTF and OBJ files, for renders in other programs and for debugging the
+//! mesher. Nothing here needs a GPU.
+
+use bevy::{
+    platform::collections::HashMap,
+    prelude::*,
+    render::mesh::{Indices, VertexAttributeValues},
+};
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{material::ATTRIBUTE_TERRAIN_VERTEX_DATA, texture::TEXTURE_PATHS};
+use crate::chunk::CHUNK_SIZE_I32;
+
+// Normal indices, see `Quad::get_single_vertex_data`
+const NORTH: u32 = 0;
+const SOUTH: u32 = 1;
+const UP: u32 = 2;
+const DOWN: u32 = 3;
+const EAST: u32 = 4;
+const WEST: u32 = 5;
+const CROSS: u32 = 6;
+
+/// A vertex of `ATTRIBUTE_TERRAIN_VERTEX_DATA`, decoded the same way as in shaders/terrain.wgsl
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    /// Texture coordinates grow by one per block, so textures must be set to repeat
+    pub uv: Vec2,
+    /// Ambient occlusion, from 0 (unoccluded) to 3
+    pub ao_factor: u32,
+    pub texture_index: u32,
+}
+
+impl TerrainVertex {
+    /// Decodes a vertex of the mesh of the chunk at `chunk_pos`
+    pub fn decode(data: u32, chunk_pos: IVec3) -> Self {
+        let local = UVec3::new(data & 63, (data >> 6) & 63, (data >> 12) & 63).as_vec3();
+        let normal_id = (data >> 18) & 7;
+        let ao_factor = (data >> 21) & 3;
+        let texture_index = data >> 23;
+        // The shader lowers every vertex except those of top faces by one block, and chunks are
+        // placed one block higher than their position
+        let y_offset = if normal_id == UP { 1.0 } else { 0.0 };
+        let position = (chunk_pos * CHUNK_SIZE_I32).as_vec3() + local + Vec3::Y * y_offset;
+        Self {
+            position,
+            normal: normal(normal_id),
+            uv: uv(position, normal_id),
+            ao_factor,
+            texture_index,
+        }
+    }
+
+    /// Matches `get_ao_brightness` in shaders/terrain.wgsl
+    pub fn ao_brightness(&self) -> f32 {
+        0.6_f32.powi(self.ao_factor as i32)
+    }
+}
+
+fn normal(normal_id: u32) -> Vec3 {
+    match normal_id {
+        NORTH => Vec3::X,
+        SOUTH => Vec3::NEG_X,
+        UP => Vec3::Y,
+        DOWN => Vec3::NEG_Y,
+        EAST => Vec3::Z,
+        WEST => Vec3::NEG_Z,
+        CROSS => Vec3::Y,
+        _ => Vec3::X,
+    }
+}
+
+/// Matches `get_uv` in shaders/terrain_functions.wgsl, without wrapping into [0, 1]
+fn uv(position: Vec3, normal_id: u32) -> Vec2 {
+    let Vec3 { x, y, z } = position;
+    match normal_id {
+        NORTH => Vec2::new(z, -y),
+        SOUTH => Vec2::new(-z, -y),
+        UP => Vec2::new(-x, z),
+        DOWN => Vec2::new(x, z),
+        EAST => Vec2::new(-x, -y),
+        WEST => Vec2::new(x, -y),
+        CROSS => Vec2::new(z, -y),
+        _ => Vec2::ZERO,
+    }
+}
+
+/// Matches `get_color_for_texture` in shaders/terrain_functions.wgsl. The overlay of grass sides
+/// isn't exported.
+fn texture_tint(texture_index: u32) -> [f32; 4] {
+    match texture_index {
+        2 | 3 => [0.2, 0.6, 0.0, 1.0],
+        7 => [0.03, 0.295, 0.045, 1.0],
+        9 => [0.046, 0.184, 0.782, 0.5],
+        _ => [1.0, 1.0, 1.0, 1.0],
+    }
+}
+
+/// The vertices and triangles of one texture
+#[derive(Default)]
+struct Primitive {
+    vertices: Vec<TerrainVertex>,
+    indices: Vec<u32>,
+}
+
+/// Meshes of several chunks, combined in world space
+#[derive(Default)]
+pub struct ExportedMesh {
+    primitives: BTreeMap<u32, Primitive>,
+}
+
+impl ExportedMesh {
+    /// Adds a mesh made by `chunk_mesh`
+    pub fn add_chunk(&mut self, chunk_pos: IVec3, mesh: &Mesh) {
+        let Some(VertexAttributeValues::Uint32(vertex_data)) =
+            mesh.attribute(ATTRIBUTE_TERRAIN_VERTEX_DATA)
+        else {
+            return;
+        };
+        let indices = match mesh.indices() {
+            Some(Indices::U32(indices)) => indices.clone(),
+            Some(Indices::U16(indices)) => indices
+                .iter()
+                .map(|i| *i as u32)
+                .collect(),
+            None => return,
+        };
+        let vertices = vertex_data
+            .iter()
+            .map(|data| TerrainVertex::decode(*data, chunk_pos))
+            .collect::<Vec<_>>();
+        // Every quad has one texture, so each triangle can be moved over on its own
+        let mut remapped: HashMap<u32, u32> = HashMap::new();
+        for triangle in indices.chunks_exact(3) {
+            let texture_index = vertices[triangle[0] as usize].texture_index;
+            let primitive = self
+                .primitives
+                .entry(texture_index)
+                .or_default();
+            for index in triangle {
+                let new_index = *remapped
+                    .entry(*index)
+                    .or_insert_with(|| {
+                        primitive
+                            .vertices
+                            .push(vertices[*index as usize]);
+                        primitive.vertices.len() as u32 - 1
+                    });
+                primitive.indices.push(new_index);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.primitives
+            .values()
+            .map(|primitive| primitive.indices.len() / 3)
+            .sum()
+    }
+
+    /// Writes a .gltf file with its buffer in a .bin file next to it. Used textures are copied to
+    /// a directory next to it as well.
+    pub fn write_gltf(&self, path: &Path) -> io::Result<()> {
+        let stem = file_stem(path)?;
+        let texture_names = copy_textures(path, self.primitives.keys())?;
+        let mut buffer: Vec<u8> = vec![];
+        let mut buffer_views = vec![];
+        let mut accessors = vec![];
+        let mut meshes_primitives = vec![];
+        let mut materials = vec![];
+        let mut textures = vec![];
+        let mut images = vec![];
+        for (texture_index, primitive) in self.primitives.iter() {
+            let attributes = [
+                (
+                    "POSITION",
+                    "VEC3",
+                    primitive
+                        .vertices
+                        .iter()
+                        .flat_map(|v| v.position.to_array())
+                        .collect::<Vec<_>>(),
+                ),
+                (
+                    "NORMAL",
+                    "VEC3",
+                    primitive
+                        .vertices
+                        .iter()
+                        .flat_map(|v| v.normal.to_array())
+                        .collect(),
+                ),
+                (
+                    "TEXCOORD_0",
+                    "VEC2",
+                    primitive
+                        .vertices
+                        .iter()
+                        .flat_map(|v| v.uv.to_array())
+                        .collect(),
+                ),
+                (
+                    "COLOR_0",
+                    "VEC3",
+                    primitive
+                        .vertices
+                        .iter()
+                        .flat_map(|v| [v.ao_brightness(); 3])
+                        .collect(),
+                ),
+            ];
+            let mut primitive_attributes = serde_json::Map::new();
+            for (name, accessor_type, values) in attributes {
+                let mut accessor = json!({
+                    "bufferView": buffer_views.len(),
+                    "componentType": 5126,
+                    "count": primitive.vertices.len(),
+                    "type": accessor_type,
+                });
+                if name == "POSITION" {
+                    let (min, max) = bounds(&primitive.vertices);
+                    accessor["min"] = json!(min.to_array());
+                    accessor["max"] = json!(max.to_array());
+                }
+                primitive_attributes.insert(name.into(), accessors.len().into());
+                accessors.push(accessor);
+                buffer_views.push(push_buffer_view(
+                    &mut buffer,
+                    values.iter().flat_map(|n| n.to_le_bytes()),
+                    34962,
+                ));
+            }
+            accessors.push(json!({
+                "bufferView": buffer_views.len(),
+                "componentType": 5125,
+                "count": primitive.indices.len(),
+                "type": "SCALAR",
+            }));
+            buffer_views.push(push_buffer_view(
+                &mut buffer,
+                primitive
+                    .indices
+                    .iter()
+                    .flat_map(|n| n.to_le_bytes()),
+                34963,
+            ));
+            meshes_primitives.push(json!({
+                "attributes": primitive_attributes,
+                "indices": accessors.len() - 1,
+                "material": materials.len(),
+            }));
+            let alpha_mode = if texture_tint(*texture_index)[3] < 1.0 {
+                "BLEND"
+            } else {
+                "MASK"
+            };
+            materials.push(json!({
+                "name": texture_names[texture_index].trim_end_matches(".png"),
+                "pbrMetallicRoughness": {
+                    "baseColorTexture": { "index": textures.len() },
+                    "baseColorFactor": texture_tint(*texture_index),
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 1.0,
+                },
+                "alphaMode": alpha_mode,
+            }));
+            textures.push(json!({ "source": images.len(), "sampler": 0 }));
+            images.push(json!({
+                "uri": format!("{stem}_textures/{}", texture_names[texture_index]),
+            }));
+        }
+        let gltf = json!({
+            "asset": { "version": "2.0", "generator": "voxel-engine" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": meshes_primitives }],
+            "materials": materials,
+            "textures": textures,
+            "images": images,
+            // Nearest filtering and repeating, like the terrain material
+            "samplers": [{ "magFilter": 9728, "minFilter": 9728, "wrapS": 10497, "wrapT": 10497 }],
+            "buffers": [{ "uri": format!("{stem}.bin"), "byteLength": buffer.len() }],
+            "bufferViews": buffer_views,
+            "accessors": accessors,
+        });
+        fs::write(path.with_extension("bin"), buffer)?;
+        fs::write(path, serde_json::to_vec_pretty(&gltf)?)
+    }
+
+    /// Writes a .obj file with a .mtl file next to it. Ambient occlusion is stored as vertex
+    /// colours, which most programs which read OBJ files support. Used textures are copied to a
+    /// directory next to it as well.
+    pub fn write_obj(&self, path: &Path) -> io::Result<()> {
+        let stem = file_stem(path)?;
+        let texture_names = copy_textures(path, self.primitives.keys())?;
+        let mut obj = format!("mtllib {stem}.mtl\n");
+        let mut mtl = String::new();
+        // OBJ indices are global and start at 1
+        let mut first_index = 1;
+        for (texture_index, primitive) in self.primitives.iter() {
+            let name = texture_names[texture_index].trim_end_matches(".png");
+            let [r, g, b, a] = texture_tint(*texture_index);
+            let _ = write!(
+                mtl,
+                "newmtl {name}\nKd {r} {g} {b}\nd {a}\nmap_Kd {stem}_textures/{}\n\n",
+                texture_names[texture_index]
+            );
+            for v in primitive.vertices.iter() {
+                let Vec3 { x, y, z } = v.position;
+                let brightness = v.ao_brightness();
+                let _ = writeln!(obj, "v {x} {y} {z} {brightness} {brightness} {brightness}");
+                // OBJ texture coordinates start at the bottom of the image
+                let _ = writeln!(obj, "vt {} {}", v.uv.x, -v.uv.y);
+                let _ = writeln!(obj, "vn {} {} {}", v.normal.x, v.normal.y, v.normal.z);
+            }
+            let _ = writeln!(obj, "usemtl {name}");
+            for triangle in primitive.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| triangle[i] + first_index);
+                let _ = writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}");
+            }
+            first_index += primitive.vertices.len() as u32;
+        }
+        fs::write(path.with_extension("mtl"), mtl)?;
+        fs::write(path, obj)
+    }
+}
+
+fn bounds(vertices: &[TerrainVertex]) -> (Vec3, Vec3) {
+    vertices.iter().fold(
+        (Vec3::INFINITY, Vec3::NEG_INFINITY),
+        |(min, max), v| (min.min(v.position), max.max(v.position)),
+    )
+}
+
+/// Returns the JSON of a buffer view of the bytes, which are appended to the buffer
+fn push_buffer_view(
+    buffer: &mut Vec<u8>,
+    bytes: impl Iterator<Item = u8>,
+    target: u32,
+) -> serde_json::Value {
+    let offset = buffer.len();
+    buffer.extend(bytes);
+    json!({
+        "buffer": 0,
+        "byteOffset": offset,
+        "byteLength": buffer.len() - offset,
+        "target": target,
+    })
+}
+
+fn file_stem(path: &Path) -> io::Result<String> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(String::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid output path"))
+}
+
+/// Copies the textures to `<stem>_textures` next to the output file and returns their file names
+fn copy_textures<'a>(
+    path: &Path,
+    texture_indices: impl Iterator<Item = &'a u32>,
+) -> io::Result<HashMap<u32, String>> {
+    let directory = path.with_file_name(format!("{}_textures", file_stem(path)?));
+    fs::create_dir_all(&directory)?;
+    let mut names = HashMap::new();
+    for texture_index in texture_indices {
+        let Some(texture_path) = TEXTURE_PATHS.get(*texture_index as usize) else {
+            return Err(io::Error::other(format!(
+                "Unknown texture index {texture_index}"
+            )));
+        };
+        let source = PathBuf::from("assets").join(texture_path);
+        let name = source
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        fs::copy(&source, directory.join(&name))?;
+        names.insert(*texture_index, name);
+    }
+    Ok(names)
+}
@@ -69,8 +69,10 @@ impl Material for TerrainMaterial {
     }
 }
 
-const MAX_TEXTURE_COUNT: usize = 16;
-const MAX_OVERLAY_COUNT: usize = 16;
+// Together these stay within the 32 sampled textures per shader stage which the material has
+// always used
+const MAX_TEXTURE_COUNT: usize = 24;
+const MAX_OVERLAY_COUNT: usize = 8;
 
 impl AsBindGroup for TerrainMaterial {
     type Data = ();
@@ -229,6 +229,7 @@ impl Quad {
     fn get_single_vertex_data(&self, i: usize) -> u32 {
         // Make sure this matches the index in the terrain shader
         let normal_index: u32 = match self.side {
+            _ if self.block.is_plant() => 6,
             BlockSide::North => 0,
             BlockSide::South => 1,
             BlockSide::Up => 2,
@@ -249,7 +250,7 @@ impl Quad {
     }
 }
 
-fn chunk_mesh(chunk: Neighborhood<Blocks>) -> Option<Mesh> {
+pub fn chunk_mesh(chunk: Neighborhood<Blocks>) -> Option<Mesh> {
     let mut quads = vec![];
     quads.extend(greedy_mesh(&chunk, BlockSide::Up));
     quads.extend(greedy_mesh(&chunk, BlockSide::Down));
@@ -257,9 +258,46 @@ fn chunk_mesh(chunk: Neighborhood<Blocks>) -> Option<Mesh> {
     quads.extend(greedy_mesh(&chunk, BlockSide::South));
     quads.extend(greedy_mesh(&chunk, BlockSide::West));
     quads.extend(greedy_mesh(&chunk, BlockSide::East));
+    quads.extend(plant_quads(&chunk));
     return create_mesh_from_quads(quads);
 }
 
+/// Plants are two quads which cross diagonally through the block, each drawn from both sides
+fn plant_quads(chunk: &Neighborhood<Blocks>) -> Vec<Quad> {
+    let mut quads: Vec<Quad> = vec![];
+    let middle = chunk.middle_chunk().clone().expect("Already checked");
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let block = middle.at_pos([x, y, z]);
+                if !block.is_plant() {
+                    continue;
+                }
+                let pos = IVec3::new(x as i32, y as i32, z as i32);
+                for (start, end) in [(IVec3::ZERO, IVec3::new(1, 0, 1)), (IVec3::X, IVec3::Z)] {
+                    let front = [
+                        pos + start,
+                        pos + end,
+                        pos + end + IVec3::Y,
+                        pos + start + IVec3::Y,
+                    ];
+                    let mut back = front;
+                    back.reverse();
+                    for vertices in [front, back] {
+                        quads.push(Quad {
+                            block: *block,
+                            side: BlockSide::Up,
+                            vertices,
+                            ao_factors: [0; 4],
+                        });
+                    }
+                }
+            }
+        }
+    }
+    return quads;
+}
+
 // TODO: Replace slow implementation with binary mesher
 fn greedy_mesh(chunk: &Neighborhood<Blocks>, direction: BlockSide) -> Vec<Quad> {
     let mut quads: Vec<Quad> = vec![];
@@ -270,6 +308,7 @@ fn greedy_mesh(chunk: &Neighborhood<Blocks>, direction: BlockSide) -> Vec<Quad>
             for col in 0..CHUNK_SIZE {
                 let block = blocks.get_from_layer_coords(&direction, layer, row, col);
                 if block == &Block::Air
+                    || block.is_plant()
                     || chunk.block_is_hidden_from_above(
                         &direction,
                         layer as i32,
@@ -57,6 +57,33 @@ impl Plugin for TexturePlugin {
 //     }
 // }
 
+/// Block textures in the order of their texture indices, see `get_texture_index`
+pub const TEXTURE_PATHS: [&str; 23] = [
+    "textures/blocks/stone.png",
+    "textures/blocks/dirt.png",
+    "textures/blocks/grass.png",
+    "textures/blocks/grass_side.png",
+    "textures/blocks/sand.png",
+    "textures/blocks/oak_log.png",
+    "textures/blocks/oak_log_top.png",
+    "textures/blocks/oak_leaves.png",
+    "textures/blocks/bedrock.png",
+    "textures/blocks/water.png",
+    "textures/blocks/coal_ore.png",
+    "textures/blocks/iron_ore.png",
+    "textures/blocks/gold_ore.png",
+    "textures/blocks/diamond_ore.png",
+    "textures/blocks/gravel.png",
+    "textures/blocks/tall_grass.png",
+    "textures/blocks/dandelion.png",
+    "textures/blocks/poppy.png",
+    "textures/blocks/dead_bush.png",
+    "textures/blocks/red_mushroom.png",
+    "textures/blocks/brown_mushroom.png",
+    "textures/blocks/cactus_side.png",
+    "textures/blocks/cactus_top.png",
+];
+
 #[derive(Resource)]
 pub struct BlockMaterials {
     pub terrain: Handle<TerrainMaterial>,
@@ -100,18 +127,10 @@ fn setup(
     //     |path, colour| get_material_with_colour(path, &asset_server, &mut materials, colour);
 
     let terrain_material_handle = materials.add(TerrainMaterial {
-        textures: vec![
-            asset_server.load("textures/blocks/stone.png"),
-            asset_server.load("textures/blocks/dirt.png"),
-            asset_server.load("textures/blocks/grass.png"),
-            asset_server.load("textures/blocks/grass_side.png"),
-            asset_server.load("textures/blocks/sand.png"),
-            asset_server.load("textures/blocks/oak_log.png"),
-            asset_server.load("textures/blocks/oak_log_top.png"),
-            asset_server.load("textures/blocks/oak_leaves.png"),
-            asset_server.load("textures/blocks/bedrock.png"),
-            asset_server.load("textures/blocks/water.png"),
-        ],
+        textures: TEXTURE_PATHS
+            .iter()
+            .map(|path| asset_server.load(*path))
+            .collect(),
         overlay_textures: vec![asset_server.load("textures/blocks/grass_side_overlay.png")],
     });
     let block_materials = BlockMaterials {
@@ -138,7 +157,7 @@ fn setup(
     commands.insert_resource(block_materials);
 }
 
-// Make sure this matches the texture array loaded during setup
+// Make sure this matches TEXTURE_PATHS
 pub fn get_texture_index(block: &Block, side: &BlockSide) -> u32 {
     match block {
         Block::Air => panic!("No texture for air"),
@@ -157,6 +176,21 @@ pub fn get_texture_index(block: &Block, side: &BlockSide) -> u32 {
         Block::Leaves => 7,
         Block::Bedrock => 8,
         Block::Water => 9,
+        Block::CoalOre => 10,
+        Block::IronOre => 11,
+        Block::GoldOre => 12,
+        Block::DiamondOre => 13,
+        Block::Gravel => 14,
+        Block::TallGrass => 15,
+        Block::Dandelion => 16,
+        Block::Poppy => 17,
+        Block::DeadBush => 18,
+        Block::RedMushroom => 19,
+        Block::BrownMushroom => 20,
+        Block::Cactus => match side {
+            BlockSide::Up | BlockSide::Down => 22,
+            _ => 21,
+        },
     }
 }
 
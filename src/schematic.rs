@@ -0,0 +1,399 @@
+use crate::{
+    block::Block,
+    chunk::data::Blocks,
+    player::block_target::{TargetedBlock, TargetedSpace},
+    state::{AppState, InGameState},
+    world::{block_update::SetBlockEvent, neighborhood::ComponentIndex},
+};
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use std::path::{Path, PathBuf};
+
+pub mod file;
+
+/// Schematics can be at most this many blocks long along each axis
+pub const MAX_SCHEMATIC_SIZE: u32 = 256;
+const SCHEMATICS_DIRECTORY: &str = "schematics";
+
+pub struct SchematicPlugin;
+
+impl Plugin for SchematicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CopySchematicEvent>()
+            .add_event::<PasteSchematicEvent>()
+            .add_event::<SaveSchematicEvent>()
+            .add_event::<LoadSchematicEvent>()
+            .init_resource::<Selection>()
+            .init_resource::<Clipboard>()
+            .add_systems(
+                Update,
+                (
+                    (
+                        select_first_corner.run_if(input_just_pressed(KeyCode::BracketLeft)),
+                        select_second_corner.run_if(input_just_pressed(KeyCode::BracketRight)),
+                        copy_selection.run_if(input_just_pressed(KeyCode::KeyC)),
+                        paste_clipboard.run_if(input_just_pressed(KeyCode::KeyV)),
+                        rotate_clipboard.run_if(input_just_pressed(KeyCode::KeyR)),
+                        mirror_clipboard.run_if(input_just_pressed(KeyCode::KeyM)),
+                        save_clipboard.run_if(input_just_pressed(KeyCode::F7)),
+                        load_dropped_files,
+                        draw_selection,
+                    )
+                        .run_if(in_state(InGameState::Playing)),
+                    (
+                        copy_schematic,
+                        load_schematic,
+                        save_schematic,
+                        paste_schematic,
+                    )
+                        .chain(),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// A cuboid of blocks which can be pasted into the world
+#[derive(Clone)]
+pub struct Schematic {
+    size: UVec3,
+    /// Laid out in the same order as `Blocks`
+    blocks: Vec<Block>,
+}
+
+impl Schematic {
+    pub fn empty(size: UVec3) -> Self {
+        Self {
+            size,
+            blocks: vec![Block::Air; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    fn index(&self, pos: UVec3) -> usize {
+        ((pos.x * self.size.z + pos.z) * self.size.y + pos.y) as usize
+    }
+
+    pub fn at(&self, pos: UVec3) -> Block {
+        self.blocks[self.index(pos)]
+    }
+
+    pub fn at_mut(&mut self, pos: UVec3) -> &mut Block {
+        let index = self.index(pos);
+        &mut self.blocks[index]
+    }
+
+    /// Copies the blocks between two corners (inclusive). Returns the position of a block whose
+    /// chunk isn't loaded if there is one.
+    pub fn copy(index: &ComponentIndex<Blocks>, a: IVec3, b: IVec3) -> Result<Self, IVec3> {
+        let min = a.min(b);
+        let max = a.max(b);
+        let mut schematic = Self::empty((max - min + IVec3::ONE).as_uvec3());
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let pos = IVec3::new(x, y, z);
+                    let block = index.at_pos(pos).ok_or(pos)?;
+                    *schematic.at_mut((pos - min).as_uvec3()) = *block;
+                }
+            }
+        }
+        Ok(schematic)
+    }
+
+    /// Every block with its offset from the lowest corner once the transform has been applied
+    pub fn transformed_blocks(
+        &self,
+        transform: SchematicTransform,
+    ) -> impl Iterator<Item = (UVec3, Block)> + '_ {
+        let UVec3 { x, y, z } = self.size;
+        (0..x)
+            .flat_map(move |x| (0..z).flat_map(move |z| (0..y).map(move |y| UVec3::new(x, y, z))))
+            .map(move |pos| (transform.apply(pos, self.size), self.at(pos)))
+    }
+}
+
+/// How a schematic is turned when it is pasted. Mirroring happens before rotating.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct SchematicTransform {
+    /// Clockwise quarter turns around the y axis, seen from above
+    pub quarter_turns: u8,
+    /// Flips the schematic along the x axis
+    pub mirrored: bool,
+}
+
+impl SchematicTransform {
+    pub fn rotated_clockwise(self) -> Self {
+        Self {
+            quarter_turns: (self.quarter_turns + 1) % 4,
+            ..self
+        }
+    }
+
+    /// The size of a schematic after it has been transformed
+    pub fn size(&self, size: UVec3) -> UVec3 {
+        if self.quarter_turns % 2 == 1 {
+            UVec3::new(size.z, size.y, size.x)
+        } else {
+            size
+        }
+    }
+
+    /// Moves an offset from the lowest corner of a schematic to where it is after the transform
+    pub fn apply(&self, pos: UVec3, size: UVec3) -> UVec3 {
+        let mut pos = pos;
+        let mut size = size;
+        if self.mirrored {
+            pos.x = size.x - 1 - pos.x;
+        }
+        for _ in 0..self.quarter_turns % 4 {
+            // North (-z) turns to east (+x)
+            pos = UVec3::new(size.z - 1 - pos.z, pos.y, pos.x);
+            size = UVec3::new(size.z, size.y, size.x);
+        }
+        pos
+    }
+}
+
+/// Corners of the cuboid which `C` copies
+#[derive(Resource, Default)]
+pub struct Selection {
+    pub first: Option<IVec3>,
+    pub second: Option<IVec3>,
+}
+
+/// The most recently copied or loaded schematic, and how it will be pasted
+#[derive(Resource, Default)]
+pub struct Clipboard {
+    pub schematic: Option<Schematic>,
+    pub transform: SchematicTransform,
+}
+
+/// Copies the blocks between two corners (inclusive) to the clipboard. Every chunk in the region
+/// must be loaded.
+#[derive(Event)]
+pub struct CopySchematicEvent {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+/// Places the clipboard's blocks with their lowest corner at `position`. Chunks which aren't
+/// loaded are skipped.
+#[derive(Event)]
+pub struct PasteSchematicEvent {
+    pub position: IVec3,
+    pub transform: SchematicTransform,
+    /// Whether air in the schematic replaces the blocks which are already there
+    pub replace_with_air: bool,
+}
+
+#[derive(Event)]
+pub struct SaveSchematicEvent {
+    pub path: PathBuf,
+}
+
+/// Replaces the clipboard with a schematic file
+#[derive(Event)]
+pub struct LoadSchematicEvent {
+    pub path: PathBuf,
+}
+
+fn select_first_corner(targeted_block: Res<TargetedBlock>, mut selection: ResMut<Selection>) {
+    if let Some(pos) = targeted_block.0 {
+        selection.first = Some(pos);
+        info!("Selected {} as the first corner", pos);
+    }
+}
+
+fn select_second_corner(targeted_block: Res<TargetedBlock>, mut selection: ResMut<Selection>) {
+    if let Some(pos) = targeted_block.0 {
+        selection.second = Some(pos);
+        info!("Selected {} as the second corner", pos);
+    }
+}
+
+fn copy_selection(selection: Res<Selection>, mut copy_events: EventWriter<CopySchematicEvent>) {
+    let (Some(min), Some(max)) = (selection.first, selection.second) else {
+        warn!("Select two corners before copying");
+        return;
+    };
+    copy_events.write(CopySchematicEvent { min, max });
+}
+
+/// Shift+V keeps the blocks where the schematic has air
+fn paste_clipboard(
+    targeted_space: Res<TargetedSpace>,
+    clipboard: Res<Clipboard>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut paste_events: EventWriter<PasteSchematicEvent>,
+) {
+    let Some(position) = targeted_space.0 else {
+        warn!("Look at a block to choose where to paste");
+        return;
+    };
+    paste_events.write(PasteSchematicEvent {
+        position,
+        transform: clipboard.transform,
+        replace_with_air: !keys.pressed(KeyCode::ShiftLeft),
+    });
+}
+
+fn rotate_clipboard(mut clipboard: ResMut<Clipboard>) {
+    clipboard.transform = clipboard
+        .transform
+        .rotated_clockwise();
+    info!(
+        "Pasting with {} clockwise quarter turns",
+        clipboard.transform.quarter_turns
+    );
+}
+
+fn mirror_clipboard(mut clipboard: ResMut<Clipboard>) {
+    clipboard.transform.mirrored = !clipboard.transform.mirrored;
+    info!("Pasting mirrored: {}", clipboard.transform.mirrored);
+}
+
+/// Saves to the first unused name in the schematics directory
+fn save_clipboard(mut save_events: EventWriter<SaveSchematicEvent>) {
+    let path = (1..)
+        .map(|n| {
+            Path::new(SCHEMATICS_DIRECTORY).join(format!("schematic_{n}.{}", file::FILE_EXTENSION))
+        })
+        .find(|path| !path.exists())
+        .unwrap();
+    save_events.write(SaveSchematicEvent { path });
+}
+
+/// Dropping a schematic file onto the window loads it into the clipboard
+fn load_dropped_files(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    mut load_events: EventWriter<LoadSchematicEvent>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        if path_buf
+            .extension()
+            .is_some_and(|ext| ext == file::FILE_EXTENSION)
+        {
+            load_events.write(LoadSchematicEvent {
+                path: path_buf.clone(),
+            });
+        }
+    }
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    targeted_block: Res<TargetedBlock>,
+    targeted_space: Res<TargetedSpace>,
+    clipboard: Res<Clipboard>,
+) {
+    // Until the second corner is chosen, the selection follows the targeted block
+    if let (Some(first), Some(second)) = (selection.first, selection.second.or(targeted_block.0)) {
+        draw_cuboid(
+            &mut gizmos,
+            first.min(second),
+            first.max(second),
+            Color::WHITE,
+        );
+    }
+    if let (Some(schematic), Some(position)) = (&clipboard.schematic, targeted_space.0) {
+        let size = clipboard
+            .transform
+            .size(schematic.size());
+        draw_cuboid(
+            &mut gizmos,
+            position,
+            position + size.as_ivec3() - IVec3::ONE,
+            Color::srgb(0.3, 0.8, 1.0),
+        );
+    }
+}
+
+fn draw_cuboid(gizmos: &mut Gizmos, min: IVec3, max: IVec3, color: Color) {
+    let min = min.as_vec3();
+    let max = max.as_vec3() + Vec3::ONE;
+    let transform = Transform::from_translation(0.5 * (min + max)).with_scale(max - min);
+    gizmos.cuboid(transform, color);
+}
+
+fn copy_schematic(
+    mut events: EventReader<CopySchematicEvent>,
+    index: Res<ComponentIndex<Blocks>>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    for event in events.read() {
+        let size = (event.max - event.min).abs() + IVec3::ONE;
+        if size.cmpgt(IVec3::splat(MAX_SCHEMATIC_SIZE as i32)).any() {
+            warn!(
+                "Selections can be at most {} blocks long",
+                MAX_SCHEMATIC_SIZE
+            );
+            continue;
+        }
+        match Schematic::copy(&index, event.min, event.max) {
+            Ok(schematic) => {
+                info!("Copied {} blocks", size.element_product());
+                clipboard.schematic = Some(schematic);
+                clipboard.transform = SchematicTransform::default();
+            }
+            Err(pos) => warn!("Failed to copy, the chunk at {} isn't loaded", pos),
+        }
+    }
+}
+
+fn load_schematic(mut events: EventReader<LoadSchematicEvent>, mut clipboard: ResMut<Clipboard>) {
+    for event in events.read() {
+        match file::read(&event.path) {
+            Ok(schematic) => {
+                info!("Loaded {} into the clipboard", event.path.display());
+                clipboard.schematic = Some(schematic);
+                clipboard.transform = SchematicTransform::default();
+            }
+            Err(e) => warn!("Failed to load {}: {}", event.path.display(), e),
+        }
+    }
+}
+
+fn save_schematic(mut events: EventReader<SaveSchematicEvent>, clipboard: Res<Clipboard>) {
+    for event in events.read() {
+        let Some(schematic) = &clipboard.schematic else {
+            warn!("The clipboard is empty");
+            continue;
+        };
+        match file::write(&event.path, schematic) {
+            Ok(()) => info!("Saved {}", event.path.display()),
+            Err(e) => warn!("Failed to save {}: {}", event.path.display(), e),
+        }
+    }
+}
+
+/// Goes through `SetBlockEvent` so that the pasted blocks get block updates
+fn paste_schematic(
+    mut events: EventReader<PasteSchematicEvent>,
+    clipboard: Res<Clipboard>,
+    mut set_block_events: EventWriter<SetBlockEvent>,
+) {
+    for event in events.read() {
+        let Some(schematic) = &clipboard.schematic else {
+            warn!("The clipboard is empty");
+            continue;
+        };
+        for (offset, block) in schematic.transformed_blocks(event.transform) {
+            if block == Block::Air && !event.replace_with_air {
+                continue;
+            }
+            let world_pos = event.position + offset.as_ivec3();
+            set_block_events.write(SetBlockEvent {
+                block,
+                world_pos: world_pos.to_array(),
+            });
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Reads and writes schematic files, see docs/schematic_file_format.md
+
+use crate::block::Block;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use super::{Schematic, MAX_SCHEMATIC_SIZE};
+
+pub const FILE_EXTENSION: &str = "schematic";
+const SCHEMATIC_FILE_VERSION: u64 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SchematicFile {
+    version: u64,
+    size: [u32; 3],
+    /// Vertical runs of each block in the form `[x, y, z, length]`
+    blocks: BTreeMap<Block, Vec<[u32; 4]>>,
+}
+
+pub fn write(path: &Path, schematic: &Schematic) -> io::Result<()> {
+    let mut runs: BTreeMap<Block, Vec<[u32; 4]>> = BTreeMap::new();
+    let UVec3 {
+        x: size_x,
+        y: size_y,
+        z: size_z,
+    } = schematic.size;
+    for x in 0..size_x {
+        for z in 0..size_z {
+            let mut y = 0;
+            while y < size_y {
+                let block = schematic.at(UVec3::new(x, y, z));
+                let start = y;
+                while y < size_y && schematic.at(UVec3::new(x, y, z)) == block {
+                    y += 1;
+                }
+                if block == Block::Air {
+                    continue;
+                }
+                runs.entry(block)
+                    .or_default()
+                    .push([x, start, z, y - start]);
+            }
+        }
+    }
+    let file = SchematicFile {
+        version: SCHEMATIC_FILE_VERSION,
+        size: schematic.size.to_array(),
+        blocks: runs,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec(&file)?)
+}
+
+pub fn read(path: &Path) -> io::Result<Schematic> {
+    let file: SchematicFile = serde_json::from_slice(&fs::read(path)?)?;
+    if file.version != SCHEMATIC_FILE_VERSION {
+        return Err(invalid_data(format!(
+            "Unsupported schematic version {}",
+            file.version
+        )));
+    }
+    let size = UVec3::from(file.size);
+    if size.cmpgt(UVec3::splat(MAX_SCHEMATIC_SIZE)).any() || size.cmpeq(UVec3::ZERO).any() {
+        return Err(invalid_data(format!(
+            "Schematics must be between 1 and {MAX_SCHEMATIC_SIZE} blocks long, not {size}"
+        )));
+    }
+    let mut schematic = Schematic::empty(size);
+    for (block, block_runs) in file.blocks.iter() {
+        if block == &Block::Air {
+            return Err(invalid_data("Air must not be listed explicitly"));
+        }
+        for run in block_runs.iter() {
+            let [x, y, z, length] = *run;
+            if x >= size.x || z >= size.z || y as u64 + length as u64 > size.y as u64 {
+                return Err(invalid_data(format!("Run {:?} is out of bounds", run)));
+            }
+            for y in y..y + length {
+                *schematic.at_mut(UVec3::new(x, y, z)) = *block;
+            }
+        }
+    }
+    Ok(schematic)
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
@@ -5,6 +5,9 @@ pub enum AppState {
     Init,
     InGame,
     MainMenu,
+    Singleplayer,
+    /// The settings screen, opened from the main menu
+    Settings,
 }
 
 #[derive(SubStates, Clone, PartialEq, Eq, Hash, Debug, Default)]
@@ -13,4 +16,8 @@ pub enum InGameState {
     #[default]
     Playing,
     Paused,
+    /// The settings screen, opened from the pause menu
+    Settings,
+    /// Waiting for the world to finish saving before returning to the main menu
+    Saving,
 }
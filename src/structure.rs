@@ -1,19 +1,47 @@
-use itertools::Itertools;
+use bevy::{
+    ecs::{query::QueryItem, system::SystemParamItem},
+    prelude::*,
+};
+use std::sync::Arc;
+
+pub mod start;
 
 use crate::{
     block::Block,
     chunk::{
-        data::{Noise3d, Terrain},
+        data::{BiomeMap, Terrain},
         spatial::SpatiallyMapped,
-        CHUNK_SIZE, CHUNK_SIZE_I32, CHUNK_SIZE_U32,
+        CHUNK_SIZE, CHUNK_SIZE_I32,
+    },
+    utils::{mix_hash, nth_random, VolumetricRange},
+    world::{
+        biome::Biome,
+        neighborhood::Neighborhood,
+        pass::{PassOutput, WorldgenPass},
+        preset::WorldPreset,
+        seed::WorldSeed,
+        stage::Stage,
+        SurfaceSampler, TerrainNoise,
     },
-    utils::{fast_hash, fast_hash_f32, VolumetricRange},
-    world::neighborhood::Neighborhood,
 };
 
+/// No structure reaches further than this from its position horizontally, or further than twice
+/// this vertically. This keeps every structure which could reach a chunk, and every structure
+/// which could stop it from being placed, inside the chunk's neighborhood.
+const MAX_STRUCTURE_RADIUS: i32 = 8;
+/// Candidates further than this from the chunk can neither reach it nor stop a structure which
+/// reaches it from being placed
+const CANDIDATE_MARGIN: i32 = 2 * MAX_STRUCTURE_RADIUS;
+
 #[derive(Clone)]
 pub enum Structure {
     Tree { trunk_height: u8, leaf_radius: u8 },
+    PineTree { trunk_height: u8 },
+    Bush { radius: u8 },
+    Boulder { radius: u8 },
+    Cactus { height: u8 },
+    /// Broken stone walls around a square floor. Each wall column is between 0 and 3 blocks high.
+    Ruin { width: u8, wall_hash: u32 },
 }
 
 impl Structure {
@@ -47,129 +75,463 @@ impl Structure {
                 blocks.push((Block::Dirt, [0, -1, 0]));
                 return blocks;
             }
+            Structure::PineTree { trunk_height } => {
+                let trunk_height = *trunk_height as i32;
+                let mut blocks = vec![];
+                for y in 0..trunk_height {
+                    blocks.push((Block::Wood, [0, y, 0]));
+                }
+                // Layers of leaves which get narrower towards the top
+                for y in 2..=trunk_height {
+                    let depth = trunk_height - y;
+                    let radius = match depth {
+                        0 => 0,
+                        1 | 2 => 1,
+                        _ if depth % 2 == 1 => 2,
+                        _ => 1,
+                    };
+                    VolumetricRange::new(-radius..radius + 1, y..y + 1, -radius..radius + 1)
+                        .filter(|(x, _, z)| (x != &0 || z != &0) || y == trunk_height)
+                        .filter(|(x, _, z)| radius < 2 || x.abs() != 2 || z.abs() != 2)
+                        .for_each(|(x, y, z)| blocks.push((Block::Leaves, [x, y, z])));
+                }
+                blocks.push((Block::Dirt, [0, -1, 0]));
+                blocks
+            }
+            Structure::Bush { radius } => {
+                let radius = *radius as i32;
+                VolumetricRange::new(-radius..radius + 1, 0..radius + 1, -radius..radius + 1)
+                    .filter(|(x, y, z)| x.abs() + y + z.abs() <= radius)
+                    .map(|(x, y, z)| (Block::Leaves, [x, y, z]))
+                    .collect()
+            }
+            Structure::Boulder { radius } => {
+                let radius = *radius as i32;
+                // Sunk into the ground by one block
+                VolumetricRange::new(-radius..radius + 1, -radius..radius + 1, -radius..radius + 1)
+                    .filter(|(x, y, z)| x * x + y * y + z * z <= radius * radius + radius)
+                    .map(|(x, y, z)| (Block::Stone, [x, y + radius - 1, z]))
+                    .collect()
+            }
+            Structure::Cactus { height } => (0..*height as i32)
+                .map(|y| (Block::Cactus, [0, y, 0]))
+                .collect(),
+            Structure::Ruin { width, wall_hash } => {
+                let width = *width as i32;
+                let half_width = width / 2;
+                let range = -half_width..width - half_width;
+                let mut blocks = vec![];
+                for (x, _, z) in VolumetricRange::new(range.clone(), 0..1, range.clone()) {
+                    blocks.push((Block::Stone, [x, -1, z]));
+                    let is_wall = x == range.start
+                        || z == range.start
+                        || x == range.end - 1
+                        || z == range.end - 1;
+                    if !is_wall {
+                        continue;
+                    }
+                    let wall_height = nth_random(*wall_hash, (x * width + z) as u32) % 4;
+                    for y in 0..wall_height as i32 {
+                        blocks.push((Block::Stone, [x, y, z]));
+                    }
+                }
+                blocks
+            }
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum StructureType {
     Tree,
+    PineTree,
+    Bush,
+    Boulder,
+    Cactus,
+    Ruin,
 }
 
 impl StructureType {
+    /// Picks the size of a structure from a random number
+    fn create(&self, hash: u32) -> Structure {
+        let random = |n, range: u32| (nth_random(hash, n) % range) as u8;
+        match self {
+            StructureType::Tree => Structure::Tree {
+                trunk_height: 4 + random(0, 3),
+                leaf_radius: 2,
+            },
+            StructureType::PineTree => Structure::PineTree {
+                trunk_height: 6 + random(0, 4),
+            },
+            StructureType::Bush => Structure::Bush {
+                radius: 1 + random(0, 2),
+            },
+            StructureType::Boulder => Structure::Boulder {
+                radius: 1 + random(0, 2),
+            },
+            StructureType::Cactus => Structure::Cactus {
+                height: 2 + random(0, 3),
+            },
+            StructureType::Ruin => Structure::Ruin {
+                width: 5 + random(0, 3),
+                wall_hash: nth_random(hash, 1),
+            },
+        }
+    }
+
+    /// How far the structure reaches from its position horizontally, at most
+    /// `MAX_STRUCTURE_RADIUS`
+    fn radius(&self) -> i32 {
+        match self {
+            StructureType::Tree
+            | StructureType::PineTree
+            | StructureType::Bush
+            | StructureType::Boulder => 2,
+            StructureType::Cactus => 0,
+            StructureType::Ruin => 4,
+        }
+    }
+}
+
+/// Where a type of structure can be placed
+#[derive(Clone)]
+pub struct StructureRule {
+    pub structure_type: StructureType,
+    /// The chance of each cell of the placement grid getting a structure, by biome. Biomes which
+    /// aren't listed don't get the structure.
+    pub densities: Vec<(Biome, f32)>,
+    /// Blocks which the structure can be placed on
+    pub ground: Vec<Block>,
+    /// The largest difference in height between the ground under the structure and the ground
+    /// at the edge of the structure
+    pub max_slope: i32,
+    /// Size of the cells of the placement grid. At most one structure is placed per cell, so
+    /// structures of the same type are at least half of this apart.
+    pub spacing: i32,
+}
+
+impl StructureRule {
+    fn density(&self, biome: Biome) -> f32 {
+        self.densities
+            .iter()
+            .find(|(b, _)| *b == biome)
+            .map_or(0.0, |(_, density)| *density)
+    }
+}
+
+/// Every type of structure which worldgen places. Rules which come first take priority when
+/// structures of different types would overlap.
+#[derive(Resource, Clone)]
+pub struct StructureRegistry(pub Arc<Vec<StructureRule>>);
+
+impl Default for StructureRegistry {
+    fn default() -> Self {
+        use Biome::*;
+        let rules = vec![
+            StructureRule {
+                structure_type: StructureType::Ruin,
+                densities: vec![(Plains, 0.03), (Desert, 0.04), (Savanna, 0.02)],
+                ground: vec![Block::Grass, Block::Dirt, Block::Sand],
+                max_slope: 1,
+                spacing: 24,
+            },
+            StructureRule {
+                structure_type: StructureType::Boulder,
+                densities: vec![(Tundra, 0.15), (Taiga, 0.08), (Plains, 0.03)],
+                ground: vec![Block::Grass, Block::Dirt],
+                max_slope: 2,
+                spacing: 12,
+            },
+            StructureRule {
+                structure_type: StructureType::Tree,
+                densities: vec![(Forest, 0.6), (Plains, 0.04), (Savanna, 0.08)],
+                ground: vec![Block::Grass, Block::Dirt],
+                max_slope: 3,
+                spacing: 6,
+            },
+            StructureRule {
+                structure_type: StructureType::PineTree,
+                densities: vec![(Taiga, 0.6), (Tundra, 0.03), (Forest, 0.1)],
+                ground: vec![Block::Grass, Block::Dirt],
+                max_slope: 3,
+                spacing: 6,
+            },
+            StructureRule {
+                structure_type: StructureType::Cactus,
+                densities: vec![(Desert, 0.12)],
+                ground: vec![Block::Sand],
+                max_slope: 2,
+                spacing: 7,
+            },
+            StructureRule {
+                structure_type: StructureType::Bush,
+                densities: vec![(Plains, 0.1), (Savanna, 0.15), (Forest, 0.1)],
+                ground: vec![Block::Grass],
+                max_slope: 2,
+                spacing: 5,
+            },
+        ];
+        Self(Arc::new(rules))
+    }
+}
+
+/// Places the structures of the registry and then the structure starts. Structures reach into
+/// neighbouring chunks, so every chunk around the chunk must have been sculpted. Presets without
+/// features have no structures.
+pub struct StructurePass;
+
+impl WorldgenPass for StructurePass {
+    type Param = (
+        Res<'static, StructureRegistry>,
+        Res<'static, WorldSeed>,
+        Res<'static, WorldPreset>,
+        TerrainNoise<'static>,
+    );
+    type ChunkData = (
+        &'static Neighborhood<Terrain>,
+        &'static Neighborhood<BiomeMap>,
+    );
+    type Input = (
+        StructureRegistry,
+        u32,
+        bool,
+        SurfaceSampler,
+        Neighborhood<Terrain>,
+        Neighborhood<BiomeMap>,
+    );
+
+    const INPUT_STAGE: Option<Stage> = Some(Stage::Ores);
+    const OUTPUT_STAGE: Stage = Stage::Structures;
+    const NEIGHBORHOOD_RADIUS: i32 = 1;
+
+    /// Saved chunks only get their terrain back once a neighbour needs it
+    fn is_ready((terrain, _): &QueryItem<Self::ChunkData>) -> bool {
+        terrain
+            .0
+            .iter()
+            .all(Option::is_some)
+    }
+
+    fn prepare(
+        (registry, seed, preset, terrain_noise): &SystemParamItem<Self::Param>,
+        (terrain, biomes): QueryItem<Self::ChunkData>,
+    ) -> Self::Input {
+        (
+            (*registry).clone(),
+            seed.0,
+            preset.has_features(),
+            terrain_noise.sampler(),
+            terrain.clone(),
+            biomes.clone(),
+        )
+    }
+
+    fn run(
+        (registry, seed, has_features, sampler, terrain, biomes): Self::Input,
+        chunk_pos: IVec3,
+    ) -> PassOutput {
+        if !has_features {
+            return PassOutput::BlockUpdates(vec![]);
+        }
+        // Structure starts are stamped last so that they replace smaller structures
+        let mut block_updates = registry.get_structure_blocks(seed, chunk_pos, &terrain, &biomes);
+        block_updates.extend(start::stamp_starts(seed, chunk_pos, &sampler));
+        PassOutput::BlockUpdates(block_updates)
+    }
+}
+
+struct Placement {
+    rule_index: usize,
+    /// Position of the block above the ground, relative to the middle chunk
+    pos: IVec3,
+    hash: u32,
+}
+
+impl StructureRegistry {
+    /// Blocks of every structure which reaches into the middle chunk of the neighborhood, in the
+    /// middle chunk's coordinates. Structures are placed on a grid which only depends on the seed,
+    /// so every chunk agrees on where the structures around it are.
     pub fn get_structure_blocks(
         &self,
-        blocks: &Neighborhood<Terrain>,
-        noise: &Neighborhood<Noise3d>,
+        seed: u32,
+        chunk_pos: IVec3,
+        terrain: &Neighborhood<Terrain>,
+        biomes: &Neighborhood<BiomeMap>,
     ) -> Vec<(Block, [usize; 3])> {
-        self.get_structures(blocks, noise)
-            .flat_map(|(structure, [x0, y0, z0])| {
-                structure
+        let candidates = self.find_candidates(seed, chunk_pos, terrain, biomes);
+        candidates
+            .iter()
+            .filter(|candidate| !self.is_blocked(candidate, &candidates))
+            .flat_map(|placement| {
+                let structure_type = self.0[placement.rule_index].structure_type;
+                structure_type
+                    .create(placement.hash)
                     .get_blocks()
-                    .iter()
-                    .map(|(block, [x, y, z])| {
-                        let x = x0 + x;
-                        let y = y0 + y;
-                        let z = z0 + z;
-                        (*block, [x, y, z])
-                    })
-                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(move |(block, offset)| (block, placement.pos + IVec3::from(offset)))
             })
-            .filter_map(|(block, [x, y, z])| {
-                let x = usize::try_from(x).ok()?;
-                let y = usize::try_from(y).ok()?;
-                let z = usize::try_from(z).ok()?;
+            .filter_map(|(block, pos)| {
+                let x = usize::try_from(pos.x).ok()?;
+                let y = usize::try_from(pos.y).ok()?;
+                let z = usize::try_from(pos.z).ok()?;
                 if x >= CHUNK_SIZE || y >= CHUNK_SIZE || z >= CHUNK_SIZE {
                     return None;
                 }
-                return Some((block, [x, y, z]));
+                Some((block, [x, y, z]))
             })
-            .collect::<_>()
+            .collect()
     }
 
-    fn get_structures<'a>(
+    fn find_candidates(
         &self,
-        blocks: &'a Neighborhood<Terrain>,
-        noise: &'a Neighborhood<Noise3d>,
-    ) -> impl Iterator<Item = (Structure, [i32; 3])> + use<'a> {
-        match self {
-            StructureType::Tree => {
-                const NUM_TREES_PER_CHUNK: usize = 20;
-                let tree = Structure::Tree {
-                    trunk_height: 4,
-                    leaf_radius: 2,
-                };
-                let range = -1..=1;
-                let chunk_positions = range
-                    .clone()
-                    .cartesian_product(range.clone())
-                    .cartesian_product(range.clone());
-                chunk_positions
-                    .map(|((x, y), z)| (x, y, z))
-                    .flat_map(|(x_chunk, y_chunk, z_chunk)| {
-                        let blocks = blocks
-                            .get_chunk(x_chunk, y_chunk, z_chunk)
-                            .clone()
-                            .expect("Full block neighborhood");
-                        let noise = noise
-                            .get_chunk(x_chunk, y_chunk, z_chunk)
-                            .clone()
-                            .expect("Full noise neighborhood");
-                        return generate_tree_spots_xyz(
-                            NUM_TREES_PER_CHUNK,
-                            noise.as_ref(),
-                            blocks.as_ref(),
-                        )
-                        .map(move |[x_local, y_local, z_local]| {
-                            [
-                                x_local as i32 + CHUNK_SIZE_I32 * x_chunk,
-                                y_local as i32 + CHUNK_SIZE_I32 * y_chunk,
-                                z_local as i32 + CHUNK_SIZE_I32 * z_chunk,
-                            ]
-                        })
-                        .collect::<Vec<_>>();
-                    })
-                    .map(move |pos| (tree.clone(), pos))
+        seed: u32,
+        chunk_pos: IVec3,
+        terrain: &Neighborhood<Terrain>,
+        biomes: &Neighborhood<BiomeMap>,
+    ) -> Vec<Placement> {
+        let origin = chunk_pos.xz() * CHUNK_SIZE_I32;
+        let min = origin - IVec2::splat(CANDIDATE_MARGIN);
+        let max = origin + IVec2::splat(CHUNK_SIZE_I32 + CANDIDATE_MARGIN - 1);
+        let mut candidates = vec![];
+        for (rule_index, rule) in self.0.iter().enumerate() {
+            let spacing = rule.spacing.max(1);
+            // Structures are kept in the first half of each cell
+            let jitter = (spacing as u32).div_ceil(2);
+            let min_cell = min.div_euclid(IVec2::splat(spacing));
+            let max_cell = max.div_euclid(IVec2::splat(spacing));
+            for cell_x in min_cell.x..=max_cell.x {
+                for cell_z in min_cell.y..=max_cell.y {
+                    let hash = cell_hash(seed, rule_index, cell_x, cell_z);
+                    let offset = IVec2::new(
+                        (nth_random(hash, 0) % jitter) as i32,
+                        (nth_random(hash, 1) % jitter) as i32,
+                    );
+                    let column = IVec2::new(cell_x, cell_z) * spacing + offset - origin;
+                    if column.cmplt(IVec2::splat(-CANDIDATE_MARGIN)).any()
+                        || column.cmpge(IVec2::splat(CHUNK_SIZE_I32 + CANDIDATE_MARGIN)).any()
+                    {
+                        continue;
+                    }
+                    let Some(biome) = biome_at(biomes, column) else {
+                        continue;
+                    };
+                    let chance = (nth_random(hash, 2) % 1000) as f32 / 1000.0;
+                    if chance >= rule.density(biome) {
+                        continue;
+                    }
+                    // A column can have ground in each chunk above another, for example on
+                    // the floor of a cave which is open to the sky
+                    for chunk_y in -1..=1 {
+                        let Some(ground_y) = find_ground(terrain, column, chunk_y) else {
+                            continue;
+                        };
+                        let pos = IVec3::new(column.x, ground_y + 1, column.y);
+                        if !rule_allows(rule, terrain, pos) {
+                            continue;
+                        }
+                        candidates.push(Placement {
+                            rule_index,
+                            pos,
+                            hash: nth_random(nth_random(hash, 3), (chunk_pos.y + chunk_y) as u32),
+                        });
+                    }
+                }
             }
         }
+        candidates
     }
-}
 
-fn generate_tree_spots_xyz<'a>(
-    count: usize,
-    noise: &'a Noise3d,
-    blocks: &'a Terrain,
-) -> impl Iterator<Item = [u32; 3]> + use<'a> {
-    generate_tree_spots_xz(noise)
-        .take(count)
-        .filter_map(|xz| find_grass_block(xz, blocks))
-        .map(|[x, y, z]| [x, y + 1, z])
+    /// Whether a structure of a rule which comes first would overlap the candidate. The blocking
+    /// structure doesn't have to be placed itself, so that the result doesn't depend on
+    /// structures outside of the neighborhood.
+    fn is_blocked(&self, candidate: &Placement, candidates: &[Placement]) -> bool {
+        let radius = self.0[candidate.rule_index]
+            .structure_type
+            .radius();
+        candidates
+            .iter()
+            .filter(|other| other.rule_index < candidate.rule_index)
+            .any(|other| {
+                let other_radius = self.0[other.rule_index]
+                    .structure_type
+                    .radius();
+                let distance = (other.pos - candidate.pos).abs();
+                distance.x <= radius + other_radius
+                    && distance.z <= radius + other_radius
+                    && distance.y <= 2 * MAX_STRUCTURE_RADIUS
+            })
+    }
 }
 
-fn generate_tree_spots_xz(noise: &Noise3d) -> impl Iterator<Item = [u32; 2]> + use<'_> {
-    noise
-        .0
-        .iter()
-        .map(|x| fast_hash_f32(*x))
-        .map(|x| {
-            let z = fast_hash(x);
-            [x % CHUNK_SIZE_U32, z % CHUNK_SIZE_U32]
+fn rule_allows(rule: &StructureRule, terrain: &Neighborhood<Terrain>, pos: IVec3) -> bool {
+    let Some(ground) = terrain_at(terrain, pos - IVec3::Y) else {
+        return false;
+    };
+    if !rule.ground.contains(ground) {
+        return false;
+    }
+    // The ground around the structure must be within reach of the ground under it
+    let radius = rule
+        .structure_type
+        .radius()
+        .max(1);
+    [
+        IVec3::new(radius, 0, 0),
+        IVec3::new(-radius, 0, 0),
+        IVec3::new(0, 0, radius),
+        IVec3::new(0, 0, -radius),
+    ]
+    .into_iter()
+    .all(|offset| {
+        (-rule.max_slope..=rule.max_slope).any(|dy| {
+            let surface = pos + offset + IVec3::new(0, dy, 0);
+            terrain_at(terrain, surface - IVec3::Y)
+                .is_some_and(|block| block.is_solid() && block != &Block::Water)
+                && terrain_at(terrain, surface).is_some_and(|block| block == &Block::Air)
         })
+    })
 }
 
-fn find_grass_block([x, z]: [u32; 2], blocks: &Terrain) -> Option<[u32; 3]> {
-    let x = x as usize;
-    let z = z as usize;
+/// `Neighborhood::at_pos` only works for positions inside the neighborhood
+fn terrain_at(terrain: &Neighborhood<Terrain>, pos: IVec3) -> Option<&Block> {
+    let inside = pos.cmpge(IVec3::splat(-CHUNK_SIZE_I32)).all()
+        && pos.cmplt(IVec3::splat(2 * CHUNK_SIZE_I32)).all();
+    inside
+        .then(|| terrain.at_pos(pos))
+        .flatten()
+}
+
+/// The highest block of a column within one chunk of the neighborhood which has air above it, in
+/// the coordinates of the middle chunk. Only the chunk's own blocks are looked at so that every
+/// chunk finds the same ground.
+fn find_ground(terrain: &Neighborhood<Terrain>, column: IVec2, chunk_y: i32) -> Option<i32> {
+    let chunk_x = column.x.div_euclid(CHUNK_SIZE_I32);
+    let chunk_z = column.y.div_euclid(CHUNK_SIZE_I32);
+    let x = column.x.rem_euclid(CHUNK_SIZE_I32) as usize;
+    let z = column.y.rem_euclid(CHUNK_SIZE_I32) as usize;
+    let blocks = terrain
+        .get_chunk(chunk_x, chunk_y, chunk_z)
+        .as_ref()?;
     (0..CHUNK_SIZE - 1)
         .rev()
-        .skip_while(|y| blocks.at_pos([x, *y, z]) == &Block::Air)
-        .next()
+        .find(|y| blocks.at_pos([x, *y, z]) != &Block::Air)
         .filter(|y| blocks.at_pos([x, *y + 1, z]) == &Block::Air)
-        .filter(|y| {
-            let block = blocks.at_pos([x, *y, z]);
-            match block {
-                &Block::Grass | &Block::Dirt => true,
-                _ => false,
-            }
-        })
-        .map(|y| [x as u32, y as u32, z as u32])
+        .map(|y| y as i32 + chunk_y * CHUNK_SIZE_I32)
+}
+
+fn biome_at(biomes: &Neighborhood<BiomeMap>, column: IVec2) -> Option<Biome> {
+    let chunk_x = column.x.div_euclid(CHUNK_SIZE_I32);
+    let chunk_z = column.y.div_euclid(CHUNK_SIZE_I32);
+    let x = column.x.rem_euclid(CHUNK_SIZE_I32) as usize;
+    let z = column.y.rem_euclid(CHUNK_SIZE_I32) as usize;
+    biomes
+        .get_chunk(chunk_x, 0, chunk_z)
+        .as_ref()
+        .map(|biome_map| *biome_map.at_pos([x, z]))
+}
+
+fn cell_hash(seed: u32, rule_index: usize, cell_x: i32, cell_z: i32) -> u32 {
+    let hash = mix_hash(seed ^ mix_hash(rule_index as u32));
+    let hash = mix_hash(hash ^ cell_x as u32);
+    mix_hash(hash.wrapping_add(mix_hash(cell_z as u32)))
 }
@@ -0,0 +1,380 @@
+//! Structures which are too large for a chunk's neighborhood, like villages, dungeons and towers.
+//! Each region of the world has at most one structure start, which is planned from the seed and
+//! the terrain noise alone. Every chunk plans the starts around it again and stamps the part of
+//! each piece which it contains, so the pieces line up whichever order the chunks load in.
+
+use bevy::prelude::*;
+
+use super::cell_hash;
+use crate::{
+    block::Block,
+    chunk::CHUNK_SIZE_I32,
+    utils::{nth_random, VolumetricRange},
+    world::{biome::Biome, SurfaceSampler},
+};
+
+/// Width of the square regions of the world which each get at most one structure start
+pub const START_REGION_SIZE: i32 = 256;
+/// No piece of a start reaches further than this from the start's centre horizontally. Centres
+/// are kept this far from the edge of their region, so starts never overlap each other.
+const MAX_START_EXTENT: i32 = 64;
+/// Keeps the hashes of starts apart from the hashes of the structure registry's rules
+const START_SALT: usize = 0x5354_4152;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartType {
+    /// Houses around a well
+    Village,
+    /// Stone rooms joined by corridors, deep underground
+    Dungeon,
+    /// A tall stone tower with wooden floors
+    Tower,
+}
+
+impl StartType {
+    /// Picks the type of a region's start from a random number, if it has one
+    fn from_roll(roll: u32) -> Option<Self> {
+        match roll % 100 {
+            0..25 => Some(Self::Village),
+            25..55 => Some(Self::Dungeon),
+            55..75 => Some(Self::Tower),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Piece {
+    /// A hollow box of stone
+    Room { min: IVec3, max: IVec3 },
+    /// Air with a stone floor. Corridors come after the rooms they join, so they carve doorways.
+    Corridor { min: IVec3, max: IVec3 },
+    /// `ground` is the highest block of land under the middle of the tower
+    Tower {
+        ground: IVec3,
+        radius: i32,
+        height: i32,
+    },
+    /// `door` is the side of the house which the door is on, as a unit vector
+    House {
+        min: IVec3,
+        max: IVec3,
+        door: IVec2,
+    },
+    Well { ground: IVec3 },
+}
+
+impl Piece {
+    const TOWER_FOUNDATION_DEPTH: i32 = 6;
+    const TOWER_FLOOR_SPACING: i32 = 6;
+    const HOUSE_FOUNDATION_DEPTH: i32 = 3;
+    const HOUSE_WALL_HEIGHT: i32 = 3;
+    const WELL_RADIUS: i32 = 2;
+    const WELL_DEPTH: i32 = 4;
+
+    /// The smallest and largest positions which the piece can place blocks at, inclusive
+    pub fn bounds(&self) -> (IVec3, IVec3) {
+        match self {
+            Piece::Room { min, max } | Piece::Corridor { min, max } => (*min, *max),
+            Piece::Tower {
+                ground,
+                radius,
+                height,
+            } => (
+                ground - IVec3::new(*radius, Self::TOWER_FOUNDATION_DEPTH, *radius),
+                ground + IVec3::new(*radius, height + 1, *radius),
+            ),
+            Piece::House { min, max, .. } => (
+                min - IVec3::Y * Self::HOUSE_FOUNDATION_DEPTH,
+                max + IVec3::Y * (Self::HOUSE_WALL_HEIGHT + 1),
+            ),
+            Piece::Well { ground } => (
+                ground - IVec3::new(Self::WELL_RADIUS, Self::WELL_DEPTH, Self::WELL_RADIUS),
+                ground + IVec3::new(Self::WELL_RADIUS, 1, Self::WELL_RADIUS),
+            ),
+        }
+    }
+
+    /// The block which the piece places at a position inside its bounds, if any
+    pub fn block_at(&self, pos: IVec3) -> Option<Block> {
+        match self {
+            Piece::Room { min, max } => {
+                let is_shell = pos.cmpeq(*min).any() || pos.cmpeq(*max).any();
+                Some(if is_shell { Block::Stone } else { Block::Air })
+            }
+            Piece::Corridor { min, .. } => Some(if pos.y == min.y {
+                Block::Stone
+            } else {
+                Block::Air
+            }),
+            Piece::Tower {
+                ground,
+                radius,
+                height,
+            } => {
+                let offset = pos - ground;
+                let distance_squared = offset.x * offset.x + offset.z * offset.z;
+                let inner_radius = radius - 1;
+                if distance_squared > radius * radius + radius {
+                    return None;
+                }
+                let is_wall = distance_squared > inner_radius * inner_radius + inner_radius;
+                if offset.y <= 0 {
+                    return Some(Block::Stone);
+                }
+                if offset.y == height + 1 {
+                    // Battlements
+                    return (is_wall && (offset.x + offset.z) % 2 == 0).then_some(Block::Stone);
+                }
+                if !is_wall {
+                    let is_floor = offset.y == *height || offset.y % Self::TOWER_FLOOR_SPACING == 0;
+                    return Some(if is_floor { Block::Wood } else { Block::Air });
+                }
+                let is_door = offset.x == 0 && offset.z < 0 && offset.y <= 2;
+                let is_window = (offset.x == 0 || offset.z == 0)
+                    && offset.y % Self::TOWER_FLOOR_SPACING == 3
+                    && offset.y < *height;
+                Some(if is_door || is_window {
+                    Block::Air
+                } else {
+                    Block::Stone
+                })
+            }
+            Piece::House { min, max, door } => {
+                let y = pos.y - min.y;
+                if y <= 0 {
+                    return Some(Block::Stone);
+                }
+                if y == Self::HOUSE_WALL_HEIGHT + 1 {
+                    return Some(Block::Wood);
+                }
+                let is_wall = pos.x == min.x || pos.z == min.z || pos.x == max.x || pos.z == max.z;
+                if !is_wall {
+                    return Some(Block::Air);
+                }
+                let middle = (min.xz() + max.xz()) / 2;
+                let door_pos = IVec2::new(
+                    [min.x, middle.x, max.x][(door.x + 1) as usize],
+                    [min.z, middle.y, max.z][(door.y + 1) as usize],
+                );
+                let is_door = pos.xz() == door_pos && y <= 2;
+                Some(if is_door { Block::Air } else { Block::Wood })
+            }
+            Piece::Well { ground } => {
+                let offset = pos - ground;
+                if offset.x.abs() == Self::WELL_RADIUS
+                    || offset.z.abs() == Self::WELL_RADIUS
+                    || offset.y == -Self::WELL_DEPTH
+                {
+                    Some(Block::Stone)
+                } else if offset.y <= 0 {
+                    Some(Block::Water)
+                } else {
+                    Some(Block::Air)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StructureStart {
+    pub start_type: StartType,
+    /// The middle of the start, which none of its pieces are further than `MAX_START_EXTENT` from
+    /// horizontally
+    pub centre: IVec2,
+    /// Pieces which come later are stamped over pieces which come before them
+    pub pieces: Vec<Piece>,
+}
+
+/// Plans the structure start of a region, if it has one
+pub fn plan_start(seed: u32, region: IVec2, sampler: &SurfaceSampler) -> Option<StructureStart> {
+    let hash = cell_hash(seed, START_SALT, region.x, region.y);
+    let start_type = StartType::from_roll(nth_random(hash, 0))?;
+    let centre_range = (START_REGION_SIZE - 2 * MAX_START_EXTENT) as u32;
+    let centre = region * START_REGION_SIZE
+        + IVec2::splat(MAX_START_EXTENT)
+        + IVec2::new(
+            (nth_random(hash, 1) % centre_range) as i32,
+            (nth_random(hash, 2) % centre_range) as i32,
+        );
+    let hash = nth_random(hash, 3);
+    let pieces = match start_type {
+        StartType::Village => plan_village(hash, centre, sampler)?,
+        StartType::Dungeon => plan_dungeon(hash, centre, sampler)?,
+        StartType::Tower => plan_tower(hash, centre, sampler)?,
+    };
+    Some(StructureStart {
+        start_type,
+        centre,
+        pieces,
+    })
+}
+
+fn plan_village(hash: u32, centre: IVec2, sampler: &SurfaceSampler) -> Option<Vec<Piece>> {
+    const HOUSE_DISTANCE: u32 = 32;
+    const MAX_HOUSE_SLOPE: i32 = 2;
+    if !matches!(
+        sampler.biome(centre),
+        Biome::Plains | Biome::Savanna | Biome::Desert
+    ) {
+        return None;
+    }
+    let ground = sampler.ground_height(centre)?;
+    let mut pieces = vec![Piece::Well {
+        ground: IVec3::new(centre.x, ground, centre.y),
+    }];
+    let house_count = 4 + nth_random(hash, 0) % 5;
+    for i in 0..house_count {
+        let random = |n: u32, range: u32| nth_random(hash, 8 * (i + 1) + n) % range;
+        let size = IVec2::new(5 + random(0, 3) as i32, 5 + random(1, 3) as i32);
+        let min = centre - IVec2::splat(HOUSE_DISTANCE as i32)
+            + IVec2::new(
+                random(2, 2 * HOUSE_DISTANCE - size.x as u32) as i32,
+                random(3, 2 * HOUSE_DISTANCE - size.y as u32) as i32,
+            );
+        let max = min + size - IVec2::ONE;
+        let overlaps = pieces.iter().any(|piece| {
+            let (other_min, other_max) = piece.bounds();
+            (min - IVec2::ONE).cmple(other_max.xz()).all()
+                && (max + IVec2::ONE).cmpge(other_min.xz()).all()
+        });
+        if overlaps {
+            continue;
+        }
+        // Houses are built on fairly flat ground above the sea
+        let middle = (min + max) / 2;
+        let Some(ground) = sampler.ground_height(middle) else {
+            continue;
+        };
+        let is_flat = [min, max, IVec2::new(min.x, max.y), IVec2::new(max.x, min.y)]
+            .into_iter()
+            .all(|corner| {
+                sampler
+                    .ground_height(corner)
+                    .is_some_and(|height| (height - ground).abs() <= MAX_HOUSE_SLOPE)
+            });
+        if !is_flat {
+            continue;
+        }
+        // The door faces the well
+        let to_centre = centre - middle;
+        let door = if to_centre.x.abs() > to_centre.y.abs() {
+            IVec2::new(to_centre.x.signum(), 0)
+        } else {
+            IVec2::new(0, to_centre.y.signum())
+        };
+        pieces.push(Piece::House {
+            min: IVec3::new(min.x, ground, min.y),
+            max: IVec3::new(max.x, ground, max.y),
+            door,
+        });
+    }
+    Some(pieces)
+}
+
+fn plan_dungeon(hash: u32, centre: IVec2, sampler: &SurfaceSampler) -> Option<Vec<Piece>> {
+    const MIN_FLOOR: i32 = -140;
+    const MAX_FLOOR: i32 = -40;
+    const ROOM_HEIGHT: i32 = 5;
+    const CORRIDOR_HEIGHT: i32 = 3;
+    const DIRECTIONS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+    // Dungeons are dug out of the solid ground far below the surface, which only presets with
+    // continents have. Elsewhere the rooms would hang in the void.
+    if !sampler.preset().has_continents() {
+        return None;
+    }
+    let random = |n: u32, range: u32| nth_random(hash, n) % range;
+    let floor = MIN_FLOOR + random(0, (MAX_FLOOR - MIN_FLOOR) as u32) as i32;
+    let room_count = 3 + random(1, 3);
+    let mut room_centres = vec![centre];
+    for i in 1..room_count {
+        let direction = DIRECTIONS[random(2 * i, 4) as usize];
+        let step = 10 + random(2 * i + 1, 5) as i32;
+        room_centres.push(room_centres[i as usize - 1] + direction * step);
+    }
+    let mut pieces: Vec<Piece> = room_centres
+        .iter()
+        .enumerate()
+        .map(|(i, room_centre)| {
+            let half_size = IVec2::new(
+                3 + random(16 + 2 * i as u32, 3) as i32,
+                3 + random(17 + 2 * i as u32, 3) as i32,
+            );
+            Piece::Room {
+                min: IVec3::new(
+                    room_centre.x - half_size.x,
+                    floor,
+                    room_centre.y - half_size.y,
+                ),
+                max: IVec3::new(
+                    room_centre.x + half_size.x,
+                    floor + ROOM_HEIGHT,
+                    room_centre.y + half_size.y,
+                ),
+            }
+        })
+        .collect();
+    // Corridors go along x and then along z
+    for pair in room_centres.windows(2) {
+        let [from, to] = [pair[0], pair[1]];
+        let corner = IVec2::new(to.x, from.y);
+        for (a, b) in [(from, corner), (corner, to)] {
+            pieces.push(Piece::Corridor {
+                min: IVec3::new(a.x.min(b.x) - 1, floor, a.y.min(b.y) - 1),
+                max: IVec3::new(a.x.max(b.x) + 1, floor + CORRIDOR_HEIGHT, a.y.max(b.y) + 1),
+            });
+        }
+    }
+    Some(pieces)
+}
+
+fn plan_tower(hash: u32, centre: IVec2, sampler: &SurfaceSampler) -> Option<Vec<Piece>> {
+    let ground = sampler.ground_height(centre)?;
+    Some(vec![Piece::Tower {
+        ground: IVec3::new(centre.x, ground, centre.y),
+        radius: 3,
+        height: 16 + (nth_random(hash, 0) % 12) as i32,
+    }])
+}
+
+/// Blocks of every structure start's pieces which reach into a chunk, in the chunk's coordinates
+pub fn stamp_starts(
+    seed: u32,
+    chunk_pos: IVec3,
+    sampler: &SurfaceSampler,
+) -> Vec<(Block, [usize; 3])> {
+    let chunk_min = chunk_pos * CHUNK_SIZE_I32;
+    let chunk_max = chunk_min + IVec3::splat(CHUNK_SIZE_I32 - 1);
+    let min_region = (chunk_min.xz() - IVec2::splat(MAX_START_EXTENT))
+        .div_euclid(IVec2::splat(START_REGION_SIZE));
+    let max_region = (chunk_max.xz() + IVec2::splat(MAX_START_EXTENT))
+        .div_euclid(IVec2::splat(START_REGION_SIZE));
+    let mut blocks = vec![];
+    for region_x in min_region.x..=max_region.x {
+        for region_z in min_region.y..=max_region.y {
+            let region = IVec2::new(region_x, region_z);
+            let Some(start) = plan_start(seed, region, sampler) else {
+                continue;
+            };
+            for piece in start.pieces.iter() {
+                let (min, max) = piece.bounds();
+                let min = min.max(chunk_min);
+                let max = max.min(chunk_max);
+                if min.cmpgt(max).any() {
+                    continue;
+                }
+                for (x, y, z) in
+                    VolumetricRange::new(min.x..max.x + 1, min.y..max.y + 1, min.z..max.z + 1)
+                {
+                    let pos = IVec3::new(x, y, z);
+                    if let Some(block) = piece.block_at(pos) {
+                        let [x, y, z] = (pos - chunk_min).to_array();
+                        blocks.push((block, [x as usize, y as usize, z as usize]));
+                    }
+                }
+            }
+        }
+    }
+    blocks
+}
@@ -8,6 +8,8 @@ mod health;
 mod hotbar;
 mod main_menu;
 mod pause_menu;
+mod settings;
+mod singleplayer;
 
 pub struct UiPlugin;
 
@@ -20,24 +22,13 @@ impl Plugin for UiPlugin {
             block_icons::BlockIconPlugin,
             main_menu::MainMenuPlugin,
             pause_menu::PauseMenuPlugin,
+            settings::SettingsPlugin,
+            singleplayer::SingleplayerPlugin,
         ))
         .add_systems(Startup, (spawn_ui_camera, (setup, create_ui_root)).chain())
         .add_systems(Update, update_button_colour)
-        .add_systems(
-            OnTransition {
-                exited: AppState::MainMenu,
-                entered: AppState::InGame,
-            },
-            despawn_ui_camera,
-        )
-        .add_systems(
-            OnTransition {
-                exited: AppState::InGame,
-                entered: AppState::MainMenu,
-            },
-            spawn_ui_camera,
-        )
-        .add_systems(OnExit(AppState::InGame), despawn_hud);
+        .add_systems(OnEnter(AppState::InGame), despawn_ui_camera)
+        .add_systems(OnExit(AppState::InGame), (spawn_ui_camera, despawn_hud));
     }
 }
 
@@ -11,7 +11,7 @@ impl Plugin for MainMenuPlugin {
         app.add_systems(Startup, setup_assets)
             .add_systems(OnEnter(AppState::MainMenu), setup_main_menu)
             .add_systems(OnExit(AppState::MainMenu), tear_down_main_menu)
-            .add_systems(Update, (play_button, quit_button));
+            .add_systems(Update, (singleplayer_button, settings_button, quit_button));
     }
 }
 
@@ -77,10 +77,10 @@ fn setup_main_menu(
                             ..default()
                         })
                         .with_children(|buttons| {
-                            // Play button
+                            // Singleplayer button
                             buttons
                                 .spawn((
-                                    PlayButton,
+                                    SingleplayerButton,
                                     Node {
                                         width: BUTTON_WIDTH,
                                         height: BUTTON_HEIGHT,
@@ -89,7 +89,31 @@ fn setup_main_menu(
                                 ))
                                 .with_children(|text_builder| {
                                     text_builder.spawn((
-                                        Text::new("Play"),
+                                        Text::new("Singleplayer"),
+                                        TextFont {
+                                            font: font.0.clone(),
+                                            ..default()
+                                        },
+                                        TextLayout::new_with_justify(JustifyText::Center),
+                                        Node {
+                                            width: HUNDRED_PERCENT,
+                                            ..default()
+                                        },
+                                    ));
+                                });
+                            // Settings button
+                            buttons
+                                .spawn((
+                                    SettingsButton,
+                                    Node {
+                                        width: BUTTON_WIDTH,
+                                        height: BUTTON_HEIGHT,
+                                        ..Default::default()
+                                    },
+                                ))
+                                .with_children(|text_builder| {
+                                    text_builder.spawn((
+                                        Text::new("Settings"),
                                         TextFont {
                                             font: font.0.clone(),
                                             ..default()
@@ -138,15 +162,30 @@ fn tear_down_main_menu(q_root: Query<Entity, With<MainMenu>>, mut commands: Comm
 
 #[derive(Component)]
 #[require(Button)]
-struct PlayButton;
+struct SingleplayerButton;
+
+fn singleplayer_button(
+    q_button: Query<&Interaction, (With<SingleplayerButton>, Changed<Interaction>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for interaction in q_button.iter() {
+        if let Interaction::Pressed = interaction {
+            next_state.set(AppState::Singleplayer);
+        }
+    }
+}
+
+#[derive(Component)]
+#[require(Button)]
+struct SettingsButton;
 
-fn play_button(
-    q_button: Query<&Interaction, (With<PlayButton>, Changed<Interaction>)>,
+fn settings_button(
+    q_button: Query<&Interaction, (With<SettingsButton>, Changed<Interaction>)>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
     for interaction in q_button.iter() {
         if let Interaction::Pressed = interaction {
-            next_state.set(AppState::InGame);
+            next_state.set(AppState::Settings);
         }
     }
 }
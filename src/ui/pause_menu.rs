@@ -3,24 +3,33 @@ use bevy::prelude::*;
 use crate::{
     state::{AppState, InGameState},
     ui::{Ui, UiFont},
+    world::save::PendingWrites,
 };
 pub struct PauseMenuPlugin;
 
 impl Plugin for PauseMenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<FadeInTimer>()
+            .init_resource::<SaveProgress>()
             .add_systems(OnEnter(InGameState::Paused), spawn_menu)
             .add_systems(OnExit(InGameState::Paused), tear_down_menu)
+            .add_systems(OnEnter(InGameState::Saving), spawn_saving_screen)
+            .add_systems(OnExit(InGameState::Saving), tear_down_saving_screen)
             .add_systems(
                 Update,
                 (
                     update_timer,
                     fade_in_pause_menu_elements,
                     resume_game,
-                    go_to_main_menu,
+                    open_settings,
+                    save_and_quit,
                     quit_game,
                 )
                     .run_if(in_state(InGameState::Paused)),
+            )
+            .add_systems(
+                Update,
+                update_save_progress.run_if(in_state(InGameState::Saving)),
             );
     }
 }
@@ -46,7 +55,11 @@ struct ResumeButton;
 
 #[derive(Component)]
 #[require(Button)]
-struct MainMenuButton;
+struct SettingsButton;
+
+#[derive(Component)]
+#[require(Button)]
+struct SaveAndQuitButton;
 
 #[derive(Component)]
 #[require(Button)]
@@ -111,13 +124,28 @@ fn spawn_menu(mut commands: Commands, ui_font: Res<UiFont>) {
                     ));
                     spawner.spawn((
                         Ui,
-                        MainMenuButton,
+                        SettingsButton,
+                        Node {
+                            height: BUTTON_HEIGHT,
+                            width: Val::Percent(100.0),
+                            ..default()
+                        },
+                        Text::new("Settings"),
+                        TextLayout::new_with_justify(JustifyText::Center),
+                        TextFont {
+                            font: ui_font.0.clone_weak(),
+                            ..default()
+                        },
+                    ));
+                    spawner.spawn((
+                        Ui,
+                        SaveAndQuitButton,
                         Node {
                             height: BUTTON_HEIGHT,
                             width: Val::Percent(100.0),
                             ..default()
                         },
-                        Text::new("Exit to Main Menu"),
+                        Text::new("Save and Quit to Title"),
                         TextLayout::new_with_justify(JustifyText::Center),
                         TextFont {
                             font: ui_font.0.clone_weak(),
@@ -173,12 +201,21 @@ fn resume_game(
     };
 }
 
-fn go_to_main_menu(
-    q_button: Query<&Interaction, (With<MainMenuButton>, Changed<Interaction>)>,
-    mut next_state: ResMut<NextState<AppState>>,
+fn open_settings(
+    q_button: Query<&Interaction, (With<SettingsButton>, Changed<Interaction>)>,
+    mut next_state: ResMut<NextState<InGameState>>,
 ) {
     if let Ok(Interaction::Pressed) = q_button.single() {
-        next_state.set(AppState::MainMenu);
+        next_state.set(InGameState::Settings);
+    };
+}
+
+fn save_and_quit(
+    q_button: Query<&Interaction, (With<SaveAndQuitButton>, Changed<Interaction>)>,
+    mut next_state: ResMut<NextState<InGameState>>,
+) {
+    if let Ok(Interaction::Pressed) = q_button.single() {
+        next_state.set(InGameState::Saving);
     };
 }
 
@@ -190,3 +227,66 @@ fn quit_game(
         quit_events.write(AppExit::Success);
     };
 }
+
+#[derive(Component)]
+struct SavingScreenRoot;
+
+#[derive(Component)]
+struct SaveProgressText;
+
+/// The most chunk writes which have been pending at once since saving started
+#[derive(Resource, Default)]
+struct SaveProgress {
+    total: usize,
+}
+
+fn spawn_saving_screen(mut commands: Commands, ui_font: Res<UiFont>) {
+    commands
+        .spawn((
+            Ui,
+            SavingScreenRoot,
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(PAUSE_BACKGROUND_ALPHA)),
+        ))
+        .with_children(|spawner| {
+            spawner.spawn((
+                Ui,
+                SaveProgressText,
+                Text::new("Saving world..."),
+                TextLayout::new_with_justify(JustifyText::Center),
+                TextFont {
+                    font: ui_font.0.clone_weak(),
+                    ..default()
+                },
+            ));
+        });
+    commands.insert_resource(SaveProgress::default());
+}
+
+fn tear_down_saving_screen(mut commands: Commands, q_root: Query<Entity, With<SavingScreenRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn update_save_progress(
+    pending_writes: Res<PendingWrites>,
+    mut progress: ResMut<SaveProgress>,
+    mut q_text: Query<&mut Text, With<SaveProgressText>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    progress.total = progress.total.max(pending_writes.len());
+    let saved = progress.total - pending_writes.len();
+    for mut text in q_text.iter_mut() {
+        text.0 = format!("Saving world... {}/{} chunks", saved, progress.total);
+    }
+    if pending_writes.is_empty() {
+        next_state.set(AppState::MainMenu);
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::{
+    state::{AppState, InGameState},
+    world::render_distance::RenderDistance,
+};
+
+use super::{Ui, UiFont};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Settings), spawn_settings_screen)
+            .add_systems(OnExit(AppState::Settings), tear_down_settings_screen)
+            .add_systems(OnEnter(InGameState::Settings), spawn_settings_screen)
+            .add_systems(OnExit(InGameState::Settings), tear_down_settings_screen)
+            .add_systems(
+                Update,
+                (
+                    press_button,
+                    close_settings.run_if(input_just_pressed(KeyCode::Escape)),
+                    update_render_distance_text.run_if(resource_changed::<RenderDistance>),
+                )
+                    .run_if(in_state(AppState::Settings).or(in_state(InGameState::Settings))),
+            );
+    }
+}
+
+#[derive(Component)]
+struct SettingsRoot;
+
+#[derive(Component, Clone, Copy)]
+#[require(Button)]
+enum SettingsButton {
+    DecreaseHorizontal,
+    IncreaseHorizontal,
+    DecreaseVertical,
+    IncreaseVertical,
+    Done,
+}
+
+#[derive(Component, Clone, Copy)]
+enum RenderDistanceText {
+    Horizontal,
+    Vertical,
+}
+
+impl RenderDistanceText {
+    fn text(&self, render_distance: &RenderDistance) -> String {
+        match self {
+            Self::Horizontal => format!("Horizontal: {} chunks", render_distance.horizontal),
+            Self::Vertical => format!("Vertical: {} chunks", render_distance.vertical),
+        }
+    }
+}
+
+const SETTING_SPACING: Val = Val::Px(20.0);
+const BUTTON_SPACING: Val = Val::Px(10.0);
+const BUTTON_HEIGHT: Val = Val::Px(25.0);
+const SMALL_BUTTON_WIDTH: Val = Val::Px(25.0);
+const VALUE_WIDTH: Val = Val::Px(200.0);
+const DONE_BUTTON_WIDTH: Val = Val::Px(150.0);
+const SETTINGS_BACKGROUND_ALPHA: f32 = 0.75;
+
+fn spawn_settings_screen(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    render_distance: Res<RenderDistance>,
+    in_game: Option<Res<State<InGameState>>>,
+) {
+    // The world stays visible behind the screen while in game, darkened like the pause menu
+    let background = match in_game {
+        Some(_) => Color::BLACK.with_alpha(SETTINGS_BACKGROUND_ALPHA),
+        None => Color::NONE,
+    };
+    commands
+        .spawn((
+            Ui,
+            SettingsRoot,
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: SETTING_SPACING,
+                ..default()
+            },
+            BackgroundColor(background),
+        ))
+        .with_children(|spawner| {
+            spawner.spawn((
+                Text::new("Settings"),
+                TextFont {
+                    font_size: 30.0,
+                    font: ui_font.0.clone_weak(),
+                    ..default()
+                },
+            ));
+            spawner.spawn((
+                Text::new("Render Distance"),
+                TextFont {
+                    font: ui_font.0.clone_weak(),
+                    ..default()
+                },
+            ));
+            spawn_setting_row(
+                spawner,
+                &ui_font,
+                &render_distance,
+                RenderDistanceText::Horizontal,
+                (
+                    SettingsButton::DecreaseHorizontal,
+                    SettingsButton::IncreaseHorizontal,
+                ),
+            );
+            spawn_setting_row(
+                spawner,
+                &ui_font,
+                &render_distance,
+                RenderDistanceText::Vertical,
+                (
+                    SettingsButton::DecreaseVertical,
+                    SettingsButton::IncreaseVertical,
+                ),
+            );
+            spawn_button(
+                spawner,
+                &ui_font,
+                SettingsButton::Done,
+                DONE_BUTTON_WIDTH,
+                "Done",
+            );
+        });
+}
+
+/// A value with a button on either side to decrease and increase it
+fn spawn_setting_row(
+    builder: &mut ChildSpawnerCommands,
+    font: &UiFont,
+    render_distance: &RenderDistance,
+    value: RenderDistanceText,
+    (decrease, increase): (SettingsButton, SettingsButton),
+) {
+    builder
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            column_gap: BUTTON_SPACING,
+            ..default()
+        })
+        .with_children(|row| {
+            spawn_button(row, font, decrease, SMALL_BUTTON_WIDTH, "-");
+            row.spawn((
+                value,
+                Text::new(value.text(render_distance)),
+                TextFont {
+                    font: font.0.clone_weak(),
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    width: VALUE_WIDTH,
+                    ..default()
+                },
+            ));
+            spawn_button(row, font, increase, SMALL_BUTTON_WIDTH, "+");
+        });
+}
+
+fn spawn_button(
+    builder: &mut ChildSpawnerCommands,
+    font: &UiFont,
+    button: SettingsButton,
+    width: Val,
+    text: &str,
+) {
+    builder.spawn((
+        button,
+        Node {
+            width,
+            height: BUTTON_HEIGHT,
+            ..default()
+        },
+        Text::new(text),
+        TextLayout::new_with_justify(JustifyText::Center),
+        TextFont {
+            font: font.0.clone_weak(),
+            ..default()
+        },
+    ));
+}
+
+fn tear_down_settings_screen(mut commands: Commands, q_root: Query<Entity, With<SettingsRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn press_button(
+    q_button: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut render_distance: ResMut<RenderDistance>,
+    in_game: Option<Res<State<InGameState>>>,
+    next_app_state: ResMut<NextState<AppState>>,
+    next_in_game_state: Option<ResMut<NextState<InGameState>>>,
+) {
+    let mut new_distance = *render_distance;
+    let mut done = false;
+    for (interaction, button) in q_button.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        match button {
+            SettingsButton::DecreaseHorizontal => new_distance.horizontal -= 1,
+            SettingsButton::IncreaseHorizontal => new_distance.horizontal += 1,
+            SettingsButton::DecreaseVertical => new_distance.vertical -= 1,
+            SettingsButton::IncreaseVertical => new_distance.vertical += 1,
+            SettingsButton::Done => done = true,
+        }
+    }
+    // Only changing the resource when the value changes keeps `update_chunks` and the settings
+    // file from reacting to presses at the ends of the ranges
+    render_distance.set_if_neq(new_distance.clamped());
+    if done {
+        close_settings(in_game, next_app_state, next_in_game_state);
+    }
+}
+
+/// Returns to the menu which the settings screen was opened from
+fn close_settings(
+    in_game: Option<Res<State<InGameState>>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    next_in_game_state: Option<ResMut<NextState<InGameState>>>,
+) {
+    match (in_game, next_in_game_state) {
+        (Some(_), Some(mut next_in_game_state)) => next_in_game_state.set(InGameState::Paused),
+        _ => next_app_state.set(AppState::MainMenu),
+    }
+}
+
+fn update_render_distance_text(
+    render_distance: Res<RenderDistance>,
+    mut q_text: Query<(&RenderDistanceText, &mut Text)>,
+) {
+    for (value, mut text) in q_text.iter_mut() {
+        text.0 = value.text(&render_distance);
+    }
+}
@@ -0,0 +1,752 @@
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    ecs::system::SystemParam,
+    prelude::*,
+};
+
+use crate::{
+    state::AppState,
+    world::{
+        preset::{self, WorldPreset},
+        save::{worlds, ChunkFormat, WorldDirectory},
+        seed::seed_from_text,
+    },
+};
+
+use super::{UiFont, UiRoot};
+
+pub struct SingleplayerPlugin;
+
+impl Plugin for SingleplayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<SingleplayerView>()
+            .init_resource::<SelectedWorld>()
+            .add_systems(OnEnter(SingleplayerView::List), spawn_world_list)
+            .add_systems(OnEnter(SingleplayerView::Create), spawn_create_form)
+            .add_systems(OnEnter(SingleplayerView::Rename), spawn_rename_form)
+            .add_systems(
+                OnEnter(SingleplayerView::Delete),
+                spawn_delete_confirmation,
+            )
+            .add_systems(OnExit(SingleplayerView::List), tear_down_view)
+            .add_systems(OnExit(SingleplayerView::Create), tear_down_view)
+            .add_systems(OnExit(SingleplayerView::Rename), tear_down_view)
+            .add_systems(OnExit(SingleplayerView::Delete), tear_down_view)
+            .add_systems(
+                Update,
+                (
+                    select_world,
+                    highlight_selected_world,
+                    press_button,
+                    cycle_world_type,
+                    cycle_chunk_format,
+                    focus_text_field,
+                    type_in_text_field,
+                    update_text_field_display,
+                )
+                    .run_if(in_state(AppState::Singleplayer)),
+            );
+    }
+}
+
+#[derive(SubStates, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[source(AppState = AppState::Singleplayer)]
+enum SingleplayerView {
+    #[default]
+    List,
+    Create,
+    Rename,
+    Delete,
+}
+
+#[derive(Resource, Default)]
+struct SelectedWorld(Option<String>);
+
+#[derive(Component)]
+struct SingleplayerViewRoot;
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component)]
+#[require(Button)]
+struct WorldEntry(String);
+
+#[derive(Component, Clone, Copy)]
+#[require(Button)]
+enum SingleplayerButton {
+    Play,
+    Create,
+    Rename,
+    Delete,
+    Back,
+    ConfirmCreate,
+    ConfirmRename,
+    ConfirmDelete,
+    Cancel,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum FormField {
+    Name,
+    Seed,
+    SuperflatLayers,
+}
+
+/// Cycles through the world presets when pressed, holding the index of the selected one in
+/// `WorldPreset::all`
+#[derive(Component, Default)]
+#[require(Button)]
+struct WorldTypeSelector(usize);
+
+/// Cycles through the chunk formats when pressed, holding the index of the selected one in
+/// `ChunkFormat::ALL`
+#[derive(Component, Default)]
+#[require(Button)]
+struct ChunkFormatSelector(usize);
+
+#[derive(Component, Default)]
+#[require(Button)]
+struct TextField {
+    value: String,
+    focused: bool,
+}
+
+const HUNDRED_PERCENT: Val = Val::Percent(100.0);
+const LIST_WIDTH: Val = Val::Px(300.0);
+const LIST_HEIGHT: Val = Val::Px(250.0);
+const BUTTON_WIDTH: Val = Val::Px(150.0);
+const BUTTON_HEIGHT: Val = Val::Px(25.0);
+const BUTTON_SPACING: Val = Val::Px(10.0);
+const SECTION_SPACING: Val = Val::Px(25.0);
+const FIELD_BACKGROUND: Color = Color::srgb(0.05, 0.05, 0.05);
+const SELECTED_TEXT_COLOUR: Color = Color::srgb(1.0, 0.85, 0.2);
+const ERROR_TEXT_COLOUR: Color = Color::srgb(1.0, 0.3, 0.3);
+const MAX_TEXT_FIELD_LENGTH: usize = 32;
+
+fn spawn_view_root<'a>(
+    commands: &'a mut Commands,
+    q_root: &Query<Entity, With<UiRoot>>,
+) -> EntityCommands<'a> {
+    let root = q_root
+        .single()
+        .expect("Menu root should exist");
+    commands.spawn((
+        SingleplayerViewRoot,
+        ChildOf(root),
+        Node {
+            width: HUNDRED_PERCENT,
+            height: HUNDRED_PERCENT,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: SECTION_SPACING,
+            ..default()
+        },
+    ))
+}
+
+fn spawn_label(builder: &mut ChildSpawnerCommands, font: &UiFont, text: impl Into<String>) {
+    builder.spawn((
+        Text::new(text),
+        TextFont {
+            font: font.0.clone(),
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+    ));
+}
+
+fn spawn_status_text(builder: &mut ChildSpawnerCommands, font: &UiFont) {
+    builder.spawn((
+        StatusText,
+        Text::default(),
+        TextColor(ERROR_TEXT_COLOUR),
+        TextFont {
+            font: font.0.clone(),
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+    ));
+}
+
+fn spawn_button(
+    builder: &mut ChildSpawnerCommands,
+    font: &UiFont,
+    button: SingleplayerButton,
+    text: &str,
+) {
+    builder
+        .spawn((
+            button,
+            Node {
+                width: BUTTON_WIDTH,
+                height: BUTTON_HEIGHT,
+                ..default()
+            },
+        ))
+        .with_children(|text_builder| {
+            text_builder.spawn((
+                Text::new(text),
+                TextFont {
+                    font: font.0.clone(),
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    width: HUNDRED_PERCENT,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_button_row(
+    builder: &mut ChildSpawnerCommands,
+    font: &UiFont,
+    buttons: &[(SingleplayerButton, &str)],
+) {
+    builder
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            column_gap: BUTTON_SPACING,
+            ..default()
+        })
+        .with_children(|row| {
+            for (button, text) in buttons.iter() {
+                spawn_button(row, font, *button, text);
+            }
+        });
+}
+
+fn spawn_text_field(
+    builder: &mut ChildSpawnerCommands,
+    font: &UiFont,
+    field: FormField,
+    label: &str,
+    value: &str,
+) {
+    spawn_label(builder, font, label);
+    builder
+        .spawn((
+            field,
+            TextField {
+                value: value.into(),
+                focused: field == FormField::Name,
+            },
+            Node {
+                width: LIST_WIDTH,
+                height: BUTTON_HEIGHT,
+                ..default()
+            },
+        ))
+        .with_children(|text_builder| {
+            text_builder.spawn((
+                Text::default(),
+                TextFont {
+                    font: font.0.clone(),
+                    ..default()
+                },
+                Node {
+                    width: HUNDRED_PERCENT,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_world_list(
+    mut commands: Commands,
+    q_root: Query<Entity, With<UiRoot>>,
+    font: Res<UiFont>,
+    mut selected_world: ResMut<SelectedWorld>,
+) {
+    let world_names = match worlds::list() {
+        Ok(names) => names,
+        Err(e) => {
+            warn!("Failed to list worlds: {}", e);
+            vec![]
+        }
+    };
+    if selected_world
+        .0
+        .as_ref()
+        .is_some_and(|name| !world_names.contains(name))
+    {
+        selected_world.0 = None;
+    }
+    spawn_view_root(&mut commands, &q_root).with_children(|builder| {
+        spawn_label(builder, &font, "Select World");
+        builder
+            .spawn((
+                Node {
+                    width: LIST_WIDTH,
+                    height: LIST_HEIGHT,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: BUTTON_SPACING,
+                    overflow: Overflow::scroll_y(),
+                    ..default()
+                },
+                BackgroundColor(FIELD_BACKGROUND),
+            ))
+            .with_children(|list| {
+                if world_names.is_empty() {
+                    spawn_label(list, &font, "No worlds yet");
+                }
+                for name in world_names.iter() {
+                    list.spawn((
+                        WorldEntry(name.clone()),
+                        Node {
+                            width: HUNDRED_PERCENT,
+                            min_height: BUTTON_HEIGHT,
+                            ..default()
+                        },
+                    ))
+                    .with_children(|text_builder| {
+                        text_builder.spawn((
+                            Text::new(name),
+                            TextFont {
+                                font: font.0.clone(),
+                                ..default()
+                            },
+                        ));
+                    });
+                }
+            });
+        spawn_status_text(builder, &font);
+        spawn_button_row(
+            builder,
+            &font,
+            &[
+                (SingleplayerButton::Play, "Play"),
+                (SingleplayerButton::Create, "Create"),
+            ],
+        );
+        spawn_button_row(
+            builder,
+            &font,
+            &[
+                (SingleplayerButton::Rename, "Rename"),
+                (SingleplayerButton::Delete, "Delete"),
+            ],
+        );
+        spawn_button(builder, &font, SingleplayerButton::Back, "Back");
+    });
+}
+
+fn spawn_create_form(
+    mut commands: Commands,
+    q_root: Query<Entity, With<UiRoot>>,
+    font: Res<UiFont>,
+) {
+    spawn_view_root(&mut commands, &q_root).with_children(|builder| {
+        spawn_label(builder, &font, "Create New World");
+        spawn_text_field(builder, &font, FormField::Name, "World Name", "New World");
+        spawn_text_field(
+            builder,
+            &font,
+            FormField::Seed,
+            "Seed (leave blank for random)",
+            "",
+        );
+        spawn_world_type_selector(builder, &font);
+        spawn_text_field(
+            builder,
+            &font,
+            FormField::SuperflatLayers,
+            "Superflat Layers (bottom to top)",
+            &preset::flat_layers_to_text(&preset::default_flat_layers()),
+        );
+        spawn_chunk_format_selector(builder, &font);
+        spawn_status_text(builder, &font);
+        spawn_button_row(
+            builder,
+            &font,
+            &[
+                (SingleplayerButton::ConfirmCreate, "Create World"),
+                (SingleplayerButton::Cancel, "Cancel"),
+            ],
+        );
+    });
+}
+
+fn spawn_world_type_selector(builder: &mut ChildSpawnerCommands, font: &UiFont) {
+    spawn_label(builder, font, "World Type");
+    builder
+        .spawn((
+            WorldTypeSelector::default(),
+            Node {
+                width: LIST_WIDTH,
+                height: BUTTON_HEIGHT,
+                ..default()
+            },
+        ))
+        .with_children(|text_builder| {
+            text_builder.spawn((
+                Text::new(world_type_label(&WorldPreset::all()[0])),
+                TextFont {
+                    font: font.0.clone(),
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    width: HUNDRED_PERCENT,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn world_type_label(preset: &WorldPreset) -> String {
+    preset.name().replace('_', " ")
+}
+
+fn spawn_chunk_format_selector(builder: &mut ChildSpawnerCommands, font: &UiFont) {
+    spawn_label(builder, font, "Chunk Format");
+    builder
+        .spawn((
+            ChunkFormatSelector::default(),
+            Node {
+                width: LIST_WIDTH,
+                height: BUTTON_HEIGHT,
+                ..default()
+            },
+        ))
+        .with_children(|text_builder| {
+            text_builder.spawn((
+                Text::new(chunk_format_label(ChunkFormat::ALL[0])),
+                TextFont {
+                    font: font.0.clone(),
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    width: HUNDRED_PERCENT,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn chunk_format_label(format: ChunkFormat) -> &'static str {
+    match format {
+        ChunkFormat::Region => "region files",
+        ChunkFormat::Json => "one JSON file per chunk",
+    }
+}
+
+fn spawn_rename_form(
+    mut commands: Commands,
+    q_root: Query<Entity, With<UiRoot>>,
+    font: Res<UiFont>,
+    selected_world: Res<SelectedWorld>,
+) {
+    let name = selected_world.0.clone().unwrap_or_default();
+    spawn_view_root(&mut commands, &q_root).with_children(|builder| {
+        spawn_label(builder, &font, format!("Rename \"{name}\""));
+        spawn_text_field(builder, &font, FormField::Name, "World Name", &name);
+        spawn_status_text(builder, &font);
+        spawn_button_row(
+            builder,
+            &font,
+            &[
+                (SingleplayerButton::ConfirmRename, "Rename"),
+                (SingleplayerButton::Cancel, "Cancel"),
+            ],
+        );
+    });
+}
+
+fn spawn_delete_confirmation(
+    mut commands: Commands,
+    q_root: Query<Entity, With<UiRoot>>,
+    font: Res<UiFont>,
+    selected_world: Res<SelectedWorld>,
+) {
+    let name = selected_world.0.clone().unwrap_or_default();
+    spawn_view_root(&mut commands, &q_root).with_children(|builder| {
+        spawn_label(
+            builder,
+            &font,
+            format!("Delete \"{name}\"? This cannot be undone."),
+        );
+        spawn_status_text(builder, &font);
+        spawn_button_row(
+            builder,
+            &font,
+            &[
+                (SingleplayerButton::ConfirmDelete, "Delete"),
+                (SingleplayerButton::Cancel, "Cancel"),
+            ],
+        );
+    });
+}
+
+fn tear_down_view(q_root: Query<Entity, With<SingleplayerViewRoot>>, mut commands: Commands) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn select_world(
+    q_entry: Query<(&Interaction, &WorldEntry), Changed<Interaction>>,
+    mut selected_world: ResMut<SelectedWorld>,
+) {
+    for (interaction, entry) in q_entry.iter() {
+        if let Interaction::Pressed = interaction {
+            selected_world.0 = Some(entry.0.clone());
+        }
+    }
+}
+
+fn highlight_selected_world(
+    q_entry: Query<(&WorldEntry, &Children)>,
+    mut q_text: Query<&mut TextColor>,
+    selected_world: Res<SelectedWorld>,
+) {
+    for (entry, children) in q_entry.iter() {
+        let colour = if selected_world.0.as_ref() == Some(&entry.0) {
+            SELECTED_TEXT_COLOUR
+        } else {
+            Color::WHITE
+        };
+        for child in children.iter() {
+            if let Ok(mut text_colour) = q_text.get_mut(child) {
+                text_colour.0 = colour;
+            }
+        }
+    }
+}
+
+fn press_button(
+    q_button: Query<(&Interaction, &SingleplayerButton), Changed<Interaction>>,
+    form: Form,
+    mut q_status: Query<&mut Text, With<StatusText>>,
+    mut selected_world: ResMut<SelectedWorld>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_view: ResMut<NextState<SingleplayerView>>,
+    mut commands: Commands,
+) {
+    for (interaction, button) in q_button.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        let result = match button {
+            SingleplayerButton::Back => {
+                next_app_state.set(AppState::MainMenu);
+                Ok(())
+            }
+            SingleplayerButton::Create => {
+                next_view.set(SingleplayerView::Create);
+                Ok(())
+            }
+            SingleplayerButton::Cancel => {
+                next_view.set(SingleplayerView::List);
+                Ok(())
+            }
+            SingleplayerButton::Play => match &selected_world.0 {
+                Some(name) => {
+                    commands.insert_resource(WorldDirectory::named(name));
+                    next_app_state.set(AppState::InGame);
+                    Ok(())
+                }
+                None => Err("Select a world to play".into()),
+            },
+            SingleplayerButton::Rename | SingleplayerButton::Delete => {
+                if selected_world.0.is_none() {
+                    Err("Select a world first".into())
+                } else {
+                    next_view.set(match button {
+                        SingleplayerButton::Rename => SingleplayerView::Rename,
+                        _ => SingleplayerView::Delete,
+                    });
+                    Ok(())
+                }
+            }
+            SingleplayerButton::ConfirmCreate => {
+                let seed = seed_from_text(&form.value(FormField::Seed));
+                form.preset()
+                    .and_then(|preset| {
+                        worlds::create(
+                            &form.value(FormField::Name),
+                            seed,
+                            preset,
+                            form.chunk_format(),
+                        )
+                        .map_err(|e| e.to_string())
+                    })
+                    .map(|directory| {
+                        commands.insert_resource(directory);
+                        next_app_state.set(AppState::InGame);
+                    })
+            }
+            SingleplayerButton::ConfirmRename => {
+                let name = selected_world.0.clone().unwrap_or_default();
+                let new_name = form.value(FormField::Name);
+                worlds::rename(&name, &new_name)
+                    .map(|_| {
+                        selected_world.0 = Some(new_name);
+                        next_view.set(SingleplayerView::List);
+                    })
+                    .map_err(|e| e.to_string())
+            }
+            SingleplayerButton::ConfirmDelete => {
+                let name = selected_world.0.clone().unwrap_or_default();
+                worlds::delete(&name)
+                    .map(|_| {
+                        selected_world.0 = None;
+                        next_view.set(SingleplayerView::List);
+                    })
+                    .map_err(|e| e.to_string())
+            }
+        };
+        for mut status in q_status.iter_mut() {
+            status.0 = result.clone().err().unwrap_or_default();
+        }
+    }
+}
+
+/// The inputs of whichever form is open
+#[derive(SystemParam)]
+struct Form<'w, 's> {
+    fields: Query<'w, 's, (&'static FormField, &'static TextField)>,
+    world_type: Query<'w, 's, &'static WorldTypeSelector>,
+    chunk_format: Query<'w, 's, &'static ChunkFormatSelector>,
+}
+
+impl Form<'_, '_> {
+    fn value(&self, field: FormField) -> String {
+        self.fields
+            .iter()
+            .find(|(f, _)| **f == field)
+            .map(|(_, text_field)| text_field.value.clone())
+            .unwrap_or_default()
+    }
+
+    /// The selected world type, with the layers from the form if it's superflat
+    fn preset(&self) -> Result<WorldPreset, String> {
+        let index = self
+            .world_type
+            .single()
+            .map_or(0, |selector| selector.0);
+        match WorldPreset::all()[index].clone() {
+            WorldPreset::Superflat { .. } => Ok(WorldPreset::Superflat {
+                layers: preset::parse_flat_layers(&self.value(FormField::SuperflatLayers))?,
+            }),
+            preset => Ok(preset),
+        }
+    }
+
+    fn chunk_format(&self) -> ChunkFormat {
+        let index = self
+            .chunk_format
+            .single()
+            .map_or(0, |selector| selector.0);
+        ChunkFormat::ALL[index]
+    }
+}
+
+fn cycle_world_type(
+    mut q_selector: Query<(&Interaction, &mut WorldTypeSelector, &Children), Changed<Interaction>>,
+    mut q_text: Query<&mut Text>,
+) {
+    for (interaction, mut selector, children) in q_selector.iter_mut() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        let presets = WorldPreset::all();
+        selector.0 = (selector.0 + 1) % presets.len();
+        for child in children.iter() {
+            if let Ok(mut text) = q_text.get_mut(child) {
+                text.0 = world_type_label(&presets[selector.0]);
+            }
+        }
+    }
+}
+
+fn cycle_chunk_format(
+    mut q_selector: Query<
+        (&Interaction, &mut ChunkFormatSelector, &Children),
+        Changed<Interaction>,
+    >,
+    mut q_text: Query<&mut Text>,
+) {
+    for (interaction, mut selector, children) in q_selector.iter_mut() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        selector.0 = (selector.0 + 1) % ChunkFormat::ALL.len();
+        for child in children.iter() {
+            if let Ok(mut text) = q_text.get_mut(child) {
+                text.0 = chunk_format_label(ChunkFormat::ALL[selector.0]).into();
+            }
+        }
+    }
+}
+
+fn focus_text_field(mut q_field: Query<(Entity, Ref<Interaction>, &mut TextField)>) {
+    let Some(clicked) = q_field
+        .iter()
+        .find(|(_, interaction, _)| {
+            interaction.is_changed() && **interaction == Interaction::Pressed
+        })
+        .map(|(entity, ..)| entity)
+    else {
+        return;
+    };
+    for (entity, _, mut field) in q_field.iter_mut() {
+        field.focused = entity == clicked;
+    }
+}
+
+fn type_in_text_field(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut q_field: Query<&mut TextField>,
+) {
+    let Some(mut field) = q_field
+        .iter_mut()
+        .find(|field| field.focused)
+    else {
+        keyboard_events.clear();
+        return;
+    };
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Backspace => {
+                field.value.pop();
+            }
+            Key::Space => push_text(&mut field, " "),
+            Key::Character(text) => push_text(&mut field, text),
+            _ => {}
+        }
+    }
+}
+
+fn push_text(field: &mut TextField, text: &str) {
+    for c in text.chars().filter(|c| !c.is_control()) {
+        if field.value.chars().count() >= MAX_TEXT_FIELD_LENGTH {
+            return;
+        }
+        field.value.push(c);
+    }
+}
+
+fn update_text_field_display(
+    q_field: Query<(&TextField, &Children), Changed<TextField>>,
+    mut q_text: Query<&mut Text>,
+) {
+    for (field, children) in q_field.iter() {
+        let cursor = if field.focused { "_" } else { "" };
+        for child in children.iter() {
+            if let Ok(mut text) = q_text.get_mut(child) {
+                text.0 = format!("{}{}", field.value, cursor);
+            }
+        }
+    }
+}
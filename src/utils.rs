@@ -39,3 +39,19 @@ pub fn fast_hash(a: u32) -> u32 {
     a ^= a.rotate_left(4);
     return a;
 }
+
+/// `fast_hash` only xors and rotates bits, so the hashes of similar numbers are correlated. This
+/// is slower, but the bits of its result are independent.
+pub fn mix_hash(a: u32) -> u32 {
+    let mut a = a;
+    a ^= a >> 16;
+    a = a.wrapping_mul(0x7FEB352D);
+    a ^= a >> 15;
+    a = a.wrapping_mul(0x846CA68B);
+    a ^ (a >> 16)
+}
+
+/// The `n`th of a sequence of random numbers made from a hash
+pub fn nth_random(hash: u32, n: u32) -> u32 {
+    mix_hash(hash.wrapping_add(n.wrapping_mul(0x9E3779B9)))
+}
@@ -0,0 +1,302 @@
+use crate::{
+    block::Block,
+    chunk::{data::Blocks, CHUNK_SIZE_I32},
+    player::block_target::{TargetedBlock, TargetedSpace},
+    state::{AppState, InGameState},
+    world::{block_update::SetBlockEvent, neighborhood::ComponentIndex},
+};
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use file::VoxModel;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub mod file;
+
+/// Lets players override `VoxMapping::default`
+const MAPPING_FILE: &str = "vox_mapping.json";
+const EXPORTS_DIRECTORY: &str = "exports";
+
+pub struct VoxPlugin;
+
+impl Plugin for VoxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExportVoxEvent>()
+            .add_event::<ImportVoxEvent>()
+            .add_systems(Startup, load_mapping)
+            .add_systems(
+                Update,
+                (
+                    (
+                        import_dropped_files,
+                        export_targeted_chunk.run_if(input_just_pressed(KeyCode::F8)),
+                    )
+                        .run_if(in_state(InGameState::Playing)),
+                    (export_vox, import_vox),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Saves the blocks between two corners (inclusive) as a .vox file. Every chunk in the region must
+/// be loaded.
+#[derive(Event)]
+pub struct ExportVoxEvent {
+    pub min: IVec3,
+    pub max: IVec3,
+    pub path: PathBuf,
+}
+
+/// Places the blocks of a .vox model with its lowest corner at `position`. Empty voxels don't
+/// replace the blocks which are already there.
+#[derive(Event)]
+pub struct ImportVoxEvent {
+    pub path: PathBuf,
+    pub position: IVec3,
+}
+
+/// Which block each colour of a .vox palette stands for
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct VoxMapping {
+    pub entries: Vec<VoxMappingEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VoxMappingEntry {
+    /// Palette index, starting at 1
+    pub index: u8,
+    /// RGBA colour written to exported palettes
+    pub colour: [u8; 4],
+    pub block: Block,
+}
+
+impl Default for VoxMapping {
+    fn default() -> Self {
+        let entries = [
+            (Block::Stone, [125, 125, 125, 255]),
+            (Block::Dirt, [134, 96, 67, 255]),
+            (Block::Grass, [51, 153, 0, 255]),
+            (Block::Sand, [219, 207, 163, 255]),
+            (Block::Wood, [102, 81, 51, 255]),
+            (Block::Leaves, [10, 99, 18, 255]),
+            (Block::Water, [63, 118, 228, 255]),
+            (Block::Bedrock, [40, 40, 40, 255]),
+            (Block::CoalOre, [45, 45, 45, 255]),
+            (Block::IronOre, [216, 175, 147, 255]),
+            (Block::GoldOre, [252, 238, 75, 255]),
+            (Block::DiamondOre, [93, 236, 245, 255]),
+            (Block::Gravel, [131, 125, 122, 255]),
+            (Block::TallGrass, [78, 150, 40, 255]),
+            (Block::Dandelion, [240, 200, 30, 255]),
+            (Block::Poppy, [200, 30, 30, 255]),
+            (Block::DeadBush, [120, 84, 45, 255]),
+            (Block::RedMushroom, [190, 30, 30, 255]),
+            (Block::BrownMushroom, [140, 100, 70, 255]),
+            (Block::Cactus, [70, 140, 50, 255]),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (block, colour))| VoxMappingEntry {
+            index: i as u8 + 1,
+            colour,
+            block,
+        })
+        .collect();
+        Self { entries }
+    }
+}
+
+impl VoxMapping {
+    fn index_of(&self, block: Block) -> Option<u8> {
+        self.entries
+            .iter()
+            .find(|entry| entry.block == block)
+            .map(|entry| entry.index)
+    }
+
+    /// Colour indices without an entry use the entry with the most similar colour, so that models
+    /// made with any palette can be imported
+    fn block_of(&self, index: u8, palette: Option<&Vec<[u8; 4]>>) -> Option<Block> {
+        if let Some(entry) = self
+            .entries
+            .iter()
+            .find(|entry| entry.index == index)
+        {
+            return Some(entry.block);
+        }
+        let colour = palette?.get((index as usize).checked_sub(1)?)?;
+        self.entries
+            .iter()
+            .min_by_key(|entry| {
+                (0..3)
+                    .map(|i| (entry.colour[i] as i32 - colour[i] as i32).pow(2))
+                    .sum::<i32>()
+            })
+            .map(|entry| entry.block)
+    }
+
+    fn palette(&self) -> Vec<[u8; 4]> {
+        let mut palette = vec![[0; 4]; 255];
+        for entry in self.entries.iter() {
+            if let Some(colour) = palette.get_mut((entry.index as usize).wrapping_sub(1)) {
+                *colour = entry.colour;
+            }
+        }
+        palette
+    }
+}
+
+fn load_mapping(mut commands: Commands) {
+    let mapping = match fs::read(MAPPING_FILE) {
+        Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+            warn!("Failed to read {}, using the default: {}", MAPPING_FILE, e);
+            VoxMapping::default()
+        }),
+        Err(_) => VoxMapping::default(),
+    };
+    commands.insert_resource(mapping);
+}
+
+/// MagicaVoxel's z axis points up, so the y and z axes are swapped. Flipping the new z axis keeps
+/// models from being mirrored.
+fn vox_to_world(pos: [u8; 3], size: UVec3) -> IVec3 {
+    let [x, y, z] = pos.map(|n| n as i32);
+    IVec3::new(x, z, size.y as i32 - 1 - y)
+}
+
+fn world_to_vox(offset: IVec3, size: UVec3) -> [u8; 3] {
+    [offset.x, size.y as i32 - 1 - offset.z, offset.y].map(|n| n as u8)
+}
+
+fn export_vox(
+    mut events: EventReader<ExportVoxEvent>,
+    index: Res<ComponentIndex<Blocks>>,
+    mapping: Res<VoxMapping>,
+) {
+    for event in events.read() {
+        match export(event, &index, &mapping) {
+            Ok(()) => info!("Exported {}", event.path.display()),
+            Err(e) => warn!("Failed to export {}: {}", event.path.display(), e),
+        }
+    }
+}
+
+fn export(
+    event: &ExportVoxEvent,
+    index: &ComponentIndex<Blocks>,
+    mapping: &VoxMapping,
+) -> io::Result<()> {
+    let min = event.min.min(event.max);
+    let max = event.min.max(event.max);
+    let world_size = (max - min + IVec3::ONE).as_uvec3();
+    // World y becomes vox z
+    let size = UVec3::new(world_size.x, world_size.z, world_size.y);
+    if size.cmpgt(UVec3::splat(file::MAX_MODEL_SIZE)).any() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Regions can be at most {} blocks long", file::MAX_MODEL_SIZE),
+        ));
+    }
+    let mut voxels = vec![];
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                let pos = IVec3::new(x, y, z);
+                let Some(block) = index.at_pos(pos) else {
+                    return Err(io::Error::other(format!("The chunk at {pos} isn't loaded")));
+                };
+                if let Some(colour_index) = mapping.index_of(*block) {
+                    voxels.push((world_to_vox(pos - min, size), colour_index));
+                }
+            }
+        }
+    }
+    let model = VoxModel {
+        size,
+        voxels,
+        palette: Some(mapping.palette()),
+    };
+    file::write(&event.path, &model)
+}
+
+fn import_vox(
+    mut events: EventReader<ImportVoxEvent>,
+    mapping: Res<VoxMapping>,
+    mut set_block_events: EventWriter<SetBlockEvent>,
+) {
+    for event in events.read() {
+        let model = match file::read(&event.path) {
+            Ok(model) => model,
+            Err(e) => {
+                warn!("Failed to import {}: {}", event.path.display(), e);
+                continue;
+            }
+        };
+        let mut unmapped = 0;
+        for (pos, colour_index) in model.voxels.iter() {
+            let Some(block) = mapping.block_of(*colour_index, model.palette.as_ref()) else {
+                unmapped += 1;
+                continue;
+            };
+            let world_pos = event.position + vox_to_world(*pos, model.size);
+            set_block_events.write(SetBlockEvent {
+                block,
+                world_pos: world_pos.to_array(),
+            });
+        }
+        if unmapped > 0 {
+            warn!(
+                "{} voxels of {} have colours without a block",
+                unmapped,
+                event.path.display()
+            );
+        }
+    }
+}
+
+/// Dropping a .vox file onto the window imports it where the player is looking
+fn import_dropped_files(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    targeted_space: Res<TargetedSpace>,
+    mut import_events: EventWriter<ImportVoxEvent>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        if path_buf
+            .extension()
+            .is_none_or(|ext| ext != "vox")
+        {
+            continue;
+        }
+        let Some(position) = targeted_space.0 else {
+            warn!("Look at a block to choose where to import {}", path_buf.display());
+            continue;
+        };
+        import_events.write(ImportVoxEvent {
+            path: path_buf.clone(),
+            position,
+        });
+    }
+}
+
+fn export_targeted_chunk(
+    targeted_block: Res<TargetedBlock>,
+    mut export_events: EventWriter<ExportVoxEvent>,
+) {
+    let Some(pos) = targeted_block.0 else {
+        return;
+    };
+    let chunk_pos = pos.div_euclid(IVec3::splat(CHUNK_SIZE_I32));
+    let IVec3 { x, y, z } = chunk_pos;
+    export_events.write(ExportVoxEvent {
+        min: chunk_pos * CHUNK_SIZE_I32,
+        max: chunk_pos * CHUNK_SIZE_I32 + IVec3::splat(CHUNK_SIZE_I32 - 1),
+        path: Path::new(EXPORTS_DIRECTORY).join(format!("chunk_{x}_{y}_{z}.vox")),
+    });
+}
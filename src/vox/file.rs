@@ -0,0 +1,183 @@
+//! Reads and writes MagicaVoxel .vox files, see
+//! https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt
+//!
+//! Only the first model of a file is used, and scene information such as transforms is ignored.
+
+use bevy::prelude::*;
+use std::{fs, io, path::Path};
+
+const MAGIC: &[u8; 4] = b"VOX ";
+const VERSION: i32 = 150;
+/// Models can be at most this many voxels long along each axis
+pub const MAX_MODEL_SIZE: u32 = 256;
+
+/// A model in MagicaVoxel's coordinates, where z points up
+pub struct VoxModel {
+    pub size: UVec3,
+    /// Position and colour index of every non-empty voxel. Colour indices start at 1.
+    pub voxels: Vec<([u8; 3], u8)>,
+    /// Colour of each colour index, starting with index 1. Files saved with MagicaVoxel's default
+    /// palette don't contain one.
+    pub palette: Option<Vec<[u8; 4]>>,
+}
+
+pub fn write(path: &Path, model: &VoxModel) -> io::Result<()> {
+    if model.size.cmpgt(UVec3::splat(MAX_MODEL_SIZE)).any() || model.size.cmpeq(UVec3::ZERO).any() {
+        return Err(invalid_input(format!(
+            "Models must be between 1 and {MAX_MODEL_SIZE} voxels long, not {}",
+            model.size
+        )));
+    }
+    let mut size = vec![];
+    for n in model.size.to_array() {
+        size.extend_from_slice(&(n as i32).to_le_bytes());
+    }
+    let mut xyzi = (model.voxels.len() as i32)
+        .to_le_bytes()
+        .to_vec();
+    for ([x, y, z], index) in model.voxels.iter() {
+        xyzi.extend_from_slice(&[*x, *y, *z, *index]);
+    }
+    let mut children = vec![];
+    write_chunk(&mut children, b"SIZE", &size, &[]);
+    write_chunk(&mut children, b"XYZI", &xyzi, &[]);
+    if let Some(palette) = &model.palette {
+        let mut rgba = palette
+            .iter()
+            .take(256)
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        rgba.resize(256 * 4, 0);
+        write_chunk(&mut children, b"RGBA", &rgba, &[]);
+    }
+    let mut contents = MAGIC.to_vec();
+    contents.extend_from_slice(&VERSION.to_le_bytes());
+    write_chunk(&mut contents, b"MAIN", &[], &children);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as i32).to_le_bytes());
+    out.extend_from_slice(&(children.len() as i32).to_le_bytes());
+    out.extend_from_slice(content);
+    out.extend_from_slice(children);
+}
+
+pub fn read(path: &Path) -> io::Result<VoxModel> {
+    let contents = fs::read(path)?;
+    let mut reader = Reader {
+        bytes: &contents,
+        offset: 0,
+    };
+    if reader.take(4)? != MAGIC {
+        return Err(invalid_data("Not a MagicaVoxel file"));
+    }
+    reader.i32()?;
+    let (id, _, children) = reader.chunk()?;
+    if id != b"MAIN" {
+        return Err(invalid_data("Expected a MAIN chunk"));
+    }
+    let mut children = Reader {
+        bytes: children,
+        offset: 0,
+    };
+    let mut size = None;
+    let mut voxels = None;
+    let mut palette = None;
+    while children.offset < children.bytes.len() {
+        let (id, content, _) = children.chunk()?;
+        let mut content = Reader {
+            bytes: content,
+            offset: 0,
+        };
+        match id {
+            b"SIZE" if size.is_none() => {
+                let model_size = IVec3::new(content.i32()?, content.i32()?, content.i32()?);
+                if model_size.cmplt(IVec3::ONE).any()
+                    || model_size.cmpgt(IVec3::splat(MAX_MODEL_SIZE as i32)).any()
+                {
+                    return Err(invalid_data(format!(
+                        "Models must be between 1 and {MAX_MODEL_SIZE} voxels long, not {}",
+                        model_size
+                    )));
+                }
+                size = Some(model_size.as_uvec3());
+            }
+            b"XYZI" if voxels.is_none() => {
+                let count = content.i32()?.max(0) as usize;
+                // The count can't be trusted until the voxels have been read, and each takes 4 bytes
+                let mut model_voxels = Vec::with_capacity(count.min(content.bytes.len() / 4));
+                for _ in 0..count {
+                    let [x, y, z, index] = content.take(4)?.try_into().unwrap();
+                    model_voxels.push(([x, y, z], index));
+                }
+                voxels = Some(model_voxels);
+            }
+            b"RGBA" => {
+                let rgba = content.take(256 * 4)?;
+                palette = Some(
+                    rgba.chunks_exact(4)
+                        .map(|colour| colour.try_into().unwrap())
+                        .collect(),
+                );
+            }
+            // Further models and scene information
+            _ => {}
+        }
+    }
+    let (Some(size), Some(voxels)) = (size, voxels) else {
+        return Err(invalid_data("The file doesn't contain a model"));
+    };
+    if voxels
+        .iter()
+        .any(|(pos, _)| UVec3::from(pos.map(|n| n as u32)).cmpge(size).any())
+    {
+        return Err(invalid_data("A voxel lies outside of the model"));
+    }
+    Ok(VoxModel {
+        size,
+        voxels,
+        palette,
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + count)
+            .ok_or_else(|| invalid_data("The file is truncated"))?;
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Returns the ID, content and children of the next chunk
+    fn chunk(&mut self) -> io::Result<(&'a [u8], &'a [u8], &'a [u8])> {
+        let id = self.take(4)?;
+        let content_length = self.i32()?.max(0) as usize;
+        let children_length = self.i32()?.max(0) as usize;
+        Ok((id, self.take(content_length)?, self.take(children_length)?))
+    }
+}
+
+fn invalid_input(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
@@ -3,86 +3,148 @@ use crate::{
     camera_distance::CameraDistance,
     chunk::{
         data::{
-            Blocks, ContinentNoise, FromNoise, HeightNoise, HumidityNoise, Noise3d,
-            TemperatureNoise, Terrain,
+            BiomeMap, Blocks, ContinentNoise, FromNoise, HeightNoise, HeightScale, HumidityNoise,
+            Noise3d, TemperatureNoise, Terrain,
         },
         position::ChunkPosition,
         spatial::SpatiallyMapped,
         Chunk, CHUNK_LENGTH, CHUNK_SIZE_I32,
     },
+    decoration::{DecorationPass, DecorationRegistry},
+    ore::{OrePass, OreRegistry},
     player::Player,
     render_layer::WORLD_LAYER,
-    state::AppState,
-    structure::StructureType,
-    world::neighborhood::CompleteNeighborhood,
+    state::{AppState, InGameState},
+    structure::{StructurePass, StructureRegistry},
 };
+use biome::Biome;
+use config::{WorldgenConfig, WorldgenConfigReloaded};
 use bevy::{
-    ecs::query::QueryData,
+    ecs::{
+        query::{QueryData, QueryItem},
+        system::{SystemParam, SystemParamItem},
+    },
     platform::collections::HashMap,
     prelude::*,
     render::view::RenderLayers,
     tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
 };
+use heightmap::Heightmap;
 use index::ChunkIndex;
 use neighborhood::Neighborhood;
 use noise::NoiseFn;
+use pass::{AddWorldgenPass, PassOutput, WorldgenPass};
+use preset::WorldPreset;
+use render_distance::RenderDistance;
+use save::{ChunkFormat, Dirty, PendingWrites, SavedChunk, WorldDirectory};
 use seed::{LoadSeed, WorldSeed};
 use stage::Stage;
 use std::collections::HashSet;
 use world_noise::{
     CaveNetworkNoiseGenerator, ClimateNoise, ContinentNoiseGenerator, HeightNoiseGenerator,
-    WhiteNoise,
+    DensityNoiseGenerator, IslandNoiseGenerator, RiverNoiseGenerator, WhiteNoise,
 };
+use water::WaterColumn;
 
-const CHUNK_LOAD_DISTANCE_HORIZONTAL: i32 = 3;
-const CHUNK_LOAD_DISTANCE_VERTICAL: i32 = 2;
-
+pub mod biome;
 pub mod block_update;
 mod cleanup;
+pub mod config;
+mod density;
+pub mod heightmap;
 pub mod index;
 pub mod neighborhood;
+pub mod pass;
+pub mod pregenerate;
+pub mod preset;
+pub mod preview;
+pub mod render_distance;
+pub mod save;
 pub mod seed;
+pub mod spawn;
 pub mod stage;
+pub mod water;
 mod world_noise;
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            WorldGenerationPlugin,
+            block_update::BlockPlugin,
+            cleanup::CleanupPlugin,
+            spawn::SpawnPlugin,
+            render_distance::RenderDistancePlugin,
+        ))
+        .add_systems(
+            Update,
+            (regenerate_chunks, update_chunks, despawn_chunks)
+                .chain()
+                .in_set(WorldSet)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Generates, loads and saves every chunk which is spawned, without deciding which chunks to
+/// spawn or needing a window. `WorldPlugin` spawns chunks around the player.
+pub struct WorldGenerationPlugin;
+
+impl Plugin for WorldGenerationPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
             seed::SeedPlugin,
+            preset::PresetPlugin,
+            config::WorldgenConfigPlugin,
             index::ChunkIndexPlugin,
-            block_update::BlockPlugin,
             neighborhood::NeighborhoodPlugin::<Terrain>::new(),
             neighborhood::NeighborhoodPlugin::<Blocks>::new(),
             neighborhood::NeighborhoodPlugin::<Stage>::new(),
-            neighborhood::NeighborhoodPlugin::<Noise3d>::new(),
-            cleanup::CleanupPlugin,
+            neighborhood::NeighborhoodPlugin::<BiomeMap>::new(),
+            neighborhood::NeighborhoodPlugin::<Heightmap>::new(),
+            save::SavePlugin,
         ))
         .init_resource::<ChunkLoadTasks>()
-        .add_systems(Startup, init_noise.after(LoadSeed))
+        .init_resource::<StructureRegistry>()
+        .init_resource::<OreRegistry>()
+        .init_resource::<DecorationRegistry>()
+        // The chunks are saved on entering `InGameState::Saving`, so they mustn't change after
+        // that, or their writes would only start once the world has been left
+        .configure_sets(
+            Update,
+            WorldSet.run_if(not(in_state(InGameState::Saving))),
+        )
+        .add_systems(OnEnter(AppState::InGame), init_noise.after(LoadSeed))
         .add_systems(
             Update,
             (
-                (update_chunks, despawn_chunks).chain(),
+                init_noise.run_if(on_event::<WorldgenConfigReloaded>),
                 receive_chunk_load_tasks,
-                begin_noise_load_tasks,
-                begin_terrain_sculpt_tasks,
-                begin_structure_load_tasks,
+                begin_saved_chunk_load_tasks,
+                begin_saved_terrain_tasks.after(receive_chunk_load_tasks),
             )
                 .in_set(WorldSet)
                 .run_if(in_state(AppState::InGame)),
         )
+        .add_worldgen_pass::<NoisePass>()
+        .add_worldgen_pass::<SculptPass>()
+        .add_worldgen_pass::<OrePass>()
+        .add_worldgen_pass::<StructurePass>()
+        .add_worldgen_pass::<DecorationPass>()
         .add_observer(kill_tasks_for_unloaded_chunks);
     }
 }
 
-fn init_noise(mut commands: Commands, seed: Res<WorldSeed>) {
-    commands.insert_resource(ContinentNoiseGenerator::new(seed.0));
-    commands.insert_resource(HeightNoiseGenerator::new(seed.0));
+fn init_noise(mut commands: Commands, seed: Res<WorldSeed>, config: Res<WorldgenConfig>) {
+    commands.insert_resource(ContinentNoiseGenerator::new(seed.0, &config.continent_noise));
+    commands.insert_resource(HeightNoiseGenerator::new(seed.0, &config.height_noise));
     commands.insert_resource(WhiteNoise::new(seed.0));
     commands.insert_resource(CaveNetworkNoiseGenerator::new(seed.0));
-    commands.insert_resource(ClimateNoise::new(seed.0, 1000.0))
+    commands.insert_resource(IslandNoiseGenerator::new(seed.0));
+    commands.insert_resource(DensityNoiseGenerator::new(seed.0));
+    commands.insert_resource(RiverNoiseGenerator::new(seed.0));
+    commands.insert_resource(ClimateNoise::new(seed.0, config.climate_scale))
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
@@ -94,10 +156,14 @@ struct ChunkLoadTaskData {
 }
 
 enum AddedChunkData {
-    Noise(NoiseBundle),
+    /// The chunk has no chunk file and must be generated
+    Unsaved,
+    /// The chunk was loaded from its chunk file
+    Saved(SavedChunk, BiomeMap, Heightmap),
+    /// The terrain of a saved chunk was sculpted again for a neighbouring chunk's structures
     Terrain(Terrain),
-    // Blocks(Blocks, Stage),
-    BlockUpdates(Vec<(Block, [usize; 3])>, Stage),
+    /// A worldgen pass has run, so the chunk has completed the stage
+    Pass(PassOutput, Stage),
 }
 
 #[derive(Resource, Default)]
@@ -115,20 +181,30 @@ fn kill_tasks_for_unloaded_chunks(
 
 fn update_chunks(
     mut commands: Commands,
-    q_camera_position: Query<&GlobalTransform, (With<Player>, Changed<ChunkPosition>)>,
+    q_camera_position: Query<(&GlobalTransform, Ref<ChunkPosition>), With<Player>>,
     q_chunk_position: Query<(Entity, &ChunkPosition), With<Chunk>>,
+    render_distance: Res<RenderDistance>,
 ) {
-    let Ok(pos) = q_camera_position.single() else {
+    let Ok((pos, player_chunk)) = q_camera_position.single() else {
         return;
     };
+    if !player_chunk.is_changed() && !render_distance.is_changed() {
+        return;
+    }
     let camera_position = pos.compute_transform().translation;
     // let camera_position = Vec3::ZERO;
     let chunk_pos = ChunkPosition::from_world_position(&camera_position);
     // Determine position of chunks that should be loaded
     let mut should_be_loaded_positions: HashSet<IVec3> = HashSet::new();
-    for chunk_x in -CHUNK_LOAD_DISTANCE_HORIZONTAL..=CHUNK_LOAD_DISTANCE_HORIZONTAL {
-        for chunk_z in -CHUNK_LOAD_DISTANCE_HORIZONTAL..=CHUNK_LOAD_DISTANCE_HORIZONTAL {
-            for chunk_y in -CHUNK_LOAD_DISTANCE_VERTICAL..=CHUNK_LOAD_DISTANCE_VERTICAL {
+    let RenderDistance {
+        horizontal,
+        vertical,
+    } = *render_distance;
+    for chunk_x in -horizontal..=horizontal {
+        for chunk_z in -horizontal..=horizontal {
+            // Plants are placed on a chunk only once the chunk below has its structures, so an
+            // extra layer is loaded below so that as many chunks finish below the player as above
+            for chunk_y in -vertical - 1..=vertical {
                 let cur_chunk_pos =
                     ChunkPosition(chunk_pos.0 + IVec3::new(chunk_x, chunk_y, chunk_z));
                 should_be_loaded_positions.insert(cur_chunk_pos.0);
@@ -146,13 +222,42 @@ fn update_chunks(
     }
     // Finally, load the new chunks
     for pos in should_be_loaded_positions {
-        commands.spawn((
-            Chunk,
-            ChunkPosition(pos),
-            Transform::from_translation((pos * CHUNK_SIZE_I32).as_vec3() + Vec3::Y),
-            Visibility::Visible,
-            RenderLayers::layer(WORLD_LAYER),
-        ));
+        commands.spawn(chunk_bundle(pos));
+    }
+}
+
+fn chunk_bundle(pos: IVec3) -> impl Bundle {
+    (
+        Chunk,
+        ChunkPosition(pos),
+        Transform::from_translation((pos * CHUNK_SIZE_I32).as_vec3() + Vec3::Y),
+        Visibility::Visible,
+        RenderLayers::layer(WORLD_LAYER),
+    )
+}
+
+/// Replaces every loaded chunk with one which is generated from scratch, ignoring its chunk file,
+/// so that changes to the worldgen config show up straight away. Changes made to the chunks since
+/// they were generated are lost.
+fn regenerate_chunks(
+    mut commands: Commands,
+    mut reloaded: EventReader<WorldgenConfigReloaded>,
+    q_chunk: Query<(Entity, &ChunkPosition), With<Chunk>>,
+) {
+    if reloaded.is_empty() {
+        return;
+    }
+    reloaded.clear();
+    let positions = q_chunk
+        .iter()
+        .map(|(entity, pos)| {
+            commands.entity(entity).despawn();
+            pos.0
+        })
+        .collect::<Vec<_>>();
+    // The old chunks are removed from the chunk index before the new ones are added
+    for pos in positions {
+        commands.spawn((chunk_bundle(pos), Unsaved));
     }
 }
 
@@ -176,7 +281,7 @@ fn despawn_chunks(
 fn receive_chunk_load_tasks(
     mut commands: Commands,
     mut tasks: ResMut<ChunkLoadTasks>,
-    mut q_blocks: Query<&mut Blocks>,
+    mut q_blocks: Query<(&mut Blocks, &mut Heightmap)>,
 ) {
     tasks.0.retain(|_, task| {
         let Some(data) = block_on(future::poll_once(task)) else {
@@ -186,67 +291,175 @@ fn receive_chunk_load_tasks(
             return false;
         };
         match data.added_data {
-            AddedChunkData::Noise(bundle) => {
-                entity.try_insert((Stage::Noise, bundle));
+            AddedChunkData::Unsaved => {
+                entity.try_insert(Unsaved);
+            }
+            AddedChunkData::Saved(saved, biome_map, heightmap) => {
+                entity.try_insert((biome_map, saved.blocks, heightmap, saved.stage));
+                if saved.outdated {
+                    entity.try_insert(Dirty);
+                }
             }
             AddedChunkData::Terrain(terrain) => {
-                entity.try_insert((terrain.clone(), Stage::Sculpt, Blocks(terrain.0)));
+                entity.try_insert(terrain);
+            }
+            AddedChunkData::Pass(PassOutput::Insert(insert), stage) => {
+                insert(&mut entity);
+                entity.try_insert(stage);
             }
-            // AddedChunkData::Blocks(blocks, stage) => {
-            //     entity.try_insert((blocks, stage));
-            // }
-            AddedChunkData::BlockUpdates(block_updates, stage) => {
-                let Ok(blocks) = &mut q_blocks.get_mut(data.entity) else {
+            AddedChunkData::Pass(PassOutput::BlockUpdates(block_updates), stage) => {
+                let Ok((blocks, heightmap)) = &mut q_blocks.get_mut(data.entity) else {
                     log::warn!("Failed to get Blocks component during worldgen update");
                     return false;
                 };
                 blocks.set_changed();
                 block_updates.iter().for_each(|(block, pos)| {
                     *blocks.at_pos_mut(*pos) = *block;
+                    heightmap.update(blocks, *pos);
                 });
-                entity.try_insert(stage);
+                entity.try_insert((stage, Dirty));
             }
         }
         return false;
     });
 }
 
-fn begin_noise_load_tasks(
+/// Marks a chunk which has never been saved
+#[derive(Component)]
+struct Unsaved;
+
+fn begin_saved_chunk_load_tasks(
     mut tasks: ResMut<ChunkLoadTasks>,
-    q_chunk: Query<(Entity, &ChunkPosition), (With<Chunk>, Without<ContinentNoise>)>,
-    continent_noise_generator: Res<ContinentNoiseGenerator>,
-    height_noise_generator: Res<HeightNoiseGenerator>,
-    white_noise: Res<WhiteNoise>,
-    climate_noise: Res<ClimateNoise>,
+    q_chunk: Query<(Entity, &ChunkPosition), (With<Chunk>, Without<Stage>, Without<Unsaved>)>,
+    directory: Res<WorldDirectory>,
+    format: Res<ChunkFormat>,
+    terrain_noise: TerrainNoise,
+    pending_writes: Res<PendingWrites>,
 ) {
+    let sampler = terrain_noise.sampler();
     for (entity, pos) in q_chunk.iter() {
-        if tasks.0.contains_key(pos) {
+        if tasks.0.contains_key(pos) || pending_writes.contains(pos.0) {
             continue;
         }
         let task_pool = AsyncComputeTaskPool::get();
-        let continent_noise_generator = continent_noise_generator.clone();
-        let height_noise_generator = height_noise_generator.clone();
-        let white_noise = white_noise.clone();
-        let climate_noise = climate_noise.clone();
-        // let cave_noise_generator = cave_noise_generator.clone();
+        let directory = directory.0.clone();
+        let format = *format;
+        let sampler = sampler.clone();
         let pos_ivec = pos.0;
         let task = task_pool.spawn(async move {
-            let bundle = generate_chunk_noise(
+            let added_data = match format.read(&directory, pos_ivec) {
+                Ok(Some(saved)) => {
+                    let biome_map = generate_biome_map(pos_ivec, &sampler);
+                    let heightmap = Heightmap::from_blocks(&saved.blocks);
+                    AddedChunkData::Saved(saved, biome_map, heightmap)
+                }
+                Ok(None) => AddedChunkData::Unsaved,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to load chunk at {:?}, regenerating: {}",
+                        pos_ivec,
+                        e
+                    );
+                    AddedChunkData::Unsaved
+                }
+            };
+            ChunkLoadTaskData { entity, added_data }
+        });
+        tasks.0.insert(*pos, task);
+    }
+}
+
+/// Saved chunks don't keep their noise, so their biomes are worked out again when they're loaded
+fn generate_biome_map(chunk_pos: IVec3, sampler: &SurfaceSampler) -> BiomeMap {
+    let continent = ContinentNoise::from_noise(sampler.continent.0.as_ref(), chunk_pos);
+    let temperature = TemperatureNoise::from_noise(sampler.climate.temperature.as_ref(), chunk_pos);
+    let humidity = HumidityNoise::from_noise(sampler.climate.humidity.as_ref(), chunk_pos);
+    BiomeMap::from_noise(&continent, &temperature, &humidity)
+}
+
+/// Saved chunks only keep their final blocks, but neighbouring chunks which are still generating
+/// place structures on the terrain from before any structures, plants or changes. Most saved
+/// chunks are only surrounded by other finished chunks, so the terrain is only sculpted again once
+/// one of their neighbours needs it.
+fn begin_saved_terrain_tasks(
+    mut tasks: ResMut<ChunkLoadTasks>,
+    q_chunk: Query<
+        (Entity, &ChunkPosition, &Stage, &Neighborhood<Stage>),
+        (With<Chunk>, Without<Terrain>),
+    >,
+    white_noise: Res<WhiteNoise>,
+    sculpt_noise: SculptNoise,
+) {
+    for (entity, pos, stage, stage_neighborhood) in q_chunk.iter() {
+        // Chunks which are still generating get their terrain from the sculpt pass
+        if *stage != Stage::final_stage() || tasks.0.contains_key(pos) {
+            continue;
+        }
+        let has_generating_neighbor = stage_neighborhood
+            .0
+            .iter()
+            .flatten()
+            .any(|stage| **stage < Stage::Structures);
+        if !has_generating_neighbor {
+            continue;
+        }
+        let white_noise = white_noise.clone();
+        let sculptor = sculpt_noise.sculptor();
+        let pos_ivec = pos.0;
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let sampler = &sculptor.sampler;
+            let noise = generate_chunk_noise(
                 pos_ivec,
-                continent_noise_generator,
-                height_noise_generator,
+                sampler.continent.clone(),
+                sampler.height.clone(),
                 white_noise,
-                climate_noise,
+                sampler.climate.clone(),
             );
+            let terrain = sculptor.sculpt(pos_ivec, noise.into());
             ChunkLoadTaskData {
                 entity,
-                added_data: AddedChunkData::Noise(bundle),
+                added_data: AddedChunkData::Terrain(terrain),
             }
         });
         tasks.0.insert(*pos, task);
     }
 }
 
+struct NoisePass;
+
+impl WorldgenPass for NoisePass {
+    type Param = (
+        Res<'static, ContinentNoiseGenerator>,
+        Res<'static, HeightNoiseGenerator>,
+        Res<'static, WhiteNoise>,
+        Res<'static, ClimateNoise>,
+    );
+    type ChunkData = ();
+    type Input = (ContinentNoiseGenerator, HeightNoiseGenerator, WhiteNoise, ClimateNoise);
+
+    const INPUT_STAGE: Option<Stage> = None;
+    const OUTPUT_STAGE: Stage = Stage::Noise;
+    const NEIGHBORHOOD_RADIUS: i32 = 0;
+
+    fn prepare(
+        (continent, height, white, climate): &SystemParamItem<Self::Param>,
+        _: QueryItem<Self::ChunkData>,
+    ) -> Self::Input {
+        (
+            (*continent).clone(),
+            (*height).clone(),
+            (*white).clone(),
+            (*climate).clone(),
+        )
+    }
+
+    fn run((continent, height, white, climate): Self::Input, chunk_pos: IVec3) -> PassOutput {
+        PassOutput::insert(generate_chunk_noise(
+            chunk_pos, continent, height, white, climate,
+        ))
+    }
+}
+
 #[derive(Bundle)]
 struct NoiseBundle {
     continent: ContinentNoise,
@@ -254,6 +467,8 @@ struct NoiseBundle {
     white: Noise3d,
     temperature: TemperatureNoise,
     humidity: HumidityNoise,
+    biome_map: BiomeMap,
+    height_scale: HeightScale,
     // cave: CaveNetworkNoise,
 }
 
@@ -269,6 +484,10 @@ fn generate_chunk_noise(
     let white = Noise3d::from_noise(white_noise, chunk_pos);
     let temperature = TemperatureNoise::from_noise(climate_noise.temperature.as_ref(), chunk_pos);
     let humidity = HumidityNoise::from_noise(climate_noise.humidity.as_ref(), chunk_pos);
+    let biome_map = BiomeMap::from_noise(&continent, &temperature, &humidity);
+    let height_scale = HeightScale::from_fn(|pos| {
+        biome::blended_height_scale(*temperature.at_pos(pos), *humidity.at_pos(pos))
+    });
     // let cave = CaveNetworkNoise::from((cave_noise_generator, chunk_pos));
     NoiseBundle {
         continent,
@@ -276,147 +495,456 @@ fn generate_chunk_noise(
         white,
         temperature,
         humidity,
+        biome_map,
+        height_scale,
+    }
+}
+
+#[derive(SystemParam)]
+pub struct TerrainNoise<'w> {
+    seed: Res<'w, WorldSeed>,
+    continent: Res<'w, ContinentNoiseGenerator>,
+    height: Res<'w, HeightNoiseGenerator>,
+    climate: Res<'w, ClimateNoise>,
+    river: Res<'w, RiverNoiseGenerator>,
+    density: Res<'w, DensityNoiseGenerator>,
+    preset: Res<'w, WorldPreset>,
+    config: Res<'w, WorldgenConfig>,
+}
+
+impl TerrainNoise<'_> {
+    pub fn sampler(&self) -> SurfaceSampler {
+        SurfaceSampler {
+            seed: self.seed.0,
+            continent: self.continent.clone(),
+            height: self.height.clone(),
+            climate: self.climate.clone(),
+            river: self.river.clone(),
+            density: self.density.clone(),
+            preset: self.preset.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Works out the shape of the terrain anywhere in the world from its noise, without generating any
+/// chunks. Caves are ignored, and so are overhangs except in `ground_height`.
+#[derive(Clone)]
+pub struct SurfaceSampler {
+    seed: u32,
+    continent: ContinentNoiseGenerator,
+    height: HeightNoiseGenerator,
+    climate: ClimateNoise,
+    river: RiverNoiseGenerator,
+    density: DensityNoiseGenerator,
+    preset: WorldPreset,
+    config: WorldgenConfig,
+}
+
+impl SurfaceSampler {
+    /// Samples the same noise as a world generated from the seed, preset and config
+    pub fn new(seed: u32, preset: WorldPreset, config: WorldgenConfig) -> Self {
+        Self {
+            seed,
+            continent: ContinentNoiseGenerator::new(seed, &config.continent_noise),
+            height: HeightNoiseGenerator::new(seed, &config.height_noise),
+            climate: ClimateNoise::new(seed, config.climate_scale),
+            river: RiverNoiseGenerator::new(seed),
+            density: DensityNoiseGenerator::new(seed),
+            preset,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &WorldgenConfig {
+        &self.config
+    }
+
+    pub fn preset(&self) -> &WorldPreset {
+        &self.preset
+    }
+
+    fn continent_noise(&self, column: IVec2) -> f32 {
+        (self.continent.0.get(column.as_dvec2().to_array()) as f32 - 0.5) * 2.0
+    }
+
+    fn climate(&self, column: IVec2) -> (f32, f32) {
+        let point = column.as_dvec2().to_array();
+        (
+            self.climate.temperature.get(point) as f32,
+            self.climate.humidity.get(point) as f32,
+        )
+    }
+
+    pub fn biome(&self, column: IVec2) -> Biome {
+        if self.continent_noise(column) <= 0.0 {
+            return Biome::Ocean;
+        }
+        let (temperature, humidity) = self.climate(column);
+        Biome::from_climate(temperature, humidity)
+    }
+
+    /// Rivers are where this is 0, see `water`
+    pub fn river_noise(&self, column: IVec2) -> f32 {
+        self.river.0.get(column.as_dvec2().to_array()) as f32 * 2.0 - 1.0
+    }
+
+    /// Blocks below this height are land, before rivers and lakes are carved into it. `None` in
+    /// the ocean and in presets whose terrain isn't shaped by the noise.
+    fn uncarved_land_height(&self, column: IVec2) -> Option<f32> {
+        let continent_noise = self.continent_noise(column);
+        if continent_noise <= 0.0 || !self.preset.has_continents() {
+            return None;
+        }
+        let height_noise = self.height.0.get(column.as_dvec2().to_array()) as f32;
+        let (temperature, humidity) = self.climate(column);
+        let height_scale =
+            biome::blended_height_scale(temperature, humidity) * self.preset.height_scale();
+        Some(land_height(
+            continent_noise,
+            height_noise,
+            height_scale,
+            &self.config,
+        ))
+    }
+
+    /// The height of the land with rivers but not lakes carved into it, which decides where
+    /// lakes go
+    fn land_height_without_lakes(&self, column: IVec2) -> Option<f32> {
+        let land_height = self.uncarved_land_height(column)?;
+        let column = water::carve_column(
+            column,
+            land_height,
+            self.river_noise(column),
+            &[],
+            self.config.sea_level,
+        );
+        Some(column.land_height)
+    }
+
+    /// A land column with rivers and lakes carved into it
+    pub fn water_column(&self, column: IVec2) -> Option<WaterColumn> {
+        let land_height = self.uncarved_land_height(column)?;
+        let lakes = water::lakes_near(self, column, column);
+        Some(water::carve_column(
+            column,
+            land_height,
+            self.river_noise(column),
+            &lakes,
+            self.config.sea_level,
+        ))
+    }
+
+    /// The y coordinate of the highest block of land in a column, or `None` in the ocean, in
+    /// rivers and lakes and in presets whose terrain isn't shaped by the noise. In the overhangs
+    /// preset this samples the 3D noise, which moves the surface of high land.
+    pub fn ground_height(&self, column_pos: IVec2) -> Option<i32> {
+        let column = self.water_column(column_pos)?;
+        if column.water.is_some() {
+            return None;
+        }
+        // Banks keep the shape which they were carved into
+        if self.preset == WorldPreset::Overhangs && !column.is_bank {
+            return Some(density::highest_solid_block(
+                column_pos,
+                column.land_height,
+                &self.density,
+            ));
+        }
+        Some(column.land_height.ceil() as i32 - 1)
     }
 }
 
 #[derive(QueryData)]
 struct TerrainGenerateData {
-    entity: Entity,
-    chunk_pos: &'static ChunkPosition,
-    stage: &'static Stage,
     continent_noise: &'static ContinentNoise,
     height_noise: &'static HeightNoise,
+    biome_map: &'static BiomeMap,
+    height_scale: &'static HeightScale,
     noise: &'static Noise3d,
     // cave_network_noise: &'static CaveNetworkNoise,
 }
 
-fn begin_terrain_sculpt_tasks(
-    mut tasks: ResMut<ChunkLoadTasks>,
-    q_chunk: Query<TerrainGenerateData, (With<Chunk>, Without<Blocks>, Changed<Stage>)>,
-    cave_noise: Res<CaveNetworkNoiseGenerator>,
-) {
-    for item in q_chunk.iter() {
-        if tasks.0.contains_key(item.chunk_pos) || item.stage != &Stage::Noise {
-            continue;
+/// The noise of a chunk which its terrain is sculpted from
+struct ChunkNoise {
+    continent: ContinentNoise,
+    height: HeightNoise,
+    biome_map: BiomeMap,
+    height_scale: HeightScale,
+    white: Noise3d,
+}
+
+impl From<NoiseBundle> for ChunkNoise {
+    fn from(noise: NoiseBundle) -> Self {
+        Self {
+            continent: noise.continent,
+            height: noise.height,
+            biome_map: noise.biome_map,
+            height_scale: noise.height_scale,
+            white: noise.white,
         }
-        let task_pool = AsyncComputeTaskPool::get();
-        let continent_noise = item.continent_noise.clone();
-        let height_noise = item.height_noise.clone();
-        // let cave_network_noise = item.cave_network_noise.clone();
-        let cloned_pos = item.chunk_pos.clone();
-        let cloned_cave_noise = cave_noise.clone();
-        let cloned_noise = item.noise.clone();
-        let task = task_pool.spawn(async move {
-            let blocks = generate_terrain_sculpt_for_chunk(
-                cloned_pos,
-                continent_noise,
-                height_noise,
-                cloned_cave_noise,
-                cloned_noise,
-            );
-            ChunkLoadTaskData {
-                entity: item.entity,
-                added_data: AddedChunkData::Terrain(blocks),
+    }
+}
+
+#[derive(SystemParam)]
+struct SculptNoise<'w> {
+    cave: Res<'w, CaveNetworkNoiseGenerator>,
+    island: Res<'w, IslandNoiseGenerator>,
+    terrain: TerrainNoise<'w>,
+}
+
+impl SculptNoise<'_> {
+    fn sculptor(&self) -> Sculptor {
+        Sculptor {
+            cave: self.cave.clone(),
+            island: self.island.clone(),
+            sampler: self.terrain.sampler(),
+        }
+    }
+}
+
+/// Sculpts the terrain of the world's preset from a chunk's noise
+struct Sculptor {
+    cave: CaveNetworkNoiseGenerator,
+    island: IslandNoiseGenerator,
+    sampler: SurfaceSampler,
+}
+
+impl Sculptor {
+    fn sculpt(&self, chunk_pos: IVec3, noise: ChunkNoise) -> Terrain {
+        let sampler = &self.sampler;
+        match &sampler.preset {
+            WorldPreset::Default | WorldPreset::Amplified => {
+                let height_scale = HeightScale(
+                    noise
+                        .height_scale
+                        .0
+                        .iter()
+                        .map(|scale| scale * sampler.preset.height_scale())
+                        .collect(),
+                );
+                let columns = carve_water(chunk_pos, &noise, &height_scale, sampler);
+                generate_terrain_sculpt_for_chunk(
+                    ChunkPosition(chunk_pos),
+                    noise.continent,
+                    columns,
+                    noise.biome_map,
+                    self.cave.clone(),
+                    noise.white,
+                    sampler.config(),
+                )
             }
-        });
-        tasks.0.insert(*item.chunk_pos, task);
+            WorldPreset::Overhangs => {
+                let columns = carve_water(chunk_pos, &noise, &noise.height_scale, sampler);
+                density::generate_density_terrain_for_chunk(
+                    chunk_pos,
+                    &noise,
+                    columns,
+                    &self.cave,
+                    &sampler.density,
+                    sampler.config(),
+                )
+            }
+            WorldPreset::Superflat { layers } => {
+                preset::generate_superflat_terrain(layers, chunk_pos)
+            }
+            WorldPreset::Void => preset::generate_void_terrain(chunk_pos),
+            WorldPreset::FloatingIslands => preset::generate_floating_island_terrain(
+                &self.island,
+                &noise.biome_map,
+                chunk_pos,
+            ),
+        }
     }
 }
 
-const BEDROCK_DEPTH_CHUNKS: i32 = -5;
-const MAX_DEPTH: i32 = BEDROCK_DEPTH_CHUNKS * CHUNK_SIZE_I32;
+/// Generates the terrain of the world's preset
+struct SculptPass;
+
+impl WorldgenPass for SculptPass {
+    type Param = SculptNoise<'static>;
+    type ChunkData = TerrainGenerateData;
+    type Input = (ChunkNoise, Sculptor);
+
+    const INPUT_STAGE: Option<Stage> = Some(Stage::Noise);
+    const OUTPUT_STAGE: Stage = Stage::Sculpt;
+    const NEIGHBORHOOD_RADIUS: i32 = 0;
+
+    fn prepare(
+        sculpt_noise: &SystemParamItem<Self::Param>,
+        item: QueryItem<Self::ChunkData>,
+    ) -> Self::Input {
+        let noise = ChunkNoise {
+            continent: item.continent_noise.clone(),
+            height: item.height_noise.clone(),
+            biome_map: item.biome_map.clone(),
+            height_scale: item.height_scale.clone(),
+            white: item.noise.clone(),
+        };
+        (noise, sculpt_noise.sculptor())
+    }
+
+    fn run((noise, sculptor): Self::Input, chunk_pos: IVec3) -> PassOutput {
+        let terrain = sculptor.sculpt(chunk_pos, noise);
+        let blocks = Blocks(terrain.0.clone());
+        PassOutput::insert((terrain, Heightmap::from_blocks(&blocks), blocks))
+    }
+}
+
+/// Every land column of a chunk, with rivers and lakes carved into it
+fn carve_water(
+    chunk_pos: IVec3,
+    noise: &ChunkNoise,
+    height_scale: &HeightScale,
+    sampler: &SurfaceSampler,
+) -> Vec<WaterColumn> {
+    let origin = chunk_pos.xz() * CHUNK_SIZE_I32;
+    let lakes = water::lakes_near(sampler, origin, origin + IVec2::splat(CHUNK_SIZE_I32 - 1));
+    <Vec<WaterColumn> as SpatiallyMapped<2>>::from_fn(|[x, z]| {
+        let continent_noise = (noise.continent.at_pos([x, z]) - 0.5) * 2.0;
+        let land_height = land_height(
+            continent_noise,
+            *noise.height.at_pos([x, z]),
+            *height_scale.at_pos([x, z]),
+            sampler.config(),
+        );
+        let column = origin + IVec2::new(x as i32, z as i32);
+        water::carve_column(
+            column,
+            land_height,
+            sampler.river_noise(column),
+            &lakes,
+            sampler.config().sea_level,
+        )
+    })
+}
 
 fn generate_terrain_sculpt_for_chunk(
     pos: ChunkPosition,
     continent: ContinentNoise,
-    height: HeightNoise,
+    columns: Vec<WaterColumn>,
+    biome_map: BiomeMap,
     cave_noise: CaveNetworkNoiseGenerator,
     noise: Noise3d,
+    config: &WorldgenConfig,
     // cave_network_noise: CaveNetworkNoise,
 ) -> Terrain {
-    if pos.0.y < BEDROCK_DEPTH_CHUNKS {
+    if pos.0.y < config.bedrock_depth_chunks {
         return Terrain(vec![Block::Air; CHUNK_LENGTH]);
     }
 
-    const CONTINENT_SCALE: f32 = 60.0;
-    const LAND_HEIGHT_SCALE: f32 = 50.0;
-    const SEA_LEVEL: i32 = 0;
-    const DIRT_DEPTH: i32 = 4;
-    const SEA_SAND_DEPTH: f32 = 2.0;
     let chunk_pos = pos.0;
     Terrain::from_fn(|pos| {
         let [x, _, z] = pos;
         let world_pos = chunk_pos * CHUNK_SIZE_I32 + IVec3::from(pos.map(|x| x as i32));
-        let bedrock_offset = if noise.at_pos([x, 0, z]) < &0.5 { 0 } else { 1 };
-        if world_pos.y < MAX_DEPTH {
-            return Block::Air;
-        } else if world_pos.y <= MAX_DEPTH + bedrock_offset {
-            return Block::Bedrock;
+        if let Some(block) = bedrock_block(world_pos.y, &noise, [x, z], config) {
+            return block;
         }
         let y = world_pos.y as f32;
         let continent_noise = (continent.at_pos([x, z]) - 0.5) * 2.0;
         let cave_noise = cave_noise.get(world_pos.into());
-        let cave_threshold = get_cave_threshold(world_pos.y);
+        let cave_threshold = get_cave_threshold(world_pos.y, config);
         let is_cave = cave_noise < cave_threshold;
-        // Ocean
         if continent_noise <= 0.0 {
-            return if y < continent_noise * CONTINENT_SCALE {
-                if is_cave {
-                    Block::Air
-                } else if y < continent_noise * CONTINENT_SCALE - SEA_SAND_DEPTH {
-                    Block::Stone
-                } else {
-                    Block::Sand
-                }
-            } else if world_pos.y <= SEA_LEVEL {
-                Block::Water
-            } else {
-                Block::Air
-            };
+            return ocean_block(world_pos.y, continent_noise, is_cave, config);
         }
         // Land
-        let coast_height_factor = stretch_range_onto_unit_interval(continent_noise, 0.0, 0.2);
-        let height_noise = height.at_pos([x, z]);
-        let land_height = height_noise * coast_height_factor * LAND_HEIGHT_SCALE;
-        let is_coast = land_height <= 2.0;
+        let WaterColumn {
+            land_height,
+            water,
+            is_bank,
+        } = *columns.at_pos([x, z]);
+        let is_coast = land_height <= 2.0 || is_bank;
+        let biome = biome_map.at_pos([x, z]);
         if y < land_height && is_cave {
             return Block::Air;
         }
-        if y < land_height - DIRT_DEPTH as f32 {
+        if y < land_height - biome.soil_depth() as f32 {
             Block::Stone
         } else if y < land_height - 1.0 {
-            Block::Dirt
+            biome.subsurface_block()
         } else if y < land_height {
-            if is_coast {
+            if let Some(water) = water {
+                water.bed_block()
+            } else if is_coast {
                 Block::Sand
             } else {
-                Block::Grass
+                biome.surface_block()
             }
+        } else if water.is_some_and(|water| world_pos.y <= water.level()) {
+            Block::Water
         } else {
             Block::Air
         }
     })
 }
 
-fn get_cave_threshold(height: i32) -> f64 {
-    const MIN_DEPTH_THRESHOLD: f64 = 0.095;
-    const MAX_DEPTH_THRESHOLD: f64 = 0.100;
-    const DEPTH_START: i32 = -CHUNK_SIZE_I32;
-    const DEPTH_END: i32 = -CHUNK_SIZE_I32 * 8;
+/// Air below the bedrock and bedrock at the bottom of the world, or `None` above it
+fn bedrock_block(
+    y: i32,
+    noise: &Noise3d,
+    [x, z]: [usize; 2],
+    config: &WorldgenConfig,
+) -> Option<Block> {
+    let bedrock_offset = if noise.at_pos([x, 0, z]) < &0.5 { 0 } else { 1 };
+    let max_depth = config.max_depth();
+    if y < max_depth {
+        Some(Block::Air)
+    } else if y <= max_depth + bedrock_offset {
+        Some(Block::Bedrock)
+    } else {
+        None
+    }
+}
+
+/// The block at a height in a column where the continent noise is at most 0
+fn ocean_block(y: i32, continent_noise: f32, is_cave: bool, config: &WorldgenConfig) -> Block {
+    let floor_height = continent_noise * config.continent_scale;
+    if (y as f32) < floor_height {
+        if is_cave {
+            Block::Air
+        } else if (y as f32) < floor_height - config.sea_sand_depth {
+            Block::Stone
+        } else {
+            Block::Sand
+        }
+    } else if y <= config.sea_level {
+        Block::Water
+    } else {
+        Block::Air
+    }
+}
+
+/// Blocks below this height are land. The continent noise is between 0 and 1 on land.
+fn land_height(
+    continent_noise: f32,
+    height_noise: f32,
+    height_scale: f32,
+    config: &WorldgenConfig,
+) -> f32 {
+    let coast_height_factor = stretch_range_onto_unit_interval(continent_noise, 0.0, 0.2);
+    height_noise * coast_height_factor * config.land_height_scale * height_scale
+}
+
+fn get_cave_threshold(height: i32, config: &WorldgenConfig) -> f64 {
     const BUFFER_BEFORE_BEDROCK: i32 = 4;
     const CAVE_TAPER_THICKNESS: i32 = 4;
-    const CAVE_TAPER_END: i32 = MAX_DEPTH + BUFFER_BEFORE_BEDROCK;
-    const CAVE_TAPER_START: i32 = CAVE_TAPER_END + CAVE_TAPER_THICKNESS;
+    let caves = &config.caves;
+    let cave_taper_end = config.max_depth() + BUFFER_BEFORE_BEDROCK;
+    let cave_taper_start = cave_taper_end + CAVE_TAPER_THICKNESS;
 
     let buffer_t = stretch_range_onto_unit_interval(
         height as f32,
-        CAVE_TAPER_END as f32,
-        CAVE_TAPER_START as f32,
+        cave_taper_end as f32,
+        cave_taper_start as f32,
     );
 
-    let t = stretch_range_onto_unit_interval(height as f32, DEPTH_END as f32, DEPTH_START as f32)
-        as f64;
-    return MAX_DEPTH_THRESHOLD.lerp(MIN_DEPTH_THRESHOLD, t) * buffer_t as f64;
+    let t = stretch_range_onto_unit_interval(
+        height as f32,
+        caves.depth_end as f32,
+        caves.depth_start as f32,
+    ) as f64;
+    return caves.max_threshold.lerp(caves.min_threshold, t) * buffer_t as f64;
 }
 
 fn stretch_range_onto_unit_interval(value: f32, a: f32, b: f32) -> f32 {
@@ -424,46 +952,3 @@ fn stretch_range_onto_unit_interval(value: f32, a: f32, b: f32) -> f32 {
     let scaled_value = (value - a) / range_size;
     return scaled_value.clamp(0., 1.);
 }
-
-#[derive(QueryData)]
-struct StructureQueryData {
-    entity: Entity,
-    pos: &'static ChunkPosition,
-    stage: &'static Stage,
-    terrain_neighborhood: &'static Neighborhood<Terrain>,
-    noise_neighborhood: &'static Neighborhood<Noise3d>,
-}
-
-fn begin_structure_load_tasks(
-    mut tasks: ResMut<ChunkLoadTasks>,
-    q_chunk: Query<StructureQueryData, (With<Chunk>, With<CompleteNeighborhood<Terrain>>)>,
-) {
-    for item in q_chunk.iter() {
-        if tasks.0.contains_key(item.pos) || item.stage != &Stage::Sculpt {
-            continue;
-        }
-        let task_pool = AsyncComputeTaskPool::get();
-        let blocks = item.terrain_neighborhood.clone();
-        let noise = item.noise_neighborhood.clone();
-        let entity = item.entity;
-        let task = task_pool.spawn(async move {
-            let added_data = generate_structures(blocks, noise);
-            ChunkLoadTaskData { entity, added_data }
-        });
-        tasks.0.insert(*item.pos, task);
-    }
-}
-
-fn generate_structures(
-    blocks: Neighborhood<Terrain>,
-    noise: Neighborhood<Noise3d>,
-) -> AddedChunkData {
-    // TODO: Vary structure type by biome
-    // let structure = StructureType::Tree;
-    // let updates = structure.get_structures(&blocks, &noise);
-
-    AddedChunkData::BlockUpdates(
-        StructureType::Tree.get_structure_blocks(&blocks, &noise),
-        Stage::Structures,
-    )
-}
@@ -0,0 +1,152 @@
+use crate::{
+    block::Block,
+    chunk::{
+        data::{BiomeMap, ContinentNoise, HumidityNoise, TemperatureNoise},
+        spatial::SpatiallyMapped,
+    },
+};
+use bevy::prelude::*;
+
+/// Climate values closer than this to the border between two biomes blend their height scales, so
+/// that biome borders don't become cliffs
+const HEIGHT_SCALE_BLEND_DISTANCE: f32 = 0.08;
+/// The deepest soil of any biome, see `Biome::soil_depth`
+pub const MAX_SOIL_DEPTH: i32 = 6;
+const COLD_TEMPERATURE: f32 = -0.25;
+const HOT_TEMPERATURE: f32 = 0.25;
+const COLD_BAND: ClimateBand = ClimateBand {
+    dry: Biome::Tundra,
+    humid: Biome::Taiga,
+    humidity: 0.0,
+};
+const TEMPERATE_BAND: ClimateBand = ClimateBand {
+    dry: Biome::Plains,
+    humid: Biome::Forest,
+    humidity: -0.2,
+};
+const HOT_BAND: ClimateBand = ClimateBand {
+    dry: Biome::Desert,
+    humid: Biome::Savanna,
+    humidity: 0.1,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Ocean,
+    Tundra,
+    Taiga,
+    #[default]
+    Plains,
+    Forest,
+    Desert,
+    Savanna,
+}
+
+impl Biome {
+    /// Classifies a land column from its temperature and humidity, which are both roughly in the
+    /// range -1 to 1
+    pub fn from_climate(temperature: f32, humidity: f32) -> Self {
+        let band = if temperature < COLD_TEMPERATURE {
+            COLD_BAND
+        } else if temperature < HOT_TEMPERATURE {
+            TEMPERATE_BAND
+        } else {
+            HOT_BAND
+        };
+        band.biome(humidity)
+    }
+
+    /// The block at the top of each land column
+    pub fn surface_block(&self) -> Block {
+        match self {
+            Self::Ocean | Self::Desert => Block::Sand,
+            Self::Tundra => Block::Dirt,
+            _ => Block::Grass,
+        }
+    }
+
+    /// The block between the surface and the stone below it
+    pub fn subsurface_block(&self) -> Block {
+        match self {
+            Self::Ocean | Self::Desert => Block::Sand,
+            _ => Block::Dirt,
+        }
+    }
+
+    /// How many blocks of surface and subsurface lie on top of the stone
+    pub fn soil_depth(&self) -> i32 {
+        match self {
+            Self::Tundra => 2,
+            Self::Desert => 6,
+            _ => 4,
+        }
+    }
+
+    /// Multiplies the height of the land above sea level
+    pub fn height_scale(&self) -> f32 {
+        match self {
+            Self::Ocean => 1.0,
+            Self::Tundra => 0.4,
+            Self::Taiga => 1.3,
+            Self::Plains => 0.5,
+            Self::Forest => 1.0,
+            Self::Desert => 0.35,
+            Self::Savanna => 0.7,
+        }
+    }
+}
+
+/// Blends the height scales of the biomes near a point in climate space. Climate noise changes
+/// smoothly, so this also changes smoothly across the world.
+pub fn blended_height_scale(temperature: f32, humidity: f32) -> f32 {
+    let warm = blend_factor(temperature, COLD_TEMPERATURE);
+    let hot = blend_factor(temperature, HOT_TEMPERATURE);
+    (1.0 - warm) * COLD_BAND.height_scale(humidity)
+        + (warm - hot) * TEMPERATE_BAND.height_scale(humidity)
+        + hot * HOT_BAND.height_scale(humidity)
+}
+
+/// Goes from 0 to 1 as the value passes the threshold
+fn blend_factor(value: f32, threshold: f32) -> f32 {
+    ((value - threshold) / (2.0 * HEIGHT_SCALE_BLEND_DISTANCE) + 0.5).clamp(0.0, 1.0)
+}
+
+/// Biomes with a similar temperature, split by humidity
+struct ClimateBand {
+    dry: Biome,
+    humid: Biome,
+    humidity: f32,
+}
+
+impl ClimateBand {
+    fn biome(&self, humidity: f32) -> Biome {
+        if humidity < self.humidity {
+            self.dry
+        } else {
+            self.humid
+        }
+    }
+
+    fn height_scale(&self, humidity: f32) -> f32 {
+        let t = blend_factor(humidity, self.humidity);
+        self.dry
+            .height_scale()
+            .lerp(self.humid.height_scale(), t)
+    }
+}
+
+impl BiomeMap {
+    pub fn from_noise(
+        continent: &ContinentNoise,
+        temperature: &TemperatureNoise,
+        humidity: &HumidityNoise,
+    ) -> Self {
+        Self::from_fn(|pos| {
+            if *continent.at_pos(pos) <= 0.5 {
+                Biome::Ocean
+            } else {
+                Biome::from_climate(*temperature.at_pos(pos), *humidity.at_pos(pos))
+            }
+        })
+    }
+}
@@ -13,8 +13,10 @@ use crate::{
     },
     ui::block_icons::BlockMeshes,
     world::{
+        heightmap::Heightmap,
         index::ChunkIndex,
         neighborhood::{ComponentIndex, Neighborhood},
+        save::Dirty,
         stage::Stage,
         WorldSet,
     },
@@ -57,9 +59,10 @@ pub struct SetBlockEvent {
 }
 
 fn set_block(
+    mut commands: Commands,
     chunk_index: Res<ChunkIndex>,
     mut block_events: EventReader<SetBlockEvent>,
-    mut q_blocks: Query<&mut Blocks>,
+    mut q_blocks: Query<(&mut Blocks, &mut Heightmap)>,
     mut block_updates: ResMut<BlockUpdateEventQueue>,
 ) {
     for event in block_events.read() {
@@ -79,10 +82,13 @@ fn set_block(
         else {
             continue;
         };
-        let Some(mut blocks) = q_blocks.get_mut(*entity).ok() else {
+        let Some((mut blocks, mut heightmap)) = q_blocks.get_mut(*entity).ok() else {
             continue;
         };
-        *blocks.at_pos_mut([local_x, local_y, local_z]) = event.block;
+        let local_pos = [local_x, local_y, local_z];
+        *blocks.at_pos_mut(local_pos) = event.block;
+        heightmap.update(&blocks, local_pos);
+        commands.entity(*entity).try_insert(Dirty);
         block_updates.update_around(event.world_pos);
     }
 }
@@ -206,6 +212,7 @@ fn do_block_updates(
     mut block_update_event_queue: ResMut<BlockUpdateEventQueue>,
     block_index: Res<ComponentIndex<Blocks>>,
     mut spawn_falling_sand_events: EventWriter<SpawnFallingSandEvent>,
+    mut set_block_events: EventWriter<SetBlockEvent>,
 ) {
     while let Some(update) = block_update_event_queue.pop() {
         let world_pos = update.world_pos;
@@ -216,6 +223,12 @@ fn do_block_updates(
             Block::Sand if sand_should_fall(world_pos, block_index.as_ref()) => {
                 spawn_falling_sand_events.write(world_pos.into());
             }
+            block if block.is_plant() && !plant_is_supported(world_pos, block_index.as_ref()) => {
+                set_block_events.write(SetBlockEvent {
+                    block: Block::Air,
+                    world_pos: world_pos.to_array(),
+                });
+            }
             _ => {}
         }
     }
@@ -229,6 +242,14 @@ fn sand_should_fall(world_pos: IVec3, block_index: &ComponentIndex<Blocks>) -> b
     }
 }
 
+/// Plants break when the ground under them is taken away
+fn plant_is_supported(world_pos: IVec3, block_index: &ComponentIndex<Blocks>) -> bool {
+    let below = world_pos - IVec3::Y;
+    block_index
+        .at_pos(below)
+        .is_some_and(|block| block.is_solid() && block != &Block::Water)
+}
+
 #[derive(Event)]
 struct SpawnFallingSandEvent {
     world_pos: IVec3,
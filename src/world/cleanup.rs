@@ -1,6 +1,12 @@
 use bevy::prelude::*;
 
-use crate::{chunk::Chunk, item::DroppedItem, player::Player, state::AppState};
+use crate::{
+    chunk::Chunk,
+    item::DroppedItem,
+    player::Player,
+    portal::{PortalCamera, PortalEntrance},
+    state::AppState,
+};
 
 pub struct CleanupPlugin;
 
@@ -10,18 +16,16 @@ impl Plugin for CleanupPlugin {
     }
 }
 
-fn despawn(
-    mut commands: Commands,
-    q_despawnable: Query<
-        Entity,
-        Or<(
-            With<Chunk>,
-            With<DroppedItem>,
-            With<Player>,
-            With<DirectionalLight>,
-        )>,
-    >,
-) {
+type Despawnable = Or<(
+    With<Chunk>,
+    With<DroppedItem>,
+    With<Player>,
+    With<DirectionalLight>,
+    With<PortalEntrance>,
+    With<PortalCamera>,
+)>;
+
+fn despawn(mut commands: Commands, q_despawnable: Query<Entity, Despawnable>) {
     for entity in q_despawnable.iter() {
         commands.entity(entity).try_despawn();
     }
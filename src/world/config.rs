@@ -0,0 +1,200 @@
+//! Parameters of the terrain generator. Each world keeps its own copy in `worldgen.ron` in its
+//! directory, which is written with the defaults when the world is first entered, so changing the
+//! defaults doesn't change the terrain of existing worlds. While in game the file is checked for
+//! changes, and editing it regenerates every loaded chunk with the new parameters.
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use crate::{chunk::CHUNK_SIZE_I32, state::AppState};
+
+use super::{
+    save::{LoadWorldMetadata, WorldDirectory},
+    seed::LoadSeed,
+};
+
+const FILE_NAME: &str = "worldgen.ron";
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct WorldgenConfigPlugin;
+
+impl Plugin for WorldgenConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WorldgenConfigReloaded>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                load.in_set(LoadSeed)
+                    .after(LoadWorldMetadata),
+            )
+            .add_systems(
+                Update,
+                reload_if_modified
+                    .run_if(in_state(AppState::InGame).and(on_timer(RELOAD_CHECK_INTERVAL))),
+            );
+    }
+}
+
+/// See docs/worldgen_config_format.md
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WorldgenConfig {
+    /// Blocks which aren't land are water up to this height
+    pub sea_level: i32,
+    /// Depth of the ocean floor where the continent noise is at its lowest
+    pub continent_scale: f32,
+    /// Height of the land where the height noise is at its highest, before biomes and presets
+    /// scale it
+    pub land_height_scale: f32,
+    /// Thickness of the sand on the ocean floor
+    pub sea_sand_depth: f32,
+    /// The chunk whose bottom is the bedrock. Chunks below it are empty.
+    pub bedrock_depth_chunks: i32,
+    /// Size of the areas which share a climate, and so a biome
+    pub climate_scale: f64,
+    pub continent_noise: NoiseConfig,
+    pub height_noise: NoiseConfig,
+    pub caves: CaveConfig,
+}
+
+impl Default for WorldgenConfig {
+    fn default() -> Self {
+        Self {
+            sea_level: 0,
+            continent_scale: 60.0,
+            land_height_scale: 50.0,
+            sea_sand_depth: 2.0,
+            bedrock_depth_chunks: -5,
+            climate_scale: 1000.0,
+            continent_noise: NoiseConfig {
+                octaves: 6,
+                scale: 1000.0,
+            },
+            height_noise: NoiseConfig {
+                octaves: 3,
+                scale: 100.0,
+            },
+            caves: CaveConfig::default(),
+        }
+    }
+}
+
+impl WorldgenConfig {
+    /// The y coordinate of the bottom of the bedrock
+    pub fn max_depth(&self) -> i32 {
+        self.bedrock_depth_chunks * CHUNK_SIZE_I32
+    }
+}
+
+/// Layers of simplex noise, each with half the scale and half the amplitude of the one before
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NoiseConfig {
+    pub octaves: u32,
+    /// Size of the features of the first layer, in blocks
+    pub scale: f64,
+}
+
+/// Cave noise below the threshold is a cave. The threshold goes from `min_threshold` at
+/// `depth_start` to `max_threshold` at `depth_end`, so caves get wider further down.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct CaveConfig {
+    pub min_threshold: f64,
+    pub max_threshold: f64,
+    pub depth_start: i32,
+    pub depth_end: i32,
+}
+
+impl Default for CaveConfig {
+    fn default() -> Self {
+        Self {
+            min_threshold: 0.095,
+            max_threshold: 0.100,
+            depth_start: -CHUNK_SIZE_I32,
+            depth_end: -CHUNK_SIZE_I32 * 8,
+        }
+    }
+}
+
+/// Sent when the world's config file has been edited while in game
+#[derive(Event)]
+pub struct WorldgenConfigReloaded;
+
+/// When the config file was last modified, as of when it was last read
+#[derive(Resource)]
+struct ConfigModified(Option<SystemTime>);
+
+fn load(mut commands: Commands, directory: Res<WorldDirectory>) {
+    let config = match read(&directory.0) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            let config = WorldgenConfig::default();
+            if let Err(e) = write(&directory.0, &config) {
+                warn!("Failed to save worldgen config: {}", e);
+            }
+            config
+        }
+        Err(e) => {
+            // The file is left alone so that it can be fixed
+            warn!("Failed to load worldgen config, using the defaults: {}", e);
+            WorldgenConfig::default()
+        }
+    };
+    commands.insert_resource(ConfigModified(modified_time(&directory.0)));
+    commands.insert_resource(config);
+}
+
+fn reload_if_modified(
+    directory: Res<WorldDirectory>,
+    mut last_modified: ResMut<ConfigModified>,
+    mut config: ResMut<WorldgenConfig>,
+    mut reloaded: EventWriter<WorldgenConfigReloaded>,
+) {
+    let modified = modified_time(&directory.0);
+    if modified == last_modified.0 {
+        return;
+    }
+    last_modified.0 = modified;
+    match read(&directory.0) {
+        Ok(Some(new_config)) if new_config != *config => {
+            info!("Worldgen config changed, regenerating loaded chunks");
+            *config = new_config;
+            reloaded.write(WorldgenConfigReloaded);
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to reload worldgen config: {}", e),
+    }
+}
+
+fn modified_time(directory: &Path) -> Option<SystemTime> {
+    fs::metadata(directory.join(FILE_NAME))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub fn write(directory: &Path, config: &WorldgenConfig) -> io::Result<()> {
+    let contents = ron::ser::to_string_pretty(config, PrettyConfig::default())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    fs::create_dir_all(directory)?;
+    fs::write(directory.join(FILE_NAME), contents)
+}
+
+/// Returns `None` if the world has no config file
+pub fn read(directory: &Path) -> io::Result<Option<WorldgenConfig>> {
+    match read_file(&directory.join(FILE_NAME)) {
+        Ok(config) => Ok(Some(config)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads a config file which may be outside of a world
+pub fn read_file(path: &Path) -> io::Result<WorldgenConfig> {
+    let contents = fs::read(path)?;
+    ron::de::from_bytes(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
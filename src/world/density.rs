@@ -0,0 +1,153 @@
+//! Terrain of the overhangs preset. Each block is solid where its density is above 0, which is
+//! its depth below the usual land height plus some 3D noise. The noise is stronger on higher land,
+//! so mountains get overhangs, arches and cliffs, while coasts, oceans and the bedrock are the same
+//! as in the usual terrain.
+
+use bevy::prelude::*;
+use noise::NoiseFn;
+
+use crate::{
+    block::Block,
+    chunk::{data::Terrain, spatial::SpatiallyMapped, CHUNK_LENGTH, CHUNK_SIZE, CHUNK_SIZE_I32},
+};
+
+use super::{
+    bedrock_block,
+    biome::MAX_SOIL_DEPTH,
+    config::WorldgenConfig,
+    get_cave_threshold, ocean_block, stretch_range_onto_unit_interval,
+    water::WaterColumn,
+    world_noise::{CaveNetworkNoiseGenerator, DensityNoiseGenerator},
+    ChunkNoise,
+};
+
+/// The furthest that the noise can move the surface of the highest land, in blocks
+const OVERHANG_STRENGTH: f32 = 24.0;
+/// Land lower than this has no overhangs, and they get stronger up to the higher height
+const OVERHANG_MIN_HEIGHT: f32 = 8.0;
+const OVERHANG_FULL_HEIGHT: f32 = 48.0;
+/// Land at most this high is a coast, which is covered with sand
+const COAST_HEIGHT: f32 = 2.0;
+
+pub(super) fn generate_density_terrain_for_chunk(
+    chunk_pos: IVec3,
+    noise: &ChunkNoise,
+    water_columns: Vec<WaterColumn>,
+    cave_noise: &CaveNetworkNoiseGenerator,
+    density_noise: &DensityNoiseGenerator,
+    config: &WorldgenConfig,
+) -> Terrain {
+    if chunk_pos.y < config.bedrock_depth_chunks {
+        return Terrain(vec![Block::Air; CHUNK_LENGTH]);
+    }
+    // Blocks above the chunk decide how deep the soil of its highest blocks is
+    let column_height = CHUNK_SIZE + MAX_SOIL_DEPTH as usize;
+    let columns = <Vec<(f32, Vec<bool>)> as SpatiallyMapped<2>>::from_fn(|[x, z]| {
+        let continent_noise = continent_at(noise, [x, z]);
+        if continent_noise <= 0.0 {
+            return (0.0, vec![]);
+        }
+        let water_column = water_columns.at_pos([x, z]);
+        let land_height = water_column.land_height;
+        // Rivers and lakes keep the shape which they were carved into
+        if water_column.water.is_some() || water_column.is_bank {
+            let is_solid = (0..column_height)
+                .map(|y| ((chunk_pos.y * CHUNK_SIZE_I32 + y as i32) as f32) < land_height)
+                .collect();
+            return (land_height, is_solid);
+        }
+        let strength = overhang_strength(land_height);
+        let is_solid = (0..column_height)
+            .map(|y| {
+                let world_pos = chunk_pos * CHUNK_SIZE_I32
+                    + IVec3::new(x as i32, y as i32, z as i32);
+                is_solid(world_pos, land_height, strength, density_noise)
+            })
+            .collect();
+        (land_height, is_solid)
+    });
+    Terrain::from_fn(|[x, y, z]| {
+        let world_pos = chunk_pos * CHUNK_SIZE_I32 + IVec3::new(x as i32, y as i32, z as i32);
+        if let Some(block) = bedrock_block(world_pos.y, &noise.white, [x, z], config) {
+            return block;
+        }
+        let is_cave = cave_noise.get(world_pos.into()) < get_cave_threshold(world_pos.y, config);
+        let continent_noise = continent_at(noise, [x, z]);
+        if continent_noise <= 0.0 {
+            return ocean_block(world_pos.y, continent_noise, is_cave, config);
+        }
+        let (land_height, column) = columns.at_pos([x, z]);
+        let WaterColumn { water, is_bank, .. } = *water_columns.at_pos([x, z]);
+        if !column[y] && water.is_some_and(|water| world_pos.y <= water.level()) {
+            return Block::Water;
+        }
+        if !column[y] || is_cave {
+            return Block::Air;
+        }
+        let biome = noise.biome_map.at_pos([x, z]);
+        let depth = column[y + 1..]
+            .iter()
+            .position(|solid| !solid)
+            .map(|depth| depth as i32 + 1);
+        match (depth, water) {
+            (Some(1), Some(water)) => water.bed_block(),
+            (Some(1), None) if *land_height <= COAST_HEIGHT || is_bank => Block::Sand,
+            (Some(1), None) => biome.surface_block(),
+            (Some(depth), _) if depth <= biome.soil_depth() => biome.subsurface_block(),
+            _ => Block::Stone,
+        }
+    })
+}
+
+/// How far the noise can move the surface of land of this height
+fn overhang_strength(land_height: f32) -> f32 {
+    OVERHANG_STRENGTH
+        * stretch_range_onto_unit_interval(land_height, OVERHANG_MIN_HEIGHT, OVERHANG_FULL_HEIGHT)
+}
+
+fn is_solid(
+    world_pos: IVec3,
+    land_height: f32,
+    strength: f32,
+    density_noise: &DensityNoiseGenerator,
+) -> bool {
+    let depth = land_height - world_pos.y as f32;
+    // The noise can't change the sign of the density this far from the surface
+    if depth.abs() >= strength {
+        return depth > 0.0;
+    }
+    let offset = density_noise
+        .0
+        .get(world_pos.as_dvec3().to_array()) as f32
+        * 2.0
+        - 1.0;
+    depth + offset * strength > 0.0
+}
+
+/// The y coordinate of the highest solid block of a land column away from rivers and lakes. Caves
+/// are ignored, and there may be air below the block if it's the top of an overhang.
+pub(super) fn highest_solid_block(
+    column: IVec2,
+    land_height: f32,
+    density_noise: &DensityNoiseGenerator,
+) -> i32 {
+    let strength = overhang_strength(land_height);
+    // Every block this far below the usual land height is solid
+    let lowest = (land_height - strength).floor() as i32;
+    let highest = (land_height + strength).ceil() as i32;
+    (lowest..=highest)
+        .rev()
+        .find(|y| {
+            is_solid(
+                IVec3::new(column.x, *y, column.y),
+                land_height,
+                strength,
+                density_noise,
+            )
+        })
+        .unwrap_or(lowest)
+}
+
+fn continent_at(noise: &ChunkNoise, [x, z]: [usize; 2]) -> f32 {
+    (noise.continent.at_pos([x, z]) - 0.5) * 2.0
+}
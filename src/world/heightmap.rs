@@ -0,0 +1,106 @@
+//! The highest blocks of every column, so that systems which look for the surface don't have to
+//! scan the blocks of each chunk in the column. Each chunk has a heightmap of its own blocks, which
+//! is worked out when the chunk is generated or loaded and kept up to date as its blocks change.
+//! `ComponentIndex<Heightmap>` combines the heightmaps of a column of chunks.
+
+use bevy::prelude::*;
+
+use crate::{
+    block::Block,
+    chunk::{data::Blocks, spatial::SpatiallyMapped, CHUNK_SIZE, CHUNK_SIZE_I32},
+    define_spatial,
+};
+
+use super::neighborhood::ComponentIndex;
+
+/// The highest blocks of a column within one chunk, in the chunk's coordinates
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ColumnHeights {
+    /// The highest block which can be stood on. Water doesn't count.
+    pub solid: Option<u8>,
+    /// The highest block which isn't air
+    pub non_air: Option<u8>,
+}
+
+define_spatial!(Heightmap, 2, ColumnHeights);
+
+impl Heightmap {
+    pub fn from_blocks(blocks: &Blocks) -> Self {
+        Self::from_fn(|[x, z]| ColumnHeights {
+            solid: highest_below(blocks, [x, z], CHUNK_SIZE, is_solid_ground),
+            non_air: highest_below(blocks, [x, z], CHUNK_SIZE, Block::is_meshable),
+        })
+    }
+
+    /// Updates the column of a block which has just been replaced
+    pub fn update(&mut self, blocks: &Blocks, [x, y, z]: [usize; 3]) {
+        let block = blocks.at_pos([x, y, z]);
+        let heights = self.at_pos_mut([x, z]);
+        heights.solid = updated_height(heights.solid, y, is_solid_ground(block), || {
+            highest_below(blocks, [x, z], y, is_solid_ground)
+        });
+        heights.non_air = updated_height(heights.non_air, y, block.is_meshable(), || {
+            highest_below(blocks, [x, z], y, Block::is_meshable)
+        });
+    }
+}
+
+/// Blocks which can be stood on. Water is solid for physics, but it isn't ground.
+fn is_solid_ground(block: &Block) -> bool {
+    block.is_solid() && block != &Block::Water
+}
+
+/// The highest block of a column which is below `y` and matches the predicate
+fn highest_below(
+    blocks: &Blocks,
+    [x, z]: [usize; 2],
+    y: usize,
+    predicate: impl Fn(&Block) -> bool,
+) -> Option<u8> {
+    (0..y)
+        .rev()
+        .find(|y| predicate(blocks.at_pos([x, *y, z])))
+        .map(|y| y as u8)
+}
+
+/// The height of a column once the block at `y` has been replaced. The rest of the column is only
+/// scanned when the highest block is taken away.
+fn updated_height(
+    height: Option<u8>,
+    y: usize,
+    counts: bool,
+    highest_below: impl FnOnce() -> Option<u8>,
+) -> Option<u8> {
+    let y = y as u8;
+    match height {
+        _ if counts => Some(height.map_or(y, |height| height.max(y))),
+        Some(height) if height == y => highest_below(),
+        _ => height,
+    }
+}
+
+impl ComponentIndex<Heightmap> {
+    /// The y coordinate of the highest block in a column which can be stood on, or `None` if no
+    /// loaded chunk of the column has one. Chunks above the loaded chunks aren't looked at.
+    pub fn highest_solid(&self, column: IVec2) -> Option<i32> {
+        self.highest(column, |heights| heights.solid)
+    }
+
+    /// The y coordinate of the highest block in a column which isn't air, or `None` if every
+    /// loaded chunk of the column is empty there
+    pub fn highest_non_air(&self, column: IVec2) -> Option<i32> {
+        self.highest(column, |heights| heights.non_air)
+    }
+
+    fn highest(&self, column: IVec2, height: impl Fn(&ColumnHeights) -> Option<u8>) -> Option<i32> {
+        let chunk_column = column.div_euclid(IVec2::splat(CHUNK_SIZE_I32));
+        let [x, z] = column
+            .rem_euclid(IVec2::splat(CHUNK_SIZE_I32))
+            .to_array()
+            .map(|i| i as usize);
+        self.column_stack(chunk_column)
+            .find_map(|(chunk_y, heightmap)| {
+                height(heightmap.at_pos([x, z])).map(|y| chunk_y * CHUNK_SIZE_I32 + y as i32)
+            })
+    }
+}
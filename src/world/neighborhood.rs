@@ -8,7 +8,7 @@ use crate::{
     utils::VolumetricRange,
 };
 use bevy::{platform::collections::HashMap, prelude::*};
-use std::{marker::PhantomData, sync::Arc};
+use std::{collections::BTreeSet, marker::PhantomData, sync::Arc};
 
 pub struct NeighborhoodPlugin<T>(PhantomData<T>);
 
@@ -151,6 +151,7 @@ impl Neighborhood<Blocks> {
             .expect("Expect block in this neighborhood");
         match self.at_layer(side, layer + 1, row, col) {
             None | Some(&Block::Air) | Some(&Block::Leaves) => false,
+            Some(block) if block.is_plant() => false,
             Some(block) => !(block.is_translucent() && current_block != block),
         }
     }
@@ -158,6 +159,7 @@ impl Neighborhood<Blocks> {
     pub fn count_block(&self, side: &BlockSide, layer: i32, row: i32, col: i32) -> u8 {
         match self.at_layer(side, layer, row, col) {
             None | Some(&Block::Air) | Some(&Block::Water) => 0,
+            Some(block) if block.is_plant() => 0,
             _ => 1,
         }
     }
@@ -242,16 +244,55 @@ fn update_neighborhood<T: Component + Send + Sync + 'static>(
 #[derive(Resource)]
 pub struct ComponentIndex<T> {
     component_by_position: HashMap<[i32; 3], Arc<T>>,
+    /// The y coordinates of the chunks in each column of chunks
+    chunk_ys_by_column: HashMap<[i32; 2], BTreeSet<i32>>,
 }
 
 impl<T> Default for ComponentIndex<T> {
     fn default() -> Self {
         Self {
             component_by_position: default(),
+            chunk_ys_by_column: default(),
         }
     }
 }
 
+impl<T> ComponentIndex<T> {
+    fn insert(&mut self, [x, y, z]: [i32; 3], component: Arc<T>) {
+        self.component_by_position
+            .insert([x, y, z], component);
+        self.chunk_ys_by_column
+            .entry([x, z])
+            .or_default()
+            .insert(y);
+    }
+
+    fn remove(&mut self, [x, y, z]: [i32; 3]) {
+        self.component_by_position.remove(&[x, y, z]);
+        if let Some(ys) = self.chunk_ys_by_column.get_mut(&[x, z]) {
+            ys.remove(&y);
+            if ys.is_empty() {
+                self.chunk_ys_by_column.remove(&[x, z]);
+            }
+        }
+    }
+
+    /// The components of the chunks in a column of chunks, with their y coordinates, from the
+    /// top down
+    pub fn column_stack(&self, chunk_column: IVec2) -> impl Iterator<Item = (i32, &T)> {
+        let [x, z] = chunk_column.to_array();
+        self.chunk_ys_by_column
+            .get(&[x, z])
+            .into_iter()
+            .flat_map(|ys| ys.iter().rev())
+            .filter_map(move |y| {
+                self.component_by_position
+                    .get(&[x, *y, z])
+                    .map(|component| (*y, component.as_ref()))
+            })
+    }
+}
+
 impl<T: SpatiallyMapped<3>> ComponentIndex<T> {
     pub fn at_pos(&self, pos: impl Into<[i32; 3]>) -> Option<&T::Item> {
         let [x, y, z] = pos.into();
@@ -277,10 +318,7 @@ fn update_index<T: Component + Send + Sync + 'static>(
     mut index: ResMut<ComponentIndex<T>>,
 ) {
     for (pos, copy) in q_component.iter() {
-        let key = pos.0.into();
-        index
-            .component_by_position
-            .insert(key, copy.0.clone());
+        index.insert(pos.0.into(), copy.0.clone());
     }
 }
 
@@ -293,9 +331,7 @@ fn add_to_index<T: Component + Send + Sync + 'static>(
     let Ok((pos, component)) = q.get(entity) else {
         return;
     };
-    index
-        .component_by_position
-        .insert(pos.0.into(), component.0.clone());
+    index.insert(pos.0.into(), component.0.clone());
 }
 
 fn remove_from_index<T: Component + Send + Sync + 'static>(
@@ -307,9 +343,7 @@ fn remove_from_index<T: Component + Send + Sync + 'static>(
     let Ok(pos) = q.get(entity) else {
         return;
     };
-    index
-        .component_by_position
-        .remove(&pos.0.to_array());
+    index.remove(pos.0.to_array());
 }
 
 #[derive(Component)]
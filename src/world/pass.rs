@@ -0,0 +1,202 @@
+//! World generation is split into passes, which each take chunks from one `Stage` to the next. A
+//! pass reads resources and components of a chunk on the main thread, then does its work in a
+//! task on the `AsyncComputeTaskPool`.
+
+use super::{
+    neighborhood::Neighborhood, receive_chunk_load_tasks, stage::Stage, AddedChunkData,
+    ChunkLoadTaskData, ChunkLoadTasks, Unsaved, WorldSet,
+};
+use crate::{block::Block, chunk::position::ChunkPosition, chunk::Chunk, state::AppState};
+use bevy::{
+    ecs::{
+        query::{QueryItem, ReadOnlyQueryData},
+        system::{StaticSystemParam, SystemParam, SystemParamItem},
+    },
+    prelude::*,
+    tasks::AsyncComputeTaskPool,
+};
+use std::{any::type_name, sync::Arc};
+
+pub trait WorldgenPass: Send + Sync + 'static {
+    /// Resources which the pass reads when it starts on a chunk
+    type Param: SystemParam + 'static;
+    /// Components of the chunk which the pass reads when it starts on the chunk
+    type ChunkData: ReadOnlyQueryData + 'static;
+    /// Everything which the task needs, taken from `Param` and `ChunkData`
+    type Input: Send + 'static;
+
+    /// The stage which a chunk must have completed before the pass runs on it. `None` is a chunk
+    /// which has never been saved and hasn't been generated yet.
+    const INPUT_STAGE: Option<Stage>;
+    /// The stage which a chunk has completed once the pass has run on it
+    const OUTPUT_STAGE: Stage;
+    /// Every chunk this many chunks away from the chunk, or closer, must have completed
+    /// `INPUT_STAGE` too. Neighborhoods only go one chunk out, so this is 0 or 1.
+    const NEIGHBORHOOD_RADIUS: i32;
+    /// Chunks which must have completed `INPUT_STAGE` too besides those within
+    /// `NEIGHBORHOOD_RADIUS`, as offsets from the chunk of at most one chunk along each axis
+    const REQUIRED_NEIGHBORS: &'static [IVec3] = &[];
+
+    /// Whether the chunk's components are ready for the pass, once the stages are
+    fn is_ready(_chunk: &QueryItem<Self::ChunkData>) -> bool {
+        true
+    }
+
+    fn prepare(
+        param: &SystemParamItem<Self::Param>,
+        chunk: QueryItem<Self::ChunkData>,
+    ) -> Self::Input;
+
+    /// Runs in a task, so this should do most of the work
+    fn run(input: Self::Input, chunk_pos: IVec3) -> PassOutput;
+}
+
+/// What a pass adds to a chunk
+pub enum PassOutput {
+    /// Inserts components into the chunk
+    Insert(Box<dyn FnOnce(&mut EntityCommands) + Send>),
+    /// Replaces blocks of the chunk, in the chunk's coordinates
+    BlockUpdates(Vec<(Block, [usize; 3])>),
+}
+
+impl PassOutput {
+    pub fn insert(bundle: impl Bundle) -> Self {
+        Self::Insert(Box::new(move |entity| {
+            entity.try_insert(bundle);
+        }))
+    }
+}
+
+struct PassInfo {
+    name: &'static str,
+    input: Option<Stage>,
+    output: Stage,
+}
+
+/// Every pass which has been added, in the order in which they were added
+#[derive(Resource, Default)]
+pub struct WorldgenPasses(Vec<PassInfo>);
+
+pub trait AddWorldgenPass {
+    fn add_worldgen_pass<P: WorldgenPass>(&mut self) -> &mut Self;
+}
+
+impl AddWorldgenPass for App {
+    fn add_worldgen_pass<P: WorldgenPass>(&mut self) -> &mut Self {
+        if !self
+            .world()
+            .contains_resource::<WorldgenPasses>()
+        {
+            self.init_resource::<WorldgenPasses>()
+                .add_systems(Startup, check_pass_order);
+        }
+        self.world_mut()
+            .resource_mut::<WorldgenPasses>()
+            .0
+            .push(PassInfo {
+                name: short_type_name::<P>(),
+                input: P::INPUT_STAGE,
+                output: P::OUTPUT_STAGE,
+            });
+        self.add_systems(
+            Update,
+            begin_pass_tasks::<P>
+                // Otherwise a chunk whose task has just finished would look like it's still waiting
+                // for the pass, since its new stage is only inserted once commands are applied
+                .after(receive_chunk_load_tasks)
+                .in_set(WorldSet)
+                .run_if(in_state(AppState::InGame)),
+        )
+    }
+}
+
+fn short_type_name<T>() -> &'static str {
+    let name = type_name::<T>();
+    name.rsplit("::")
+        .next()
+        .unwrap_or(name)
+}
+
+/// Passes are ordered by their stages, so every stage up to the final one must be the input of
+/// exactly one pass
+fn check_pass_order(passes: Res<WorldgenPasses>) {
+    let mut order = vec![];
+    let mut stage = None;
+    loop {
+        let mut next = passes
+            .0
+            .iter()
+            .filter(|pass| pass.input == stage);
+        let Some(pass) = next.next() else {
+            break;
+        };
+        if let Some(other) = next.next() {
+            warn!(
+                "Worldgen passes {} and {} both start from {:?}, so only {} will run",
+                pass.name, other.name, stage, pass.name
+            );
+        }
+        if stage.is_some_and(|stage| pass.output <= stage) {
+            warn!("Worldgen pass {} doesn't move chunks forwards", pass.name);
+            break;
+        }
+        order.push(pass.name);
+        stage = Some(pass.output);
+    }
+    if stage != Some(Stage::final_stage()) {
+        warn!(
+            "Worldgen passes stop at {:?}, so chunks will never reach {:?}",
+            stage,
+            Stage::final_stage()
+        );
+    }
+    info!("Worldgen passes: {}", order.join(" -> "));
+}
+
+type PassQueryData<ChunkData> = (
+    Entity,
+    &'static ChunkPosition,
+    Option<&'static Stage>,
+    Has<Unsaved>,
+    Option<&'static Neighborhood<Stage>>,
+    ChunkData,
+);
+
+fn begin_pass_tasks<P: WorldgenPass>(
+    mut tasks: ResMut<ChunkLoadTasks>,
+    q_chunk: Query<PassQueryData<P::ChunkData>, With<Chunk>>,
+    param: StaticSystemParam<P::Param>,
+) {
+    for (entity, pos, stage, unsaved, stage_neighborhood, chunk_data) in q_chunk.iter() {
+        if tasks.0.contains_key(pos) || stage != P::INPUT_STAGE.as_ref() {
+            continue;
+        }
+        let is_ready = match P::INPUT_STAGE {
+            None => unsaved,
+            Some(_) if P::NEIGHBORHOOD_RADIUS == 0 && P::REQUIRED_NEIGHBORS.is_empty() => true,
+            Some(input_stage) => stage_neighborhood.is_some_and(|neighborhood| {
+                let has_completed = |stage: &Option<Arc<Stage>>| {
+                    stage
+                        .as_ref()
+                        .is_some_and(|stage| **stage >= input_stage)
+                };
+                (P::NEIGHBORHOOD_RADIUS == 0 || neighborhood.0.iter().all(has_completed))
+                    && P::REQUIRED_NEIGHBORS
+                        .iter()
+                        .all(|offset| {
+                            has_completed(neighborhood.get_chunk(offset.x, offset.y, offset.z))
+                        })
+            }),
+        };
+        if !is_ready || !P::is_ready(&chunk_data) {
+            continue;
+        }
+        let input = P::prepare(&param, chunk_data);
+        let chunk_pos = pos.0;
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let added_data = AddedChunkData::Pass(P::run(input, chunk_pos), P::OUTPUT_STAGE);
+            ChunkLoadTaskData { entity, added_data }
+        });
+        tasks.0.insert(*pos, task);
+    }
+}
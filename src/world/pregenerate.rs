@@ -0,0 +1,175 @@
+use crate::{
+    chunk::{position::ChunkPosition, Chunk},
+    state::AppState,
+    world::{
+        save::{Dirty, WorldDirectory},
+        seed::LoadSeed,
+        stage::Stage,
+        WorldGenerationPlugin, WorldSet,
+    },
+};
+use bevy::{
+    ecs::query::QueryData, log::LogPlugin, prelude::*, state::app::StatesPlugin,
+};
+use std::{path::PathBuf, time::Instant};
+
+/// Generates the chunks between two corners (inclusive) of the world in the directory without
+/// opening a window. The world's metadata must already have been written.
+pub fn run(directory: PathBuf, min: IVec3, max: IVec3) -> AppExit {
+    App::new()
+        .add_plugins((
+            MinimalPlugins,
+            LogPlugin::default(),
+            StatesPlugin,
+            WorldGenerationPlugin,
+            PregeneratePlugin { min, max },
+        ))
+        .insert_state(AppState::InGame)
+        .insert_resource(WorldDirectory(directory))
+        .run()
+}
+
+/// Generates every chunk in a box and saves it, then exits. Chunks which have already been saved
+/// are left as they are.
+///
+/// The box is swept one slice of constant x at a time. Only the slices on either side of the one
+/// being finished are kept loaded, since structures need the terrain of every neighbouring chunk.
+pub struct PregeneratePlugin {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl Plugin for PregeneratePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Pregeneration {
+            min: self.min.min(self.max),
+            max: self.min.max(self.max),
+            next_x: self.min.x.min(self.max.x),
+            started: Instant::now(),
+            generated: 0,
+        })
+        .add_systems(OnEnter(AppState::InGame), start.after(LoadSeed))
+        .add_systems(
+            Update,
+            advance
+                .after(WorldSet)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct Pregeneration {
+    min: IVec3,
+    max: IVec3,
+    /// The first slice which hasn't been finished
+    next_x: i32,
+    started: Instant,
+    /// Number of chunks which were generated rather than loaded
+    generated: usize,
+}
+
+impl Pregeneration {
+    fn slice_volume(&self) -> usize {
+        let size = self.max - self.min + IVec3::ONE;
+        (size.y * size.z) as usize
+    }
+
+    fn volume(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize * self.slice_volume()
+    }
+
+    /// Chunks in the slice, including a margin for the neighbours of chunks at the edge of the box.
+    /// Plants are only placed on a chunk once the chunk below has its structures, which in turn
+    /// need the chunks around it, so the margin is two chunks deep below the box.
+    fn slice_with_margin(&self, x: i32) -> impl Iterator<Item = IVec3> {
+        let (min, max) = (self.min, self.max);
+        (min.y - 2..=max.y + 1).flat_map(move |y| {
+            (min.z - 1..=max.z + 1).map(move |z| IVec3::new(x, y, z))
+        })
+    }
+
+    fn contains(&self, pos: IVec3) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all()
+    }
+}
+
+fn start(mut commands: Commands, mut pregeneration: ResMut<Pregeneration>) {
+    pregeneration.started = Instant::now();
+    info!(
+        "Generating {} chunks from {} to {}",
+        pregeneration.volume(),
+        pregeneration.min,
+        pregeneration.max
+    );
+    for x in pregeneration.min.x - 1..=pregeneration.min.x + 1 {
+        spawn_slice(&mut commands, &pregeneration, x);
+    }
+}
+
+fn spawn_slice(commands: &mut Commands, pregeneration: &Pregeneration, x: i32) {
+    for pos in pregeneration.slice_with_margin(x) {
+        commands.spawn((Chunk, ChunkPosition(pos)));
+    }
+}
+
+#[derive(QueryData)]
+struct PregeneratedChunk {
+    entity: Entity,
+    pos: &'static ChunkPosition,
+    stage: Option<&'static Stage>,
+    dirty: Has<Dirty>,
+}
+
+fn advance(
+    mut commands: Commands,
+    mut pregeneration: ResMut<Pregeneration>,
+    q_chunk: Query<PregeneratedChunk, With<Chunk>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let x = pregeneration.next_x;
+    if x > pregeneration.max.x {
+        return;
+    }
+    let mut finished = 0;
+    let mut generated = 0;
+    for chunk in q_chunk.iter() {
+        let pos = chunk.pos.0;
+        if pos.x == x && pregeneration.contains(pos) && chunk.stage == Some(&Stage::final_stage()) {
+            finished += 1;
+            generated += chunk.dirty as usize;
+        }
+    }
+    if finished < pregeneration.slice_volume() {
+        return;
+    }
+    pregeneration.generated += generated;
+    pregeneration.next_x += 1;
+    // Despawning saves the chunks of the slice which is no longer needed
+    for chunk in q_chunk.iter() {
+        if chunk.pos.0.x == x - 1 {
+            commands.entity(chunk.entity).despawn();
+        }
+    }
+    if x + 2 <= pregeneration.max.x + 1 {
+        spawn_slice(&mut commands, &pregeneration, x + 2);
+    }
+    let done = (x - pregeneration.min.x + 1) as usize * pregeneration.slice_volume();
+    let seconds = pregeneration.started.elapsed().as_secs_f32();
+    info!(
+        "{done}/{} chunks done, {:.1} chunks/s",
+        pregeneration.volume(),
+        done as f32 / seconds
+    );
+    if x == pregeneration.max.x {
+        info!(
+            "Generated {} chunks and kept {} saved chunks in {:.1}s ({:.1} chunks/s)",
+            pregeneration.generated,
+            pregeneration.volume() - pregeneration.generated,
+            seconds,
+            pregeneration.volume() as f32 / seconds
+        );
+        // The remaining chunks are saved when the app exits
+        app_exit.write(AppExit::Success);
+    }
+}
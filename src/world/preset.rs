@@ -0,0 +1,303 @@
+//! Presets swap the terrain which the sculpt pass generates, for example to make flat worlds for
+//! building and testing. The preset is chosen when the world is created and saved in its metadata.
+
+use bevy::prelude::*;
+use noise::NoiseFn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block::Block,
+    chunk::{
+        data::{BiomeMap, Terrain},
+        spatial::SpatiallyMapped,
+        CHUNK_LENGTH, CHUNK_SIZE, CHUNK_SIZE_I32,
+    },
+    state::AppState,
+};
+
+use super::{
+    biome::{Biome, MAX_SOIL_DEPTH},
+    save::{metadata::WorldMetadata, LoadWorldMetadata},
+    seed::LoadSeed,
+    world_noise::IslandNoiseGenerator,
+};
+
+/// Multiplies the height of the land in amplified worlds
+pub const AMPLIFIED_HEIGHT_SCALE: f32 = 3.0;
+/// Half the width of the square platform which void worlds have around the origin
+const VOID_PLATFORM_RADIUS: i32 = 4;
+/// Floating islands are between these heights
+const ISLAND_MIN_HEIGHT: i32 = -48;
+const ISLAND_MAX_HEIGHT: i32 = 48;
+/// Island noise must be above this in the middle of the island layer for a block to be solid, and
+/// higher towards the top and bottom of the layer
+const ISLAND_THRESHOLD: f64 = 0.54;
+/// Thickest layer allowed in a superflat world
+pub const MAX_FLAT_LAYER_THICKNESS: u32 = 256;
+
+pub struct PresetPlugin;
+
+impl Plugin for PresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            setup
+                .in_set(LoadSeed)
+                .after(LoadWorldMetadata),
+        );
+    }
+}
+
+fn setup(mut commands: Commands, metadata: Res<WorldMetadata>) {
+    commands.insert_resource(metadata.preset.clone());
+}
+
+/// See docs/world_metadata_format.md
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub enum WorldPreset {
+    /// The usual terrain of continents, oceans and biomes
+    #[default]
+    Default,
+    /// Flat layers of blocks everywhere, with the ground at a height of 0
+    Superflat { layers: Vec<FlatLayer> },
+    /// Nothing but a small platform to spawn on
+    Void,
+    /// The usual terrain with much taller mountains
+    Amplified,
+    /// The usual terrain pushed in and out by 3D noise, so that mountains have overhangs, arches
+    /// and cliffs
+    Overhangs,
+    /// Islands floating in the void
+    FloatingIslands,
+}
+
+/// A layer of a superflat world
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FlatLayer {
+    pub block: Block,
+    pub thickness: u32,
+}
+
+impl WorldPreset {
+    /// Every preset with its default settings, in the order in which they're offered
+    pub fn all() -> [Self; 6] {
+        [
+            Self::Default,
+            Self::Superflat {
+                layers: default_flat_layers(),
+            },
+            Self::Void,
+            Self::Amplified,
+            Self::Overhangs,
+            Self::FloatingIslands,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Superflat { .. } => "superflat",
+            Self::Void => "void",
+            Self::Amplified => "amplified",
+            Self::Overhangs => "overhangs",
+            Self::FloatingIslands => "floating_islands",
+        }
+    }
+
+    /// Parses the name of a preset. Superflat can be followed by a colon and its layers, for
+    /// example `superflat:bedrock,3*stone,grass`.
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (name, layers) = match text.split_once(':') {
+            Some((name, layers)) => (name, Some(layers)),
+            None => (text, None),
+        };
+        let preset = Self::all()
+            .into_iter()
+            .find(|preset| preset.name() == name)
+            .ok_or_else(|| format!("Unknown world preset \"{name}\""))?;
+        match (preset, layers) {
+            (Self::Superflat { .. }, Some(layers)) => Ok(Self::Superflat {
+                layers: parse_flat_layers(layers)?,
+            }),
+            (_, Some(_)) => Err(format!("The {name} preset has no settings")),
+            (preset, None) => Ok(preset),
+        }
+    }
+
+    /// Whether ores and structures are generated. Flat and void worlds are meant for building, so
+    /// they're left empty.
+    pub fn has_features(&self) -> bool {
+        !matches!(self, Self::Superflat { .. } | Self::Void)
+    }
+
+    /// Whether the terrain is the usual terrain, shaped by the continent and height noise
+    pub fn has_continents(&self) -> bool {
+        matches!(self, Self::Default | Self::Amplified | Self::Overhangs)
+    }
+
+    /// Multiplies the height of the land of presets with continents
+    pub fn height_scale(&self) -> f32 {
+        match self {
+            Self::Amplified => AMPLIFIED_HEIGHT_SCALE,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Bedrock, then stone, then dirt with grass on top
+pub fn default_flat_layers() -> Vec<FlatLayer> {
+    [
+        (Block::Bedrock, 1),
+        (Block::Stone, 3),
+        (Block::Dirt, 2),
+        (Block::Grass, 1),
+    ]
+    .into_iter()
+    .map(|(block, thickness)| FlatLayer { block, thickness })
+    .collect()
+}
+
+/// Parses layers from the bottom up, separated by commas. Each layer is a block ID, optionally
+/// preceded by a thickness and `*`.
+pub fn parse_flat_layers(text: &str) -> Result<Vec<FlatLayer>, String> {
+    text.split(',')
+        .map(|layer| {
+            let layer = layer.trim();
+            let (thickness, id) = match layer.split_once('*') {
+                Some((thickness, id)) => (
+                    thickness
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid layer thickness \"{thickness}\""))?,
+                    id.trim(),
+                ),
+                None => (1, layer),
+            };
+            if thickness > MAX_FLAT_LAYER_THICKNESS {
+                return Err(format!(
+                    "Layers can be at most {MAX_FLAT_LAYER_THICKNESS} blocks thick"
+                ));
+            }
+            let block = Block::from_id(id).ok_or_else(|| format!("Unknown block ID \"{id}\""))?;
+            Ok(FlatLayer { block, thickness })
+        })
+        .collect()
+}
+
+pub fn flat_layers_to_text(layers: &[FlatLayer]) -> String {
+    layers
+        .iter()
+        .map(|layer| match layer.thickness {
+            1 => layer.block.id().to_string(),
+            thickness => format!("{}*{}", thickness, layer.block.id()),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The layers are stacked so that the top of the highest layer is at a height of 0
+pub fn generate_superflat_terrain(layers: &[FlatLayer], chunk_pos: IVec3) -> Terrain {
+    let min_y = chunk_pos.y * CHUNK_SIZE_I32;
+    let mut terrain = Terrain(vec![Block::Air; CHUNK_LENGTH]);
+    for y in 0..CHUNK_SIZE {
+        // Depth below the top of the highest layer, starting from 0
+        let depth = -(min_y + y as i32) - 1;
+        let Some(block) = u32::try_from(depth)
+            .ok()
+            .and_then(|depth| flat_layer_at_depth(layers, depth))
+        else {
+            continue;
+        };
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                *terrain.at_pos_mut([x, y, z]) = block;
+            }
+        }
+    }
+    terrain
+}
+
+/// The block of the layer which covers a depth below the top of the highest layer
+fn flat_layer_at_depth(layers: &[FlatLayer], depth: u32) -> Option<Block> {
+    let mut top = 0u64;
+    for layer in layers.iter().rev() {
+        let bottom = top + u64::from(layer.thickness);
+        if u64::from(depth) < bottom {
+            return Some(layer.block);
+        }
+        top = bottom;
+    }
+    None
+}
+
+/// Everything is air except for a platform of stone around the origin, whose top is at a height
+/// of 0
+pub fn generate_void_terrain(chunk_pos: IVec3) -> Terrain {
+    Terrain::from_fn(|pos| {
+        let world_pos = chunk_pos * CHUNK_SIZE_I32 + IVec3::from(pos.map(|x| x as i32));
+        let on_platform = world_pos.x.abs() <= VOID_PLATFORM_RADIUS
+            && world_pos.z.abs() <= VOID_PLATFORM_RADIUS
+            && world_pos.y == -1;
+        if on_platform {
+            Block::Stone
+        } else {
+            Block::Air
+        }
+    })
+}
+
+/// Islands are wherever the island noise is high enough, which gets harder towards the top and
+/// bottom of the island layer. They're covered with the soil of their biome.
+pub fn generate_floating_island_terrain(
+    noise: &IslandNoiseGenerator,
+    biome_map: &BiomeMap,
+    chunk_pos: IVec3,
+) -> Terrain {
+    let min_y = chunk_pos.y * CHUNK_SIZE_I32;
+    if min_y > ISLAND_MAX_HEIGHT || min_y + CHUNK_SIZE_I32 <= ISLAND_MIN_HEIGHT {
+        return Terrain(vec![Block::Air; CHUNK_LENGTH]);
+    }
+    // Blocks above the chunk decide how deep the soil of its highest blocks is
+    let column_height = CHUNK_SIZE + MAX_SOIL_DEPTH as usize;
+    let is_solid = <Vec<Vec<bool>> as SpatiallyMapped<2>>::from_fn(|[x, z]| {
+        (0..column_height)
+            .map(|y| {
+                let world_pos = chunk_pos * CHUNK_SIZE_I32
+                    + IVec3::new(x as i32, y as i32, z as i32);
+                is_island(noise, world_pos)
+            })
+            .collect()
+    });
+    Terrain::from_fn(|[x, y, z]| {
+        let column = is_solid.at_pos([x, z]);
+        if !column[y] {
+            return Block::Air;
+        }
+        // Oceans are only where the continent noise is low, which means nothing up here
+        let biome = match biome_map.at_pos([x, z]) {
+            Biome::Ocean => Biome::Plains,
+            biome => *biome,
+        };
+        let depth = column[y + 1..]
+            .iter()
+            .position(|solid| !solid)
+            .map(|depth| depth as i32 + 1);
+        match depth {
+            Some(1) => biome.surface_block(),
+            Some(depth) if depth <= biome.soil_depth() => biome.subsurface_block(),
+            _ => Block::Stone,
+        }
+    })
+}
+
+fn is_island(noise: &IslandNoiseGenerator, world_pos: IVec3) -> bool {
+    if world_pos.y < ISLAND_MIN_HEIGHT || world_pos.y > ISLAND_MAX_HEIGHT {
+        return false;
+    }
+    let middle = (ISLAND_MIN_HEIGHT + ISLAND_MAX_HEIGHT) as f64 / 2.0;
+    let half_height = (ISLAND_MAX_HEIGHT - ISLAND_MIN_HEIGHT) as f64 / 2.0;
+    let t = (world_pos.y as f64 - middle) / half_height;
+    let threshold = ISLAND_THRESHOLD + (1.0 - ISLAND_THRESHOLD) * t * t;
+    noise.0.get(world_pos.as_dvec3().to_array()) > threshold
+}
@@ -0,0 +1,202 @@
+//! Renders maps of the terrain noise to PNG images without generating any chunks or needing a GPU,
+//! so that changes to the terrain parameters can be seen and compared quickly. Each pixel is a
+//! column of the world, with x to the right and z downwards, and zoomed out previews take every
+//! nth column.
+
+use bevy::prelude::*;
+use noise::NoiseFn;
+use rayon::prelude::*;
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use super::{
+    biome::Biome, config::WorldgenConfig, get_cave_threshold, preset::WorldPreset,
+    world_noise::CaveNetworkNoiseGenerator, SurfaceSampler,
+};
+
+/// Land this far above sea level is drawn white in height maps
+const PREVIEW_MAX_HEIGHT: f32 = 128.0;
+
+type Rgb = [u8; 3];
+
+const CAVE_COLOUR: Rgb = [200, 40, 40];
+const STONE_COLOUR: Rgb = [128, 128, 128];
+const AIR_COLOUR: Rgb = [170, 210, 240];
+const WATER_COLOUR: Rgb = [60, 120, 230];
+const VOID_COLOUR: Rgb = [0, 0, 0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviewLayer {
+    /// The continent noise, from black at its lowest to white at its highest. Land is where it's
+    /// above grey.
+    Continent,
+    /// The height of the land with rivers and lakes carved into it, and the depth of the ocean.
+    /// Presets whose terrain isn't shaped by the noise are black.
+    Height,
+    /// The biome of each column
+    Biome,
+    /// A horizontal slice of the world at a height, showing caves in red. Overhangs and structures
+    /// are left out.
+    Caves { y: i32 },
+}
+
+impl PreviewLayer {
+    /// Used for the image's file name
+    pub fn name(&self) -> String {
+        match self {
+            Self::Continent => "continent".into(),
+            Self::Height => "height".into(),
+            Self::Biome => "biome".into(),
+            Self::Caves { y } => format!("caves_y{y}"),
+        }
+    }
+}
+
+/// A rectangle of columns, with both corners included
+#[derive(Clone, Copy, Debug)]
+pub struct PreviewArea {
+    pub min: IVec2,
+    pub max: IVec2,
+    /// Every nth column is drawn
+    pub blocks_per_pixel: i32,
+}
+
+impl PreviewArea {
+    fn size(&self) -> UVec2 {
+        ((self.max - self.min) / self.blocks_per_pixel + IVec2::ONE).as_uvec2()
+    }
+}
+
+pub struct PreviewImage {
+    size: UVec2,
+    pixels: Vec<Rgb>,
+}
+
+impl PreviewImage {
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.size.x, self.size.y);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(io::Error::other)?;
+        writer
+            .write_image_data(self.pixels.as_flattened())
+            .map_err(io::Error::other)
+    }
+}
+
+/// Samples the same noise as a world generated from the seed, preset and config
+pub struct NoisePreview {
+    sampler: SurfaceSampler,
+    caves: CaveNetworkNoiseGenerator,
+}
+
+impl NoisePreview {
+    pub fn new(seed: u32, preset: WorldPreset, config: WorldgenConfig) -> Self {
+        Self {
+            sampler: SurfaceSampler::new(seed, preset, config),
+            caves: CaveNetworkNoiseGenerator::new(seed),
+        }
+    }
+
+    pub fn render(&self, layer: PreviewLayer, area: &PreviewArea) -> PreviewImage {
+        let size = area.size();
+        let pixels = (0..size.y as i32)
+            .into_par_iter()
+            .flat_map_iter(|row| {
+                (0..size.x as i32).map(move |col| {
+                    let column = area.min + IVec2::new(col, row) * area.blocks_per_pixel;
+                    self.pixel(layer, column)
+                })
+            })
+            .collect();
+        PreviewImage { size, pixels }
+    }
+
+    fn pixel(&self, layer: PreviewLayer, column: IVec2) -> Rgb {
+        match layer {
+            PreviewLayer::Continent => {
+                let shade = (self.sampler.continent_noise(column) + 1.0) * 0.5;
+                grey(shade)
+            }
+            PreviewLayer::Height => self.height_pixel(column),
+            PreviewLayer::Biome => biome_colour(self.sampler.biome(column)),
+            PreviewLayer::Caves { y } => self.cave_pixel(column, y),
+        }
+    }
+
+    fn height_pixel(&self, column: IVec2) -> Rgb {
+        let config = self.sampler.config();
+        let continent_noise = self.sampler.continent_noise(column);
+        if continent_noise <= 0.0 {
+            // Darker further down, down to half brightness at the deepest
+            return shade(WATER_COLOUR, 1.0 + continent_noise * 0.5);
+        }
+        let Some(water_column) = self.sampler.water_column(column) else {
+            return VOID_COLOUR;
+        };
+        if water_column.water.is_some() {
+            return shade(WATER_COLOUR, 1.2);
+        }
+        let height = (water_column.land_height - config.sea_level as f32) / PREVIEW_MAX_HEIGHT;
+        let low = Vec3::new(0.2, 0.5, 0.1);
+        colour(low.lerp(Vec3::ONE, height.clamp(0.0, 1.0)))
+    }
+
+    fn cave_pixel(&self, column: IVec2, y: i32) -> Rgb {
+        let config = self.sampler.config();
+        if y < config.max_depth() {
+            return VOID_COLOUR;
+        }
+        let continent_noise = self.sampler.continent_noise(column);
+        let ground = if continent_noise <= 0.0 {
+            Some(continent_noise * config.continent_scale)
+        } else if self.sampler.preset.has_continents() {
+            self.sampler
+                .water_column(column)
+                .map(|water_column| water_column.land_height)
+        } else {
+            None
+        };
+        if ground.is_some_and(|ground| y as f32 >= ground) {
+            return if y <= config.sea_level {
+                WATER_COLOUR
+            } else {
+                AIR_COLOUR
+            };
+        }
+        let cave_noise = self.caves.get([column.x, y, column.y]);
+        if cave_noise < get_cave_threshold(y, config) {
+            CAVE_COLOUR
+        } else {
+            STONE_COLOUR
+        }
+    }
+}
+
+fn biome_colour(biome: Biome) -> Rgb {
+    match biome {
+        Biome::Ocean => WATER_COLOUR,
+        Biome::Tundra => [220, 225, 230],
+        Biome::Taiga => [40, 90, 70],
+        Biome::Plains => [130, 190, 80],
+        Biome::Forest => [40, 130, 40],
+        Biome::Desert => [230, 210, 140],
+        Biome::Savanna => [180, 170, 70],
+    }
+}
+
+fn grey(brightness: f32) -> Rgb {
+    colour(Vec3::splat(brightness))
+}
+
+fn shade(rgb: Rgb, brightness: f32) -> Rgb {
+    colour(Vec3::from(rgb.map(|c| c as f32 / 255.0)) * brightness)
+}
+
+fn colour(rgb: Vec3) -> Rgb {
+    rgb.clamp(Vec3::ZERO, Vec3::ONE)
+        .to_array()
+        .map(|c| (c * 255.0).round() as u8)
+}
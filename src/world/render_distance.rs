@@ -0,0 +1,94 @@
+//! How many chunks around the player are loaded. The render distance is a setting of the game
+//! rather than of a world, so it's kept in `settings.ron` in the working directory and saved
+//! whenever it's changed.
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, ops::RangeInclusive};
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+pub const HORIZONTAL_RANGE: RangeInclusive<i32> = 2..=16;
+/// The spawn is only settled once the chunks on every side of it have finished generating, which
+/// needs at least two chunks loaded above and below the player
+pub const VERTICAL_RANGE: RangeInclusive<i32> = 2..=8;
+
+pub struct RenderDistancePlugin;
+
+impl Plugin for RenderDistancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load)
+            .add_systems(Update, save.run_if(resource_changed::<RenderDistance>));
+    }
+}
+
+/// Chunks up to this many chunks away from the player's chunk are loaded, so the loaded area is
+/// `2 * horizontal + 1` chunks across and `2 * vertical + 2` chunks high, with the extra layer
+/// below the player
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct RenderDistance {
+    pub horizontal: i32,
+    pub vertical: i32,
+}
+
+impl Default for RenderDistance {
+    fn default() -> Self {
+        Self {
+            horizontal: 3,
+            vertical: 2,
+        }
+    }
+}
+
+impl RenderDistance {
+    /// Keeps both distances within their ranges
+    pub fn clamped(self) -> Self {
+        Self {
+            horizontal: self
+                .horizontal
+                .clamp(*HORIZONTAL_RANGE.start(), *HORIZONTAL_RANGE.end()),
+            vertical: self
+                .vertical
+                .clamp(*VERTICAL_RANGE.start(), *VERTICAL_RANGE.end()),
+        }
+    }
+}
+
+/// The contents of the settings file
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Settings {
+    render_distance: RenderDistance,
+}
+
+fn load(mut commands: Commands) {
+    let settings = match fs::read(SETTINGS_FILE) {
+        Ok(contents) => ron::de::from_bytes(&contents).unwrap_or_else(|e| {
+            warn!("Failed to read {}, using the defaults: {}", SETTINGS_FILE, e);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    };
+    commands.insert_resource(settings.render_distance.clamped());
+}
+
+fn save(render_distance: Res<RenderDistance>) {
+    // Inserting the resource counts as a change, but there's nothing new to save yet
+    if render_distance.is_added() {
+        return;
+    }
+    let settings = Settings {
+        render_distance: *render_distance,
+    };
+    if let Err(e) = write(&settings) {
+        warn!("Failed to save {}: {}", SETTINGS_FILE, e);
+    }
+}
+
+fn write(settings: &Settings) -> io::Result<()> {
+    let contents = ron::ser::to_string_pretty(settings, PrettyConfig::default())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    fs::write(SETTINGS_FILE, contents)
+}
@@ -0,0 +1,417 @@
+use crate::{
+    chunk::{data::Blocks, position::ChunkPosition, Chunk},
+    physics::velocity::Velocity,
+    player::{
+        health::Health,
+        inventory::{HotbarSelection, Inventory},
+        mode::PlayerMode,
+        Player,
+    },
+    portal::{self, PortalEntrance},
+    state::{AppState, InGameState},
+    world::{
+        preset::WorldPreset,
+        seed::{WorldSeed, DEFAULT_SEED},
+        stage::Stage,
+    },
+};
+use bevy::{
+    ecs::query::QueryData,
+    platform::collections::HashMap,
+    prelude::*,
+    tasks::{block_on, IoTaskPool, Task},
+    time::common_conditions::on_timer,
+};
+use metadata::{PlayerMetadata, WorldMetadata};
+use migration::WORLD_METADATA_VERSION;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+pub mod chunk_file;
+pub mod metadata;
+pub mod migration;
+pub mod region_file;
+pub mod worlds;
+
+/// The only dimension which currently exists
+const DIMENSION_ID: &str = "overworld";
+const WORLDS_DIRECTORY: &str = "worlds";
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkFormat>()
+            .init_resource::<PendingWrites>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (
+                    load_world_metadata.in_set(LoadWorldMetadata),
+                    load_chunk_format.after(LoadWorldMetadata),
+                ),
+            )
+            .add_systems(OnExit(AppState::InGame), save_world_metadata)
+            .add_systems(
+                OnEnter(InGameState::Saving),
+                (save_dirty_chunks, save_world_metadata),
+            )
+            .add_systems(
+                Update,
+                (
+                    poll_pending_writes,
+                    (save_dirty_chunks, save_world_metadata)
+                        .run_if(in_state(AppState::InGame).and(on_timer(AUTOSAVE_INTERVAL))),
+                ),
+            )
+            .add_systems(
+                Last,
+                (
+                    (save_dirty_chunks, save_world_metadata).run_if(in_state(AppState::InGame)),
+                    wait_for_pending_writes,
+                )
+                    .chain()
+                    .run_if(on_event::<AppExit>),
+            )
+            .add_observer(save_unloaded_chunk);
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LoadWorldMetadata;
+
+/// The directory containing the chunk files of the current world, inserted when a world is picked
+/// on the singleplayer screen
+#[derive(Resource, Clone)]
+pub struct WorldDirectory(pub PathBuf);
+
+impl WorldDirectory {
+    pub fn named(name: &str) -> Self {
+        Self(PathBuf::from(WORLDS_DIRECTORY).join(name))
+    }
+}
+
+/// How chunks are stored on disk. Each world keeps the format it was created with in its
+/// metadata.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ChunkFormat {
+    /// One JSON file per chunk, see docs/chunk_file_format.md
+    Json,
+    /// Binary files containing 16x16x16 chunks each, see docs/region_file_format.md
+    #[default]
+    Region,
+}
+
+impl ChunkFormat {
+    pub fn write(
+        &self,
+        directory: &Path,
+        chunk_pos: IVec3,
+        blocks: &Blocks,
+        stage: &Stage,
+    ) -> io::Result<()> {
+        match self {
+            Self::Json => chunk_file::write(directory, chunk_pos, blocks, stage),
+            Self::Region => region_file::write(directory, chunk_pos, blocks, stage),
+        }
+    }
+
+    pub const ALL: [Self; 2] = [Self::Region, Self::Json];
+
+    /// For worlds whose metadata doesn't say which format they use: worlds which were saved as
+    /// chunk files keep using them, anything else uses the default
+    pub fn detect(directory: &Path) -> Self {
+        if chunk_file::directory_has_chunk_files(directory) {
+            Self::Json
+        } else {
+            Self::default()
+        }
+    }
+
+    /// The format saved in the world's metadata, or the detected one if it has no metadata
+    pub fn of_world(directory: &Path) -> io::Result<Self> {
+        Ok(match metadata::read(directory)? {
+            Some(metadata) => metadata.chunk_format,
+            None => Self::detect(directory),
+        })
+    }
+
+    /// Whether the directory has chunks saved in the other format as well, for example after a
+    /// conversion was interrupted. Those chunks are ignored.
+    pub fn has_other_chunks(&self, directory: &Path) -> bool {
+        match self {
+            Self::Json => region_file::list_regions(directory)
+                .is_ok_and(|regions| !regions.is_empty()),
+            Self::Region => chunk_file::directory_has_chunk_files(directory),
+        }
+    }
+
+    /// Positions of every chunk saved in the directory, in no particular order
+    pub fn list_chunks(&self, directory: &Path) -> io::Result<Vec<IVec3>> {
+        match self {
+            Self::Json => chunk_file::list_chunks(directory),
+            Self::Region => region_file::list_chunks(directory),
+        }
+    }
+
+    /// Returns `None` if the chunk has never been saved
+    pub fn read(&self, directory: &Path, chunk_pos: IVec3) -> io::Result<Option<SavedChunk>> {
+        match self {
+            Self::Json => chunk_file::read(directory, chunk_pos),
+            Self::Region => region_file::read(directory, chunk_pos),
+        }
+    }
+}
+
+/// A chunk as it was read from disk
+pub struct SavedChunk {
+    pub blocks: Blocks,
+    pub stage: Stage,
+    /// The chunk was stored in an older format version, so it should be saved again to upgrade it
+    pub outdated: bool,
+}
+
+/// Positions encoded in the names of files like `overworld_1_-2_3.ext`, in no particular order
+fn list_positions(directory: &Path, extension: &str) -> io::Result<Vec<IVec3>> {
+    let mut positions = vec![];
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_none_or(|ext| ext != extension)
+        {
+            continue;
+        }
+        if let Some(pos) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(parse_position)
+        {
+            positions.push(pos);
+        }
+    }
+    Ok(positions)
+}
+
+fn parse_position(file_stem: &str) -> Option<IVec3> {
+    let mut coords = file_stem
+        .strip_prefix(DIMENSION_ID)?
+        .strip_prefix('_')?
+        .split('_')
+        .map(|n| n.parse().ok());
+    let pos = IVec3::new(coords.next()??, coords.next()??, coords.next()??);
+    coords
+        .next()
+        .is_none()
+        .then_some(pos)
+}
+
+fn load_chunk_format(
+    directory: Res<WorldDirectory>,
+    metadata: Res<WorldMetadata>,
+    mut format: ResMut<ChunkFormat>,
+) {
+    *format = metadata.chunk_format;
+    if format.has_other_chunks(&directory.0) {
+        warn!(
+            "{} has chunks saved in more than one format, only the {:?} chunks are used",
+            directory.0.display(),
+            *format
+        );
+    }
+}
+
+fn load_world_metadata(mut commands: Commands, directory: Res<WorldDirectory>) {
+    let new_metadata = || {
+        WorldMetadata::new(
+            DEFAULT_SEED,
+            WorldPreset::Default,
+            ChunkFormat::detect(&directory.0),
+        )
+    };
+    let metadata = match metadata::read(&directory.0) {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => new_metadata(),
+        Err(e) => {
+            warn!("Failed to load world metadata, starting over: {}", e);
+            new_metadata()
+        }
+    };
+    commands.insert_resource(metadata);
+}
+
+#[derive(QueryData)]
+struct SavedPlayerQuery {
+    transform: &'static Transform,
+    velocity: &'static Velocity,
+    health: &'static Health,
+    inventory: &'static Inventory,
+    hotbar_selection: &'static HotbarSelection,
+    mode: &'static PlayerMode,
+}
+
+fn save_world_metadata(
+    directory: Res<WorldDirectory>,
+    seed: Res<WorldSeed>,
+    q_player: Query<SavedPlayerQuery, With<Player>>,
+    q_portal: Query<(Entity, &Transform, &PortalEntrance)>,
+    mut metadata: ResMut<WorldMetadata>,
+) {
+    *metadata = WorldMetadata {
+        version: WORLD_METADATA_VERSION,
+        seed: seed.0,
+        preset: metadata.preset.clone(),
+        chunk_format: metadata.chunk_format,
+        spawn: metadata.spawn,
+        player: q_player
+            .single()
+            .ok()
+            .map(|player| PlayerMetadata {
+                transform: *player.transform,
+                velocity: player.velocity.0,
+                health: player.health.0,
+                inventory: *player.inventory,
+                hotbar_selection: player.hotbar_selection.index,
+                mode: *player.mode,
+            })
+            .or_else(|| metadata.player.clone()),
+        // Portals only exist while in game, so keep the saved ones when saving from elsewhere
+        portals: if q_portal.is_empty() {
+            metadata.portals.clone()
+        } else {
+            portal::placements(q_portal.iter())
+        },
+    };
+    if let Err(e) = metadata::write(&directory.0, &metadata) {
+        warn!("Failed to save world metadata: {}", e);
+    }
+}
+
+/// Marks a chunk whose blocks have changed since it was last saved
+#[derive(Component)]
+pub struct Dirty;
+
+/// Chunk writes which are still running on the IO task pool
+#[derive(Resource, Default)]
+pub struct PendingWrites(HashMap<IVec3, Task<()>>);
+
+impl PendingWrites {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Chunks shouldn't be read back from disk while a write is still in progress
+    pub fn contains(&self, chunk_pos: IVec3) -> bool {
+        self.0.contains_key(&chunk_pos)
+    }
+
+    fn queue(
+        &mut self,
+        directory: &WorldDirectory,
+        format: ChunkFormat,
+        chunk_pos: IVec3,
+        blocks: Blocks,
+        stage: Stage,
+    ) {
+        // A chunk can be saved again before its previous write finishes, in which case the new
+        // write has to wait so that the older contents can't overwrite it
+        let previous_write = self.0.remove(&chunk_pos);
+        let directory = directory.0.clone();
+        let task = IoTaskPool::get().spawn(async move {
+            if let Some(previous_write) = previous_write {
+                previous_write.await;
+            }
+            if let Err(e) = format.write(&directory, chunk_pos, &blocks, &stage) {
+                warn!("Failed to save chunk at {:?}: {}", chunk_pos, e);
+            }
+        });
+        self.0.insert(chunk_pos, task);
+    }
+}
+
+fn poll_pending_writes(mut pending_writes: ResMut<PendingWrites>) {
+    pending_writes
+        .0
+        .retain(|_, task| !task.is_finished());
+}
+
+/// The app closes at the end of the frame, so any writes still running would be lost
+fn wait_for_pending_writes(mut pending_writes: ResMut<PendingWrites>) {
+    for (_, task) in pending_writes.0.drain() {
+        block_on(task);
+    }
+}
+
+#[derive(QueryData)]
+struct ChunkToSave {
+    entity: Entity,
+    pos: &'static ChunkPosition,
+    blocks: &'static Blocks,
+    stage: &'static Stage,
+}
+
+/// Chunks are despawned both when they leave the load distance and when leaving
+/// `AppState::InGame`, so this covers both cases.
+fn save_unloaded_chunk(
+    trigger: Trigger<OnRemove, Blocks>,
+    q_chunk: Query<ChunkToSave, (With<Chunk>, With<Dirty>)>,
+    directory: Res<WorldDirectory>,
+    format: Res<ChunkFormat>,
+    mut pending_writes: ResMut<PendingWrites>,
+) {
+    let Ok(chunk) = q_chunk.get(trigger.target()) else {
+        return;
+    };
+    queue_chunk_write(&mut pending_writes, &directory, *format, &chunk);
+}
+
+fn save_dirty_chunks(
+    mut commands: Commands,
+    q_chunk: Query<ChunkToSave, (With<Chunk>, With<Dirty>)>,
+    directory: Res<WorldDirectory>,
+    format: Res<ChunkFormat>,
+    mut pending_writes: ResMut<PendingWrites>,
+) {
+    for chunk in q_chunk.iter() {
+        if queue_chunk_write(&mut pending_writes, &directory, *format, &chunk) {
+            commands.entity(chunk.entity).remove::<Dirty>();
+        }
+    }
+}
+
+/// Returns true iff a write was queued
+fn queue_chunk_write(
+    pending_writes: &mut PendingWrites,
+    directory: &WorldDirectory,
+    format: ChunkFormat,
+    chunk: &ChunkToSaveItem,
+) -> bool {
+    // Chunks which haven't finished generating will be generated again from scratch
+    if chunk.stage != &Stage::final_stage() {
+        return false;
+    }
+    pending_writes.queue(
+        directory,
+        format,
+        chunk.pos.0,
+        chunk.blocks.clone(),
+        *chunk.stage,
+    );
+    true
+}
+
+fn invalid_input(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
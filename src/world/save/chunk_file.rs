@@ -0,0 +1,129 @@
+use crate::{
+    block::Block,
+    chunk::{data::Blocks, spatial::SpatiallyMapped, CHUNK_SIZE},
+    world::stage::Stage,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    invalid_data, list_positions,
+    migration::{self, CHUNK_FILE_VERSION},
+    SavedChunk, DIMENSION_ID,
+};
+
+const FILE_EXTENSION: &str = "chunkdata";
+
+/// See docs/chunk_file_format.md
+#[derive(Serialize, Deserialize)]
+struct ChunkFile {
+    version: u64,
+    stage: Stage,
+    /// Vertical runs of each block in the form `[x, y, z, length]`
+    blocks: BTreeMap<Block, Vec<[u8; 4]>>,
+}
+
+pub fn chunk_file_path(directory: &Path, chunk_pos: IVec3) -> PathBuf {
+    let IVec3 { x, y, z } = chunk_pos;
+    directory.join(format!("{DIMENSION_ID}_{x}_{y}_{z}.{FILE_EXTENSION}"))
+}
+
+pub fn directory_has_chunk_files(directory: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        entry
+            .path()
+            .extension()
+            .is_some_and(|ext| ext == FILE_EXTENSION)
+    })
+}
+
+/// Positions of every chunk saved in the directory, in no particular order
+pub fn list_chunks(directory: &Path) -> io::Result<Vec<IVec3>> {
+    list_positions(directory, FILE_EXTENSION)
+}
+
+pub fn write(directory: &Path, chunk_pos: IVec3, blocks: &Blocks, stage: &Stage) -> io::Result<()> {
+    let contents = serde_json::to_vec(&chunk_file(blocks, stage))?;
+    fs::create_dir_all(directory)?;
+    fs::write(chunk_file_path(directory, chunk_pos), contents)
+}
+
+/// The chunk in the same format as a chunk file, but indented for people to read
+pub fn to_pretty_json(blocks: &Blocks, stage: &Stage) -> io::Result<String> {
+    Ok(serde_json::to_string_pretty(&chunk_file(blocks, stage))?)
+}
+
+fn chunk_file(blocks: &Blocks, stage: &Stage) -> ChunkFile {
+    ChunkFile {
+        version: CHUNK_FILE_VERSION,
+        stage: *stage,
+        blocks: encode_runs(blocks),
+    }
+}
+
+/// Returns `None` if the chunk has never been saved
+pub fn read(directory: &Path, chunk_pos: IVec3) -> io::Result<Option<SavedChunk>> {
+    let contents = match fs::read(chunk_file_path(directory, chunk_pos)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut value = serde_json::from_slice(&contents)?;
+    let outdated = migration::upgrade_chunk_file(&mut value)?;
+    let file: ChunkFile = serde_json::from_value(value)?;
+    Ok(Some(SavedChunk {
+        blocks: decode_runs(&file.blocks)?,
+        stage: file.stage,
+        outdated,
+    }))
+}
+
+fn encode_runs(blocks: &Blocks) -> BTreeMap<Block, Vec<[u8; 4]>> {
+    let mut runs: BTreeMap<Block, Vec<[u8; 4]>> = BTreeMap::new();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let mut y = 0;
+            while y < CHUNK_SIZE {
+                let block = blocks.at_pos([x, y, z]);
+                let start = y;
+                while y < CHUNK_SIZE && blocks.at_pos([x, y, z]) == block {
+                    y += 1;
+                }
+                if block == &Block::Air {
+                    continue;
+                }
+                runs.entry(*block)
+                    .or_default()
+                    .push([x, start, z, y - start].map(|n| n as u8));
+            }
+        }
+    }
+    runs
+}
+
+fn decode_runs(runs: &BTreeMap<Block, Vec<[u8; 4]>>) -> io::Result<Blocks> {
+    let mut blocks = Blocks::default();
+    for (block, block_runs) in runs.iter() {
+        if block == &Block::Air {
+            return Err(invalid_data("Air must not be listed explicitly"));
+        }
+        for run in block_runs.iter() {
+            let [x, y, z, length] = run.map(|n| n as usize);
+            if x >= CHUNK_SIZE || z >= CHUNK_SIZE || y + length > CHUNK_SIZE {
+                return Err(invalid_data(format!("Run {:?} is out of bounds", run)));
+            }
+            for y in y..y + length {
+                *blocks.at_pos_mut([x, y, z]) = *block;
+            }
+        }
+    }
+    Ok(blocks)
+}
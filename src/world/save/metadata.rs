@@ -0,0 +1,91 @@
+use crate::{
+    physics::velocity::Velocity,
+    player::{
+        health::Health,
+        inventory::{HotbarSelection, Inventory},
+        mode::PlayerMode,
+    },
+    portal::{self, PortalPlacement},
+    world::preset::WorldPreset,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+use super::{
+    migration::{self, WORLD_METADATA_VERSION},
+    ChunkFormat,
+};
+
+const FILE_NAME: &str = "world.json";
+
+/// See docs/world_metadata_format.md
+#[derive(Resource, Serialize, Deserialize, Clone)]
+pub struct WorldMetadata {
+    pub version: u64,
+    pub seed: u32,
+    pub preset: WorldPreset,
+    pub chunk_format: ChunkFormat,
+    /// The block which new players stand in, `None` until it has been found
+    pub spawn: Option<IVec3>,
+    /// `None` until the world has been played and saved for the first time
+    pub player: Option<PlayerMetadata>,
+    pub portals: Vec<PortalPlacement>,
+}
+
+impl WorldMetadata {
+    pub fn new(seed: u32, preset: WorldPreset, chunk_format: ChunkFormat) -> Self {
+        Self {
+            version: WORLD_METADATA_VERSION,
+            seed,
+            preset,
+            chunk_format,
+            spawn: None,
+            player: None,
+            portals: portal::default_placements(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerMetadata {
+    pub transform: Transform,
+    pub velocity: Vec3,
+    pub health: u32,
+    pub inventory: Inventory,
+    pub hotbar_selection: u8,
+    pub mode: PlayerMode,
+}
+
+impl PlayerMetadata {
+    pub fn bundle(&self) -> impl Bundle {
+        (
+            self.transform,
+            Velocity(self.velocity),
+            Health(self.health),
+            self.inventory,
+            HotbarSelection {
+                index: self.hotbar_selection,
+            },
+            self.mode,
+        )
+    }
+}
+
+pub fn write(directory: &Path, metadata: &WorldMetadata) -> io::Result<()> {
+    let contents = serde_json::to_vec_pretty(metadata)?;
+    fs::create_dir_all(directory)?;
+    fs::write(directory.join(FILE_NAME), contents)
+}
+
+/// Returns `None` if the world has never been saved
+pub fn read(directory: &Path) -> io::Result<Option<WorldMetadata>> {
+    let contents = match fs::read(directory.join(FILE_NAME)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut value = serde_json::from_slice(&contents)?;
+    migration::upgrade_world_metadata(&mut value, directory)?;
+    Ok(Some(serde_json::from_value(value)?))
+}
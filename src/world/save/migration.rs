@@ -0,0 +1,142 @@
+//! Upgrades files saved by older versions of the game to the current format.
+//!
+//! Each format has a version number which is bumped whenever its layout or the set of blocks
+//! changes. Upgrading applies every step from the saved version to the current one in order, so
+//! a new step only ever has to handle the version directly before it.
+
+use crate::block::Block;
+use serde_json::Value;
+use std::{io, path::Path};
+
+use super::{invalid_data, ChunkFormat};
+
+pub const CHUNK_FILE_VERSION: u64 = 2;
+pub const WORLD_METADATA_VERSION: u64 = 5;
+pub const REGION_FILE_VERSION: u32 = 2;
+
+/// Returns true iff the chunk file had to be upgraded
+pub fn upgrade_chunk_file(file: &mut Value) -> io::Result<bool> {
+    upgrade(file, CHUNK_FILE_VERSION, |file, version| match version {
+        1 => chunk_file_v1_to_v2(file),
+        _ => Err(unknown_version(version)),
+    })
+}
+
+/// Returns true iff the world metadata file had to be upgraded. Some steps look at the rest of the
+/// world's directory.
+pub fn upgrade_world_metadata(file: &mut Value, directory: &Path) -> io::Result<bool> {
+    upgrade(
+        file,
+        WORLD_METADATA_VERSION,
+        |file, version| match version {
+            1 => world_metadata_v1_to_v2(file),
+            2 => world_metadata_v2_to_v3(file),
+            3 => world_metadata_v3_to_v4(file),
+            4 => world_metadata_v4_to_v5(file, directory),
+            _ => Err(unknown_version(version)),
+        },
+    )
+}
+
+fn upgrade(
+    file: &mut Value,
+    current_version: u64,
+    upgrade_from: impl Fn(&mut Value, u64) -> io::Result<()>,
+) -> io::Result<bool> {
+    let object = file
+        .as_object_mut()
+        .ok_or_else(|| invalid_data("Expected a JSON object"))?;
+    // Files from before versioning was introduced are version 1
+    let version = match object.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| invalid_data("Version must be a positive integer"))?,
+    };
+    if version < 1 {
+        return Err(invalid_data(format!(
+            "Unsupported format version {version}"
+        )));
+    }
+    if version > current_version {
+        return Err(invalid_data(format!(
+            "Saved by a newer version of the game (format version {version})"
+        )));
+    }
+    for old_version in version..current_version {
+        upgrade_from(file, old_version)?;
+    }
+    file["version"] = current_version.into();
+    Ok(version < current_version)
+}
+
+fn unknown_version(version: u64) -> io::Error {
+    invalid_data(format!("No upgrade from format version {version}"))
+}
+
+fn chunk_file_v1_to_v2(file: &mut Value) -> io::Result<()> {
+    let blocks = file
+        .get_mut("blocks")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| invalid_data("Chunk file has no blocks"))?;
+    *blocks = std::mem::take(blocks)
+        .into_iter()
+        .map(|(name, runs)| Ok((block_id_from_v1_name(&name)?.into(), runs)))
+        .collect::<io::Result<_>>()?;
+    Ok(())
+}
+
+fn world_metadata_v1_to_v2(file: &mut Value) -> io::Result<()> {
+    let Some(hotbar) = file
+        .pointer_mut("/player/inventory/hotbar")
+        .and_then(Value::as_array_mut)
+    else {
+        return Ok(());
+    };
+    for block in hotbar
+        .iter_mut()
+        .filter_map(|slot| slot.pointer_mut("/item/Block"))
+    {
+        let name = block.as_str().unwrap_or_default();
+        *block = block_id_from_v1_name(name)?.into();
+    }
+    Ok(())
+}
+
+/// Version 3 added world presets, and every world before then used the default terrain
+fn world_metadata_v2_to_v3(file: &mut Value) -> io::Result<()> {
+    file["preset"] = "Default".into();
+    Ok(())
+}
+
+/// Version 4 added the spawn point, which is found again the next time the world is entered
+fn world_metadata_v3_to_v4(file: &mut Value) -> io::Result<()> {
+    file["spawn"] = Value::Null;
+    Ok(())
+}
+
+/// Version 5 saved the chunk format, which older worlds picked by looking at their files
+fn world_metadata_v4_to_v5(file: &mut Value, directory: &Path) -> io::Result<()> {
+    file["chunk_format"] = serde_json::to_value(ChunkFormat::detect(directory))?;
+    Ok(())
+}
+
+/// Version 1 identified blocks by the names of their enum variants, before `Block::id` existed
+pub fn block_from_v1_name(name: &str) -> io::Result<Block> {
+    match name {
+        "Air" => Ok(Block::Air),
+        "Stone" => Ok(Block::Stone),
+        "Dirt" => Ok(Block::Dirt),
+        "Grass" => Ok(Block::Grass),
+        "Sand" => Ok(Block::Sand),
+        "Wood" => Ok(Block::Wood),
+        "Leaves" => Ok(Block::Leaves),
+        "Water" => Ok(Block::Water),
+        "Bedrock" => Ok(Block::Bedrock),
+        _ => Err(invalid_data(format!("Unknown block {name}"))),
+    }
+}
+
+fn block_id_from_v1_name(name: &str) -> io::Result<&'static str> {
+    block_from_v1_name(name).map(|block| block.id())
+}
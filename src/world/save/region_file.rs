@@ -0,0 +1,544 @@
+use crate::{
+    block::Block,
+    chunk::{data::Blocks, CHUNK_LENGTH},
+    world::stage::Stage,
+};
+use bevy::prelude::*;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::{
+    invalid_data, list_positions,
+    migration::{self, REGION_FILE_VERSION},
+    SavedChunk, DIMENSION_ID,
+};
+
+/// Number of chunks along each axis of a region
+pub const REGION_SIZE: i32 = 16;
+const CHUNKS_PER_REGION: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+const FILE_EXTENSION: &str = "region";
+/// Upgraded region files are written to this first, so that the old file is only replaced once
+/// the new one is complete
+const UPGRADE_EXTENSION: &str = "region.tmp";
+const MAGIC: &[u8; 4] = b"VXRG";
+const SECTOR_SIZE: u64 = 512;
+/// Magic, version, then one `(first_sector: u32, sector_count: u32)` entry per chunk
+const HEADER_LENGTH: u64 = 8 + 8 * CHUNKS_PER_REGION as u64;
+const HEADER_SECTORS: u64 = HEADER_LENGTH.div_ceil(SECTOR_SIZE);
+
+/// Serializes all region file access, since several chunks share one file
+static REGION_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn region_position(chunk_pos: IVec3) -> IVec3 {
+    chunk_pos.div_euclid(IVec3::splat(REGION_SIZE))
+}
+
+pub fn region_file_path(directory: &Path, region_pos: IVec3) -> PathBuf {
+    let IVec3 { x, y, z } = region_pos;
+    directory.join(format!("{DIMENSION_ID}_{x}_{y}_{z}.{FILE_EXTENSION}"))
+}
+
+fn table_index(chunk_pos: IVec3) -> usize {
+    let IVec3 { x, y, z } = chunk_pos.rem_euclid(IVec3::splat(REGION_SIZE));
+    (REGION_SIZE * REGION_SIZE * x + REGION_SIZE * z + y) as usize
+}
+
+/// Inverse of `table_index`, relative to the region's first chunk
+fn table_position(index: usize) -> IVec3 {
+    let index = index as i32;
+    IVec3::new(
+        index / (REGION_SIZE * REGION_SIZE),
+        index % REGION_SIZE,
+        index / REGION_SIZE % REGION_SIZE,
+    )
+}
+
+/// Positions of every region file in the directory, in no particular order
+pub fn list_regions(directory: &Path) -> io::Result<Vec<IVec3>> {
+    list_positions(directory, FILE_EXTENSION)
+}
+
+/// Positions of every chunk saved in the directory, in no particular order
+pub fn list_chunks(directory: &Path) -> io::Result<Vec<IVec3>> {
+    let mut chunks = vec![];
+    for region_pos in list_regions(directory)? {
+        chunks.extend(list_chunks_in_region(directory, region_pos)?);
+    }
+    Ok(chunks)
+}
+
+pub fn list_chunks_in_region(directory: &Path, region_pos: IVec3) -> io::Result<Vec<IVec3>> {
+    let _lock = REGION_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = File::open(region_file_path(directory, region_pos))?;
+    check_header(&mut file)?;
+    let first_chunk = region_pos * REGION_SIZE;
+    Ok(read_table(&mut file)?
+        .iter()
+        .enumerate()
+        .filter(|(_, (first_sector, _))| *first_sector != 0)
+        .map(|(index, _)| first_chunk + table_position(index))
+        .collect())
+}
+
+/// Checks that the header of a region file is valid and that every chunk in its table lies
+/// within the file without overlapping any other chunk. The chunks themselves are not decoded.
+pub fn check_region(directory: &Path, region_pos: IVec3) -> io::Result<()> {
+    let _lock = REGION_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = File::open(region_file_path(directory, region_pos))?;
+    check_header(&mut file)?;
+    let file_sectors = file.metadata()?.len().div_ceil(SECTOR_SIZE);
+    let mut entries = read_table(&mut file)?
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (first_sector, _))| *first_sector != 0)
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|(_, (first_sector, _))| *first_sector);
+    let mut previous_end = HEADER_SECTORS;
+    for (index, (first_sector, sector_count)) in entries {
+        let chunk_pos = region_pos * REGION_SIZE + table_position(index);
+        if first_sector < previous_end {
+            return Err(invalid_data(format!(
+                "Chunk {chunk_pos} overlaps the header or another chunk"
+            )));
+        }
+        if sector_count == 0 || first_sector + sector_count > file_sectors {
+            return Err(invalid_data(format!(
+                "Chunk {chunk_pos} extends past the end of the file"
+            )));
+        }
+        previous_end = first_sector + sector_count;
+    }
+    Ok(())
+}
+
+pub fn write(directory: &Path, chunk_pos: IVec3, blocks: &Blocks, stage: &Stage) -> io::Result<()> {
+    let payload = encode_chunk(blocks, stage);
+    let _lock = REGION_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    fs::create_dir_all(directory)?;
+    let region_pos = region_position(chunk_pos);
+    let path = region_file_path(directory, region_pos);
+    let mut file = open_for_writing(&path)?;
+    if file.metadata()?.len() == 0 {
+        write_empty_header(&mut file)?;
+    }
+    let version = check_header(&mut file)?;
+    // Every chunk in a region shares the version in the header, so the others have to be upgraded
+    // before a chunk in the current format can be added
+    if version < REGION_FILE_VERSION {
+        drop(file);
+        upgrade(&path, region_pos, version)?;
+        file = open_for_writing(&path)?;
+    }
+    write_payload(&mut file, table_index(chunk_pos), &payload)
+}
+
+fn open_for_writing(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+fn write_payload(file: &mut File, index: usize, payload: &[u8]) -> io::Result<()> {
+    let sector_count = (payload.len() as u64).div_ceil(SECTOR_SIZE);
+    let (first_sector, existing_sector_count) = read_table_entry(file, index)?;
+    // Overwrite the chunk in place if it still fits, otherwise move it to the end of the file.
+    // The sectors it used to occupy are left unused.
+    let first_sector = if first_sector != 0 && sector_count <= existing_sector_count {
+        first_sector
+    } else {
+        file.metadata()?.len().div_ceil(SECTOR_SIZE)
+    };
+    file.seek(SeekFrom::Start(first_sector * SECTOR_SIZE))?;
+    file.write_all(payload)?;
+    let padding = sector_count * SECTOR_SIZE - payload.len() as u64;
+    file.write_all(&vec![0; padding as usize])?;
+    write_table_entry(file, index, first_sector, sector_count)
+}
+
+/// Rewrites every chunk of a region file saved by an older version in the current format. The
+/// new file replaces the old one once it has been written in full, so the region isn't lost if
+/// the game stops partway through. Chunks which can't be read are left out, so that they're
+/// generated again instead of stopping the rest of the region from being saved.
+fn upgrade(path: &Path, region_pos: IVec3, version: u32) -> io::Result<()> {
+    let mut old_file = File::open(path)?;
+    let upgraded_path = path.with_extension(UPGRADE_EXTENSION);
+    // Left over if the game stopped during an earlier upgrade
+    let mut new_file = open_for_writing(&upgraded_path)?;
+    new_file.set_len(0)?;
+    write_empty_header(&mut new_file)?;
+    for index in 0..CHUNKS_PER_REGION {
+        match read_chunk(&mut old_file, index, version) {
+            Ok(Some((blocks, stage))) => {
+                write_payload(&mut new_file, index, &encode_chunk(&blocks, &stage))?;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Dropping unreadable chunk at {} while upgrading {}: {}",
+                region_pos * REGION_SIZE + table_position(index),
+                path.display(),
+                e
+            ),
+        }
+    }
+    new_file.sync_all()?;
+    drop(old_file);
+    drop(new_file);
+    fs::rename(upgraded_path, path)
+}
+
+/// Returns `None` if the chunk has never been saved
+pub fn read(directory: &Path, chunk_pos: IVec3) -> io::Result<Option<SavedChunk>> {
+    let _lock = REGION_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = region_file_path(directory, region_position(chunk_pos));
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let version = check_header(&mut file)?;
+    let saved_chunk = read_chunk(&mut file, table_index(chunk_pos), version)?;
+    Ok(saved_chunk.map(|(blocks, stage)| SavedChunk {
+        blocks,
+        stage,
+        outdated: version < REGION_FILE_VERSION,
+    }))
+}
+
+fn read_chunk(file: &mut File, index: usize, version: u32) -> io::Result<Option<(Blocks, Stage)>> {
+    let (first_sector, sector_count) = read_table_entry(file, index)?;
+    if first_sector == 0 {
+        return Ok(None);
+    }
+    let mut payload = vec![0; (sector_count * SECTOR_SIZE) as usize];
+    file.seek(SeekFrom::Start(first_sector * SECTOR_SIZE))?;
+    file.read_exact(&mut payload)?;
+    decode_chunk(&payload, version).map(Some)
+}
+
+fn write_empty_header(file: &mut File) -> io::Result<()> {
+    let mut header = vec![0; (HEADER_SECTORS * SECTOR_SIZE) as usize];
+    header[0..4].copy_from_slice(MAGIC);
+    header[4..8].copy_from_slice(&REGION_FILE_VERSION.to_le_bytes());
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&header)
+}
+
+/// Returns the format version of the region file
+fn check_header(file: &mut File) -> io::Result<u32> {
+    let mut header = [0; 8];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC {
+        return Err(invalid_data("Not a region file"));
+    }
+    let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    if version == 0 || version > REGION_FILE_VERSION {
+        return Err(invalid_data(format!(
+            "Unsupported region format version {version}"
+        )));
+    }
+    Ok(version)
+}
+
+fn read_table_entry(file: &mut File, index: usize) -> io::Result<(u64, u64)> {
+    let mut entry = [0; 8];
+    file.seek(SeekFrom::Start(8 + 8 * index as u64))?;
+    file.read_exact(&mut entry)?;
+    let first_sector = u32::from_le_bytes(entry[0..4].try_into().unwrap());
+    let sector_count = u32::from_le_bytes(entry[4..8].try_into().unwrap());
+    Ok((first_sector as u64, sector_count as u64))
+}
+
+fn read_table(file: &mut File) -> io::Result<Vec<(u64, u64)>> {
+    let mut table = vec![0; 8 * CHUNKS_PER_REGION];
+    file.seek(SeekFrom::Start(8))?;
+    file.read_exact(&mut table)?;
+    Ok(table
+        .chunks_exact(8)
+        .map(|entry| {
+            let first_sector = u32::from_le_bytes(entry[0..4].try_into().unwrap());
+            let sector_count = u32::from_le_bytes(entry[4..8].try_into().unwrap());
+            (first_sector as u64, sector_count as u64)
+        })
+        .collect())
+}
+
+fn write_table_entry(
+    file: &mut File,
+    index: usize,
+    first_sector: u64,
+    sector_count: u64,
+) -> io::Result<()> {
+    let mut entry = [0; 8];
+    entry[0..4].copy_from_slice(&(first_sector as u32).to_le_bytes());
+    entry[4..8].copy_from_slice(&(sector_count as u32).to_le_bytes());
+    file.seek(SeekFrom::Start(8 + 8 * index as u64))?;
+    file.write_all(&entry)
+}
+
+/// The payload starts with its own length, followed by the stage, the block palette as numeric
+/// block IDs, and finally the palette indices of every block, run-length encoded.
+fn encode_chunk(blocks: &Blocks, stage: &Stage) -> Vec<u8> {
+    let mut palette: Vec<Block> = vec![];
+    let mut body = vec![];
+    for run in blocks.0.chunk_by(|a, b| a == b) {
+        let block = run[0];
+        let palette_index = match palette.iter().position(|b| b == &block) {
+            Some(index) => index,
+            None => {
+                palette.push(block);
+                palette.len() - 1
+            }
+        };
+        // CHUNK_LENGTH is small enough that even a run covering the whole chunk fits in a u16
+        body.extend_from_slice(&(run.len() as u16).to_le_bytes());
+        body.push(palette_index as u8);
+    }
+    let mut payload = vec![0; 4];
+    payload.push(stage_to_byte(stage));
+    payload.push(palette.len() as u8);
+    for block in palette.iter() {
+        payload.extend_from_slice(&block.numeric_id().to_le_bytes());
+    }
+    payload.extend(body);
+    let length = (payload.len() - 4) as u32;
+    payload[0..4].copy_from_slice(&length.to_le_bytes());
+    payload
+}
+
+fn decode_chunk(payload: &[u8], version: u32) -> io::Result<(Blocks, Stage)> {
+    let mut reader = PayloadReader { payload, offset: 0 };
+    let length = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+    reader.payload = payload
+        .get(..4 + length)
+        .ok_or_else(|| invalid_data("Chunk payload is truncated"))?;
+    let stage = stage_from_byte(reader.take(1)?[0])?;
+    let palette_length = reader.take(1)?[0];
+    let mut palette = vec![];
+    for _ in 0..palette_length {
+        let block = match version {
+            1 => {
+                let name_length = reader.take(1)?[0] as usize;
+                let name = std::str::from_utf8(reader.take(name_length)?)
+                    .map_err(|_| invalid_data("Block name is not valid UTF-8"))?;
+                migration::block_from_v1_name(name)?
+            }
+            _ => {
+                let id = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+                Block::from_numeric_id(id)
+                    .ok_or_else(|| invalid_data(format!("Unknown block ID {id}")))?
+            }
+        };
+        palette.push(block);
+    }
+    let mut blocks = Vec::with_capacity(CHUNK_LENGTH);
+    while blocks.len() < CHUNK_LENGTH {
+        let run = reader.take(3)?;
+        let count = u16::from_le_bytes([run[0], run[1]]) as usize;
+        let block = palette
+            .get(run[2] as usize)
+            .ok_or_else(|| invalid_data("Palette index out of range"))?;
+        blocks.extend(std::iter::repeat_n(*block, count));
+    }
+    if blocks.len() != CHUNK_LENGTH {
+        return Err(invalid_data("Chunk has too many blocks"));
+    }
+    Ok((Blocks(blocks), stage))
+}
+
+struct PayloadReader<'a> {
+    payload: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .payload
+            .get(self.offset..self.offset + count)
+            .ok_or_else(|| invalid_data("Chunk payload is truncated"))?;
+        self.offset += count;
+        Ok(bytes)
+    }
+}
+
+fn stage_to_byte(stage: &Stage) -> u8 {
+    match stage {
+        Stage::Noise => 0,
+        Stage::Sculpt => 1,
+        Stage::Structures => 2,
+        Stage::Ores => 3,
+        Stage::Decorations => 4,
+    }
+}
+
+fn stage_from_byte(byte: u8) -> io::Result<Stage> {
+    match byte {
+        0 => Ok(Stage::Noise),
+        1 => Ok(Stage::Sculpt),
+        2 => Ok(Stage::Structures),
+        3 => Ok(Stage::Ores),
+        4 => Ok(Stage::Decorations),
+        _ => Err(invalid_data(format!("Unknown stage {byte}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::spatial::SpatiallyMapped;
+
+    /// An empty directory of its own for each test
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "voxel-engine-region-{}-{name}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// Layers of a few blocks, which encode to a handful of runs
+    fn layered_blocks() -> Blocks {
+        Blocks::from_fn(|[_, y, _]| match y {
+            0 => Block::Bedrock,
+            1..=9 => Block::Stone,
+            10..=12 => Block::Dirt,
+            13 => Block::Grass,
+            _ => Block::Air,
+        })
+    }
+
+    /// Alternating blocks, which encode to a run per block and take up many sectors
+    fn checkered_blocks() -> Blocks {
+        Blocks::from_fn(|[x, y, z]| match (x + y + z) % 3 {
+            0 => Block::Sand,
+            1 => Block::Wood,
+            _ => Block::Water,
+        })
+    }
+
+    fn read_blocks(directory: &Path, chunk_pos: IVec3) -> Vec<Block> {
+        read(directory, chunk_pos)
+            .unwrap()
+            .expect("the chunk was saved")
+            .blocks
+            .0
+    }
+
+    #[test]
+    fn chunks_read_back_as_written() {
+        let directory = test_directory("round_trip");
+        let chunk_pos = IVec3::new(-1, 2, 17);
+        write(&directory, chunk_pos, &layered_blocks(), &Stage::Decorations).unwrap();
+        let saved = read(&directory, chunk_pos)
+            .unwrap()
+            .expect("the chunk was saved");
+        assert_eq!(saved.blocks.0, layered_blocks().0);
+        assert_eq!(saved.stage, Stage::Decorations);
+        assert!(!saved.outdated);
+        assert!(read(&directory, chunk_pos + IVec3::Y)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            list_chunks(&directory).unwrap(),
+            vec![chunk_pos]
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn growing_a_chunk_leaves_its_neighbours_unchanged() {
+        let directory = test_directory("grow");
+        let chunk_pos = IVec3::new(3, 0, 5);
+        let neighbours = [chunk_pos - IVec3::Y, chunk_pos + IVec3::Y, chunk_pos + IVec3::X];
+        write(&directory, chunk_pos, &layered_blocks(), &Stage::Decorations).unwrap();
+        for neighbour in neighbours {
+            write(&directory, neighbour, &layered_blocks(), &Stage::Decorations).unwrap();
+        }
+        write(&directory, chunk_pos, &checkered_blocks(), &Stage::Decorations).unwrap();
+        assert_eq!(read_blocks(&directory, chunk_pos), checkered_blocks().0);
+        for neighbour in neighbours {
+            assert_eq!(read_blocks(&directory, neighbour), layered_blocks().0);
+        }
+        check_region(&directory, region_position(chunk_pos)).unwrap();
+        // Shrinking it again reuses its sectors
+        write(&directory, chunk_pos, &layered_blocks(), &Stage::Decorations).unwrap();
+        assert_eq!(read_blocks(&directory, chunk_pos), layered_blocks().0);
+        check_region(&directory, region_position(chunk_pos)).unwrap();
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// A chunk as version 1 stored it, with block names in the palette
+    fn encode_v1_chunk(blocks: &Blocks, stage: &Stage, names: &[(Block, &str)]) -> Vec<u8> {
+        let payload = encode_chunk(blocks, stage);
+        let palette_length = payload[5] as usize;
+        let mut v1_payload = vec![0; 4];
+        v1_payload.extend_from_slice(&payload[4..6]);
+        for id in payload[6..6 + 2 * palette_length].chunks_exact(2) {
+            let block = Block::from_numeric_id(u16::from_le_bytes([id[0], id[1]])).unwrap();
+            let (_, name) = names
+                .iter()
+                .find(|(b, _)| *b == block)
+                .unwrap();
+            v1_payload.push(name.len() as u8);
+            v1_payload.extend_from_slice(name.as_bytes());
+        }
+        v1_payload.extend_from_slice(&payload[6 + 2 * palette_length..]);
+        let length = (v1_payload.len() - 4) as u32;
+        v1_payload[0..4].copy_from_slice(&length.to_le_bytes());
+        v1_payload
+    }
+
+    #[test]
+    fn version_1_regions_are_upgraded_when_written_to() {
+        let directory = test_directory("upgrade");
+        let old_chunk = IVec3::new(0, -1, 0);
+        let new_chunk = IVec3::new(1, -1, 0);
+        let names = [
+            (Block::Bedrock, "Bedrock"),
+            (Block::Stone, "Stone"),
+            (Block::Dirt, "Dirt"),
+            (Block::Grass, "Grass"),
+            (Block::Air, "Air"),
+        ];
+        let path = region_file_path(&directory, region_position(old_chunk));
+        let mut file = open_for_writing(&path).unwrap();
+        write_empty_header(&mut file).unwrap();
+        file.seek(SeekFrom::Start(4)).unwrap();
+        file.write_all(&1u32.to_le_bytes()).unwrap();
+        let payload = encode_v1_chunk(&layered_blocks(), &Stage::Decorations, &names);
+        write_payload(&mut file, table_index(old_chunk), &payload).unwrap();
+        drop(file);
+
+        let saved = read(&directory, old_chunk)
+            .unwrap()
+            .expect("the chunk was saved");
+        assert_eq!(saved.blocks.0, layered_blocks().0);
+        assert!(saved.outdated);
+
+        write(&directory, new_chunk, &checkered_blocks(), &Stage::Decorations).unwrap();
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(check_header(&mut file).unwrap(), REGION_FILE_VERSION);
+        let saved = read(&directory, old_chunk)
+            .unwrap()
+            .expect("the chunk was kept");
+        assert_eq!(saved.blocks.0, layered_blocks().0);
+        assert_eq!(saved.stage, Stage::Decorations);
+        assert!(!saved.outdated);
+        assert_eq!(read_blocks(&directory, new_chunk), checkered_blocks().0);
+        assert!(!path
+            .with_extension(UPGRADE_EXTENSION)
+            .exists());
+        check_region(&directory, region_position(old_chunk)).unwrap();
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
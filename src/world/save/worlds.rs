@@ -0,0 +1,97 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::world::preset::WorldPreset;
+
+use super::{
+    invalid_input,
+    metadata::{self, WorldMetadata},
+    ChunkFormat, WorldDirectory, WORLDS_DIRECTORY,
+};
+
+const MAX_WORLD_NAME_LENGTH: usize = 32;
+
+/// Names of all worlds in the worlds directory, in alphabetical order
+pub fn list() -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(WORLDS_DIRECTORY) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut names = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Ok(name) = entry.file_name().into_string() {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+pub fn create(
+    name: &str,
+    seed: u32,
+    preset: WorldPreset,
+    chunk_format: ChunkFormat,
+) -> io::Result<WorldDirectory> {
+    validate_name(name)?;
+    fs::create_dir_all(WORLDS_DIRECTORY)?;
+    let directory = WorldDirectory::named(name);
+    fs::create_dir(&directory.0).map_err(already_exists_message)?;
+    metadata::write(&directory.0, &WorldMetadata::new(seed, preset, chunk_format))?;
+    Ok(directory)
+}
+
+pub fn rename(name: &str, new_name: &str) -> io::Result<()> {
+    validate_name(new_name)?;
+    let new_directory = world_path(new_name);
+    if new_directory.exists() {
+        return Err(already_exists_message(io::ErrorKind::AlreadyExists.into()));
+    }
+    fs::rename(world_path(name), new_directory)
+}
+
+pub fn delete(name: &str) -> io::Result<()> {
+    fs::remove_dir_all(world_path(name))
+}
+
+fn world_path(name: &str) -> PathBuf {
+    WorldDirectory::named(name).0
+}
+
+/// World names double as directory names, so only allow characters which are safe on every platform
+fn validate_name(name: &str) -> io::Result<()> {
+    if name.trim().is_empty() {
+        return Err(invalid_input("World name must not be empty"));
+    }
+    if name.trim() != name {
+        return Err(invalid_input(
+            "World name must not start or end with a space",
+        ));
+    }
+    if name.chars().count() > MAX_WORLD_NAME_LENGTH {
+        return Err(invalid_input(format!(
+            "World name must be at most {MAX_WORLD_NAME_LENGTH} characters long"
+        )));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_')
+    {
+        return Err(invalid_input(
+            "World name may only contain letters, digits, spaces, '-' and '_'",
+        ));
+    }
+    Ok(())
+}
+
+fn already_exists_message(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::AlreadyExists {
+        invalid_input("A world with that name already exists")
+    } else {
+        e
+    }
+}
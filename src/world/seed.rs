@@ -1,10 +1,22 @@
 use bevy::prelude::*;
 
+use crate::state::AppState;
+
+use super::save::{metadata::WorldMetadata, LoadWorldMetadata};
+
+/// Seed of worlds which don't specify one
+pub const DEFAULT_SEED: u32 = 0xDEADBEEF;
+
 pub struct SeedPlugin;
 
 impl Plugin for SeedPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup.in_set(LoadSeed));
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            setup
+                .in_set(LoadSeed)
+                .after(LoadWorldMetadata),
+        );
     }
 }
 
@@ -14,6 +26,25 @@ pub struct LoadSeed;
 #[derive(Resource)]
 pub struct WorldSeed(pub u32);
 
-fn setup(mut commands: Commands) {
-    commands.insert_resource(WorldSeed(0xDEADBEEF));
+fn setup(mut commands: Commands, metadata: Res<WorldMetadata>) {
+    commands.insert_resource(WorldSeed(metadata.seed));
+}
+
+/// Seeds can be entered as a number or as arbitrary text, which is hashed. Leaving the seed blank
+/// picks one at random.
+pub fn seed_from_text(text: &str) -> u32 {
+    let text = text.trim();
+    if text.is_empty() {
+        return rand::random();
+    }
+    if let Ok(seed) = text.parse::<u32>() {
+        return seed;
+    }
+    if let Ok(seed) = text.parse::<i64>() {
+        return seed as u32;
+    }
+    // 32-bit FNV-1a, since the std hashers aren't guaranteed to be stable between releases
+    text.bytes().fold(0x811C9DC5, |hash: u32, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
 }
@@ -0,0 +1,198 @@
+//! Finds a safe place for players who have never been in the world. The terrain noise gives a
+//! rough idea of where there's dry land near the origin, and once the chunks there have been
+//! generated the player is placed on the ground nearby. The result is saved in the world metadata
+//! as the spawn point, so it's only searched for once.
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+
+use crate::{
+    block::Block,
+    chunk::{data::Blocks, position::ChunkPosition, CHUNK_SIZE_I32},
+    physics::velocity::Velocity,
+    player::{Player, PLAYER_EYE_HEIGHT},
+    state::AppState,
+};
+
+use super::{
+    heightmap::Heightmap,
+    index::ChunkIndex,
+    neighborhood::{ComponentIndex, Neighborhood},
+    save::metadata::WorldMetadata,
+    stage::Stage,
+    SurfaceSampler, TerrainNoise, WorldSet,
+};
+
+/// Columns this far apart are sampled when looking for land in the terrain noise
+const ESTIMATE_STEP: i32 = 16;
+/// The terrain noise is sampled up to this far from the origin
+const ESTIMATE_RADIUS: i32 = 1024;
+/// Generated columns up to this far from the estimate are looked at for dry ground
+const GROUND_SEARCH_RADIUS: i32 = CHUNK_SIZE_I32;
+
+pub struct SpawnPlugin;
+
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (estimate_spawn, settle_spawn)
+                .chain()
+                .after(WorldSet)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Marks a player who has never been in the world. They're held in place until the ground under
+/// them has been generated.
+#[derive(Component, Default)]
+pub struct FindingSpawn {
+    /// The block which the player's feet should be in according to the terrain noise, once it has
+    /// been worked out
+    estimate: Option<IVec3>,
+    /// Searches the terrain noise for the estimate, which can take a while
+    task: Option<Task<IVec3>>,
+}
+
+/// Where the player's camera is when they stand in a block
+pub fn spawn_translation(spawn: IVec3) -> Vec3 {
+    spawn.as_vec3() + Vec3::new(0.5, PLAYER_EYE_HEIGHT, 0.5)
+}
+
+fn estimate_spawn(
+    mut commands: Commands,
+    mut q_player: Query<(Entity, &mut Transform, &mut FindingSpawn), With<Player>>,
+    metadata: Res<WorldMetadata>,
+    terrain_noise: TerrainNoise,
+) {
+    for (entity, mut transform, mut finding_spawn) in q_player.iter_mut() {
+        if finding_spawn.estimate.is_some() {
+            continue;
+        }
+        if let Some(spawn) = metadata.spawn {
+            transform.translation = spawn_translation(spawn);
+            commands
+                .entity(entity)
+                .remove::<FindingSpawn>();
+            continue;
+        }
+        let Some(task) = &mut finding_spawn.task else {
+            let sampler = terrain_noise.sampler();
+            finding_spawn.task = Some(
+                AsyncComputeTaskPool::get().spawn(async move { estimate_from_noise(&sampler) }),
+            );
+            continue;
+        };
+        let Some(estimate) = block_on(future::poll_once(task)) else {
+            continue;
+        };
+        transform.translation = spawn_translation(estimate);
+        finding_spawn.estimate = Some(estimate);
+        finding_spawn.task = None;
+    }
+}
+
+/// The closest dry land to the origin according to the terrain noise, or the origin at sea level
+/// if there's none nearby or the preset's terrain isn't shaped by the noise
+fn estimate_from_noise(sampler: &SurfaceSampler) -> IVec3 {
+    (0..=ESTIMATE_RADIUS / ESTIMATE_STEP)
+        .flat_map(square_ring)
+        .map(|column| column * ESTIMATE_STEP)
+        .find_map(|column| {
+            sampler
+                .ground_height(column)
+                .map(|ground| IVec3::new(column.x, ground + 1, column.y))
+        })
+        .unwrap_or(IVec3::new(0, sampler.config().sea_level + 1, 0))
+}
+
+/// The columns whose furthest coordinate from the origin is the radius
+fn square_ring(radius: i32) -> impl Iterator<Item = IVec2> {
+    (-radius..=radius)
+        .flat_map(move |x| (-radius..=radius).map(move |z| IVec2::new(x, z)))
+        .filter(move |column| column.abs().max_element() == radius)
+}
+
+fn settle_spawn(
+    mut commands: Commands,
+    mut q_player: Query<(Entity, &mut Transform, &mut Velocity, &FindingSpawn), With<Player>>,
+    q_stage: Query<&Neighborhood<Stage>>,
+    chunk_index: Res<ChunkIndex>,
+    heightmaps: Res<ComponentIndex<Heightmap>>,
+    blocks: Res<ComponentIndex<Blocks>>,
+    mut metadata: ResMut<WorldMetadata>,
+) {
+    for (entity, mut transform, mut velocity, finding_spawn) in q_player.iter_mut() {
+        let Some(estimate) = finding_spawn.estimate else {
+            continue;
+        };
+        transform.translation = spawn_translation(estimate);
+        velocity.0 = Vec3::ZERO;
+        // Every chunk around the estimate must have its structures, so that the player isn't
+        // placed inside a tree
+        let chunk_pos = ChunkPosition::from_world_position(&transform.translation);
+        let is_generated = chunk_index
+            .entity_by_pos
+            .get(&chunk_pos.0)
+            .and_then(|chunk| q_stage.get(*chunk).ok())
+            .is_some_and(|neighborhood| {
+                neighborhood
+                    .0
+                    .iter()
+                    .all(|stage| stage.as_deref() == Some(&Stage::final_stage()))
+            });
+        if !is_generated {
+            continue;
+        }
+        let spawn = find_dry_ground(estimate, &heightmaps, &blocks).unwrap_or_else(|| {
+            warn!("Found no dry ground near {}, spawning there anyway", estimate);
+            estimate
+        });
+        transform.translation = spawn_translation(spawn);
+        metadata.spawn = Some(spawn);
+        commands
+            .entity(entity)
+            .remove::<FindingSpawn>();
+    }
+}
+
+/// The block above the closest ground to the estimate with nothing but a plant above it, so that
+/// it isn't under water or a tree. Only the chunks next to the estimate's chunk are looked at,
+/// since chunks further out may still be generating.
+fn find_dry_ground(
+    estimate: IVec3,
+    heightmaps: &ComponentIndex<Heightmap>,
+    blocks: &ComponentIndex<Blocks>,
+) -> Option<IVec3> {
+    let chunk_size = IVec2::splat(CHUNK_SIZE_I32);
+    let estimate_chunk = estimate.xz().div_euclid(chunk_size);
+    let mut columns = square_area(GROUND_SEARCH_RADIUS)
+        .map(|offset| estimate.xz() + offset)
+        .filter(|column| {
+            (column.div_euclid(chunk_size) - estimate_chunk)
+                .abs()
+                .max_element()
+                <= 1
+        })
+        .collect::<Vec<_>>();
+    columns.sort_by_key(|column| (*column - estimate.xz()).length_squared());
+    columns.into_iter().find_map(|column| {
+        let ground = heightmaps.highest_solid(column)?;
+        let top = heightmaps.highest_non_air(column)?;
+        let pos = IVec3::new(column.x, ground, column.y);
+        // Plants can be stood in
+        let is_clear = top == ground
+            || (top == ground + 1 && blocks.at_pos(pos + IVec3::Y).is_some_and(Block::is_plant));
+        let is_ground = blocks
+            .at_pos(pos)
+            .is_some_and(|block| !matches!(block, Block::Leaves | Block::Cactus));
+        (is_clear && is_ground).then_some(pos + IVec3::Y)
+    })
+}
+
+fn square_area(radius: i32) -> impl Iterator<Item = IVec2> {
+    (-radius..=radius).flat_map(move |x| (-radius..=radius).map(move |z| IVec2::new(x, z)))
+}
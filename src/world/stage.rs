@@ -1,15 +1,20 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(
+    Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
 pub enum Stage {
     #[default]
     Noise,
     Sculpt,
+    Ores,
     Structures,
+    Decorations,
 }
 
 impl Stage {
     pub fn final_stage() -> Self {
-        Self::Structures
+        Self::Decorations
     }
 }
@@ -0,0 +1,189 @@
+//! Rivers and lakes, which are carved into the land once its height has been worked out. They only
+//! depend on the seed, so they line up across chunk borders.
+//!
+//! Rivers follow the lines where the river noise is 0, so they wind across whole continents and
+//! reach the ocean. Their water is at sea level, so they cut valleys through higher land. Lakes
+//! are placed on a grid like structure starts, and fill a bowl up to a level above the sea. A lake
+//! is only placed where the land all around it is higher than its water.
+
+use bevy::prelude::*;
+
+use crate::{
+    block::Block,
+    utils::{mix_hash, nth_random},
+};
+
+use super::SurfaceSampler;
+
+/// River noise further than this from 0 is outside the river
+const RIVER_WIDTH: f32 = 0.02;
+/// River noise closer than this to 0 is in the valley of the river, which is lowered towards the
+/// height of its banks
+const VALLEY_WIDTH: f32 = 0.08;
+/// Land in river valleys is lowered to at most this far above sea level
+const BANK_HEIGHT: f32 = 2.0;
+/// Depth of the water in the middle of a river
+const RIVER_DEPTH: f32 = 4.0;
+/// The width and length of the cells of the grid on which lakes are placed
+const LAKE_CELL_SIZE: i32 = 192;
+/// Percentage of cells which have a lake
+const LAKE_CHANCE: u32 = 40;
+const MIN_LAKE_RADIUS: i32 = 8;
+const MAX_LAKE_RADIUS: i32 = 24;
+/// Depth of the water in the middle of a lake
+const LAKE_DEPTH: f32 = 6.0;
+/// The water of a lake is at least this far above sea level
+const MIN_LAKE_HEIGHT: i32 = 3;
+/// The land is checked at this many points on each of the circles around a lake
+const RIM_SAMPLES: u32 = 16;
+/// Land this many blocks around a lake, and no higher than this above its water, is a bank
+const LAKE_BANK_WIDTH: f32 = 3.0;
+const LAKE_BANK_HEIGHT: f32 = 2.0;
+const LAKE_SALT: u32 = 0x1A4E;
+
+/// The height of the highest water block of a river or lake
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Water {
+    /// Rivers are at sea level
+    River(i32),
+    Lake(i32),
+}
+
+impl Water {
+    /// The height of the highest water block
+    pub fn level(&self) -> i32 {
+        match self {
+            Self::River(level) | Self::Lake(level) => *level,
+        }
+    }
+
+    /// The block under the water
+    pub fn bed_block(&self) -> Block {
+        match self {
+            Self::River(_) => Block::Gravel,
+            Self::Lake(_) => Block::Sand,
+        }
+    }
+}
+
+/// A land column once rivers and lakes have been carved into it
+#[derive(Clone, Copy, Debug)]
+pub struct WaterColumn {
+    /// Blocks below this height are land
+    pub land_height: f32,
+    /// The water above the land, if the column is in a river or lake
+    pub water: Option<Water>,
+    /// The land at the edge of a river or lake, which is covered with sand
+    pub is_bank: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Lake {
+    centre: IVec2,
+    radius: i32,
+    level: i32,
+}
+
+/// Lakes which reach into the area between the corners, inclusive
+pub fn lakes_near(sampler: &SurfaceSampler, min: IVec2, max: IVec2) -> Vec<Lake> {
+    let margin = IVec2::splat(MAX_LAKE_RADIUS + LAKE_BANK_WIDTH as i32);
+    let min_cell = (min - margin).div_euclid(IVec2::splat(LAKE_CELL_SIZE));
+    let max_cell = (max + margin).div_euclid(IVec2::splat(LAKE_CELL_SIZE));
+    let mut lakes = vec![];
+    for cell_x in min_cell.x..=max_cell.x {
+        for cell_z in min_cell.y..=max_cell.y {
+            if let Some(lake) = plan_lake(sampler, IVec2::new(cell_x, cell_z)) {
+                lakes.push(lake);
+            }
+        }
+    }
+    lakes
+}
+
+fn plan_lake(sampler: &SurfaceSampler, cell: IVec2) -> Option<Lake> {
+    let hash = mix_hash(sampler.seed ^ LAKE_SALT);
+    let hash = mix_hash(hash ^ cell.x as u32);
+    let hash = mix_hash(hash.wrapping_add(mix_hash(cell.y as u32)));
+    if nth_random(hash, 0) % 100 >= LAKE_CHANCE {
+        return None;
+    }
+    let radius = MIN_LAKE_RADIUS
+        + (nth_random(hash, 1) % (MAX_LAKE_RADIUS - MIN_LAKE_RADIUS + 1) as u32) as i32;
+    // Lakes are kept inside their cells so that they never overlap
+    let span = (LAKE_CELL_SIZE - 2 * MAX_LAKE_RADIUS) as u32;
+    let centre = cell * LAKE_CELL_SIZE
+        + IVec2::splat(MAX_LAKE_RADIUS)
+        + IVec2::new(
+            (nth_random(hash, 2) % span) as i32,
+            (nth_random(hash, 3) % span) as i32,
+        );
+    let level = sampler
+        .land_height_without_lakes(centre)?
+        .floor() as i32
+        - 1;
+    if level < sampler.config().sea_level + MIN_LAKE_HEIGHT {
+        return None;
+    }
+    // The water would spill out anywhere that the land around it is lower
+    for scale in [1.0, 1.25] {
+        for i in 0..RIM_SAMPLES {
+            let angle = i as f32 / RIM_SAMPLES as f32 * std::f32::consts::TAU;
+            let offset = Vec2::from_angle(angle) * radius as f32 * scale;
+            let rim = centre + offset.round().as_ivec2();
+            let rim_height = sampler.land_height_without_lakes(rim)?;
+            if rim_height < (level + 1) as f32 {
+                return None;
+            }
+        }
+    }
+    Some(Lake {
+        centre,
+        radius,
+        level,
+    })
+}
+
+/// Carves the rivers and the lakes into a land column, given the height of the land and the river
+/// noise in the column
+pub fn carve_column(
+    column: IVec2,
+    land_height: f32,
+    river_noise: f32,
+    lakes: &[Lake],
+    sea_level: i32,
+) -> WaterColumn {
+    let bank_height = sea_level as f32 + BANK_HEIGHT;
+    let mut land_height = carve_river_valley(land_height, river_noise, bank_height);
+    let mut water = None;
+    let mut is_bank = river_noise.abs() < VALLEY_WIDTH && land_height <= bank_height;
+    let river_t = 1.0 - river_noise.abs() / RIVER_WIDTH;
+    if river_t > 0.0 {
+        let bed = sea_level as f32 - RIVER_DEPTH * river_t;
+        land_height = land_height.min(bed);
+        water = Some(Water::River(sea_level));
+    }
+    for lake in lakes {
+        let distance = column.as_vec2().distance(lake.centre.as_vec2()) / lake.radius as f32;
+        if distance < 1.0 {
+            let bed = lake.level as f32 - (LAKE_DEPTH - 1.0) * (1.0 - distance * distance);
+            land_height = land_height.min(bed);
+            water = Some(Water::Lake(lake.level));
+        } else if distance < 1.0 + LAKE_BANK_WIDTH / lake.radius as f32
+            && land_height <= lake.level as f32 + 1.0 + LAKE_BANK_HEIGHT
+        {
+            is_bank = true;
+        }
+    }
+    WaterColumn {
+        land_height,
+        water,
+        is_bank: is_bank && water.is_none(),
+    }
+}
+
+/// Lowers land near a river towards the height of its banks, more so closer to the river
+fn carve_river_valley(land_height: f32, river_noise: f32, bank_height: f32) -> f32 {
+    let t = (1.0 - river_noise.abs() / VALLEY_WIDTH).clamp(0.0, 1.0);
+    let t = t * t * (3.0 - 2.0 * t);
+    land_height.lerp(land_height.min(bank_height), t)
+}
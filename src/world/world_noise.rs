@@ -6,12 +6,14 @@ use noise::{
 };
 use std::sync::Arc;
 
+use super::config::NoiseConfig;
+
 #[derive(Resource, Clone)]
 pub struct ContinentNoiseGenerator(pub Arc<StackedNoise>);
 
 impl ContinentNoiseGenerator {
-    pub fn new(seed: u32) -> Self {
-        let noise = StackedNoise::new(seed, 6, 1000.0);
+    pub fn new(seed: u32, config: &NoiseConfig) -> Self {
+        let noise = StackedNoise::new(seed, config.octaves, config.scale);
         Self(Arc::new(noise))
     }
 }
@@ -20,12 +22,46 @@ impl ContinentNoiseGenerator {
 pub struct HeightNoiseGenerator(pub Arc<StackedNoise>);
 
 impl HeightNoiseGenerator {
+    pub fn new(seed: u32, config: &NoiseConfig) -> Self {
+        let noise = StackedNoise::new(seed, config.octaves, config.scale);
+        Self(Arc::new(noise))
+    }
+}
+
+/// Only used by the floating islands preset
+#[derive(Resource, Clone)]
+pub struct IslandNoiseGenerator(pub Arc<StackedNoise>);
+
+impl IslandNoiseGenerator {
+    pub fn new(seed: u32) -> Self {
+        let noise = StackedNoise::new(seed ^ 0x15A4D, 3, 80.0);
+        Self(Arc::new(noise))
+    }
+}
+
+/// Rivers follow the lines where this noise is halfway between its lowest and highest values
+#[derive(Resource, Clone)]
+pub struct RiverNoiseGenerator(pub Arc<StackedNoise>);
+
+impl RiverNoiseGenerator {
     pub fn new(seed: u32) -> Self {
-        let noise = StackedNoise::new(seed, 3, 100.0);
+        let noise = StackedNoise::new(seed ^ 0x21FE5, 2, 500.0);
         Self(Arc::new(noise))
     }
 }
 
+/// Only used by the overhangs preset. Features are squashed vertically so that they make ledges
+/// rather than blobs.
+#[derive(Resource, Clone)]
+pub struct DensityNoiseGenerator(pub Arc<ScalePoint<StackedNoise>>);
+
+impl DensityNoiseGenerator {
+    pub fn new(seed: u32) -> Self {
+        let noise = StackedNoise::new(seed ^ 0xDE751, 3, 64.0);
+        Self(Arc::new(ScalePoint::new(noise).set_y_scale(2.0)))
+    }
+}
+
 #[derive(Resource, Clone)]
 pub struct WhiteNoise {
     permutation_table: PermutationTable,
@@ -195,7 +231,7 @@ impl ClimateNoise {
             ),
             humidity: Arc::new(
                 ScalePoint::new(Simplex::new(seed ^ 0xBABA))
-                    .set_scale(scale)
+                    .set_scale(scale.recip())
                     .into(),
             ),
         }